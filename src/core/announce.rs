@@ -0,0 +1,100 @@
+use crate::core::identity::RingIdentity;
+use anyhow::{Result, anyhow};
+use chacha20poly1305::{
+    XChaCha20Poly1305, Key, XNonce,
+    aead::{Aead, KeyInit, Payload}
+};
+use hkdf::Hkdf;
+use rand::{RngCore, thread_rng};
+use serde::{Serialize, Deserialize};
+use sha2::Sha256;
+use zeroize::Zeroizing;
+
+// Annuncio mDNS che non rivela nulla di stabile a chi non è nel ring:
+// - nome del servizio casuale, rigenerato a ogni periodo del tag;
+// - in chiaro solo il tag del ring (`RingIdentity::announce_tag`), che cambia anch'esso;
+// - tutto il resto (nome, device_id, preferenze) cifrato con una chiave del ring,
+//   legato al tag del periodo così che un annuncio copiato non valga in un altro.
+// Restano visibili, per forza di cose, l'indirizzo IP e la porta.
+
+/// Caratteri hex per proprietà TXT: una stringa TXT non supera i 255 byte
+const CHUNK_LEN: usize = 240;
+/// Limite ai pezzi letti da un annuncio, contro TXT gonfiati ad arte
+const MAX_CHUNKS: usize = 8;
+/// Caratteri del nome del dispositivo messi nell'annuncio (e nell'handshake)
+const MAX_NAME_CHARS: usize = 64;
+
+/// Dati del dispositivo nell'annuncio, leggibili solo dai membri del ring
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct AnnounceMeta {
+    pub device_id: String,
+    pub device_name: String,
+    /// Algoritmi di compressione supportati (`compress::supported_txt`)
+    pub zip: String,
+    pub os: String,
+    pub app: String,
+    /// Il dispositivo ha la selezione PRIMARY (solo Linux)
+    pub primary: bool,
+    /// Preferenza sulle immagini da ricevere (`imaging::preference_txt`)
+    pub img: Option<String>,
+}
+
+/// Il nome del dispositivo come lo vedono i peer: un annuncio mDNS ha poco spazio, ne basta l'inizio
+pub fn announced_name(name: &str) -> String {
+    name.chars().take(MAX_NAME_CHARS).collect()
+}
+
+/// Nome del servizio per un periodo: casuale, non collegabile a quello precedente
+pub fn random_instance_name() -> String {
+    let mut bytes = [0u8; 8];
+    thread_rng().fill_bytes(&mut bytes);
+    format!("rc-{}", hex::encode(bytes))
+}
+
+fn meta_cipher(ring: &RingIdentity) -> XChaCha20Poly1305 {
+    let hkdf = Hkdf::<Sha256>::new(None, &ring.shared_secret);
+    let mut key = Zeroizing::new([0u8; 32]);
+    hkdf.expand(b"rustclip_announce_meta_v1", key.as_mut())
+        .expect("32 byte sono una lunghezza valida per HKDF");
+    XChaCha20Poly1305::new(Key::from_slice(key.as_ref()))
+}
+
+/// Cifra i metadati per il periodo di `tag`: proprietà TXT `m0`, `m1`, ... da aggiungere all'annuncio
+pub fn seal_meta(ring: &RingIdentity, tag: &str, meta: &AnnounceMeta) -> Result<Vec<(String, String)>> {
+    let plain = bincode::serialize(meta)?;
+    let mut nonce = [0u8; 24];
+    thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = meta_cipher(ring)
+        .encrypt(XNonce::from_slice(&nonce), Payload { msg: &plain, aad: tag.as_bytes() })
+        .map_err(|e| anyhow!("Encryption error: {}", e))?;
+
+    let mut sealed = nonce.to_vec();
+    sealed.extend_from_slice(&ciphertext);
+    let encoded = hex::encode(sealed);
+    let chunks: Vec<(String, String)> = encoded.as_bytes()
+        .chunks(CHUNK_LEN)
+        .enumerate()
+        .map(|(i, chunk)| (format!("m{}", i), String::from_utf8_lossy(chunk).into_owned()))
+        .collect();
+    if chunks.len() > MAX_CHUNKS {
+        return Err(anyhow!("Metadati dell'annuncio troppo grandi"));
+    }
+    Ok(chunks)
+}
+
+/// Inverso di `seal_meta`; `property` legge una proprietà TXT dell'annuncio.
+/// None se i metadati mancano o non sono di questo ring e di questo tag.
+pub fn open_meta<'a>(ring: &RingIdentity, tag: &str, property: impl Fn(&str) -> Option<&'a str>) -> Option<AnnounceMeta> {
+    let encoded: String = (0..MAX_CHUNKS)
+        .map_while(|i| property(&format!("m{}", i)))
+        .collect();
+    let sealed = hex::decode(encoded).ok()?;
+    if sealed.len() < 24 {
+        return None;
+    }
+    let (nonce, ciphertext) = sealed.split_at(24);
+    let plain = meta_cipher(ring)
+        .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad: tag.as_bytes() })
+        .ok()?;
+    bincode::deserialize(&plain).ok()
+}
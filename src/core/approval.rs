@@ -0,0 +1,92 @@
+use crate::core::protocol::{ClipContent, ClipMeta};
+use crate::events::PendingClipInfo;
+use std::collections::VecDeque;
+use std::io::Cursor;
+use std::net::IpAddr;
+use std::time::{Duration, Instant};
+
+// Coda dei clip ricevuti in attesa che l'utente li accetti ("chiedi prima di incollare").
+// Nulla finisce nella clipboard finché il clip resta qui. Un clip con scadenza (`expire_secs`)
+// viene scartato se l'utente non lo accetta in tempo.
+
+/// Oltre questo numero i clip più vecchi vengono scartati
+pub const MAX_PENDING: usize = 20;
+const PREVIEW_CHARS: usize = 80;
+
+#[derive(Debug)]
+pub struct PendingClip {
+    pub id: u64,
+    /// Nome del peer (o IP se sconosciuto)
+    pub from: String,
+    pub sender: IpAddr,
+    pub received_at: String,
+    held_at: Instant,
+    pub content: ClipContent,
+    pub meta: ClipMeta,
+}
+
+#[derive(Debug, Default)]
+pub struct PendingQueue {
+    next_id: u64,
+    clips: VecDeque<PendingClip>,
+}
+
+impl PendingQueue {
+    /// Accoda un clip; ritorna il suo id e, se la coda era piena, il clip più vecchio scartato
+    pub fn push(&mut self, from: String, sender: IpAddr, content: ClipContent, meta: ClipMeta) -> (u64, Option<PendingClip>) {
+        self.next_id += 1;
+        let received_at = chrono::Local::now().format("%H:%M:%S").to_string();
+        self.clips.push_back(PendingClip { id: self.next_id, from, sender, received_at, held_at: Instant::now(), content, meta });
+        let dropped = (self.clips.len() > MAX_PENDING).then(|| self.clips.pop_front()).flatten();
+        (self.next_id, dropped)
+    }
+
+    /// Toglie il clip dalla coda (per accettarlo o rifiutarlo)
+    pub fn take(&mut self, id: u64) -> Option<PendingClip> {
+        let pos = self.clips.iter().position(|c| c.id == id)?;
+        self.clips.remove(pos)
+    }
+
+    /// Toglie e restituisce i clip la cui scadenza è passata a `now`
+    pub fn expire(&mut self, now: Instant) -> Vec<PendingClip> {
+        let (expired, kept): (Vec<_>, Vec<_>) = std::mem::take(&mut self.clips).into_iter().partition(|c| {
+            c.meta.expire_secs.is_some_and(|secs| now >= c.held_at + Duration::from_secs(secs as u64))
+        });
+        self.clips = kept.into();
+        expired
+    }
+
+    /// Vista per la UI: solo anteprime, mai il contenuto completo
+    pub fn infos(&self) -> Vec<PendingClipInfo> {
+        self.clips.iter().map(|c| PendingClipInfo {
+            id: c.id,
+            from: c.from.clone(),
+            received_at: c.received_at.clone(),
+            preview: preview(&c.content, &c.meta),
+        }).collect()
+    }
+}
+
+/// Anteprima breve: inizio del testo su una riga, dimensioni per le immagini.
+/// I clip sensibili non vengono mostrati.
+pub fn preview(content: &ClipContent, meta: &ClipMeta) -> String {
+    match content {
+        ClipContent::Text(_) if meta.sensitive => "🔒 ••••••••".to_string(),
+        ClipContent::Text(text) => {
+            let line: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            if line.chars().count() > PREVIEW_CHARS {
+                format!("{}…", line.chars().take(PREVIEW_CHARS).collect::<String>())
+            } else {
+                line
+            }
+        },
+        ClipContent::Image(bytes) => {
+            let size = image::io::Reader::new(Cursor::new(bytes)).with_guessed_format().ok()
+                .and_then(|r| r.into_dimensions().ok());
+            match size {
+                Some((w, h)) => format!("🖼️ {}×{} ({} KiB)", w, h, bytes.len() / 1024),
+                None => format!("🖼️ {} KiB", bytes.len() / 1024),
+            }
+        }
+    }
+}
@@ -1,91 +1,176 @@
 use crate::core::identity::RingIdentity;
 use crate::core::discovery::PeerMap;
 use crate::core::crypto::CryptoLayer;
-use crate::core::config::AppConfig;
-use crate::events::CoreEvent; // NUOVO
-use flume::Sender; // NUOVO
+use crate::core::config::{AppConfig, SharedConfig, AutoClearConfig, AutoClearMode, CompressionAlgorithm, PrimaryTarget, ApprovalPolicy};
+use crate::core::protocol::{self, ClipContent, ClipMeta, Selection};
+use crate::core::sensitive::{self, SensitiveFilter, Verdict};
+use crate::core::source;
+use crate::core::pipeline::{Pipeline, Direction};
+use crate::core::hooks::{self, HookContext, HookEvent, HookOutcome};
+use crate::core::imaging::{self, ImageSettings, RawImage};
+use crate::core::compress;
+use crate::core::throttle::{self, Debouncer, RateLimiter};
+use crate::core::approval::PendingQueue;
+use crate::core::handshake;
+use crate::core::announce;
+use crate::core::metrics::{Failure, Metrics, SharedMetrics};
+use crate::events::{CoreEvent, PeerInfo}; // NUOVO
+use flume::{Sender, Receiver}; // NUOVO
 use anyhow::Result;
+use tracing::{debug, info, warn, error};
 use arboard::{Clipboard, ImageData};
 use tokio::io::{AsyncReadExt, AsyncWriteExt}; 
 use tokio::net::{TcpListener, TcpStream};    
 use tokio::time::{sleep, Duration};
-use std::sync::{Arc, Mutex};
+use tokio::task::JoinHandle;
+use std::sync::{Arc, Mutex, Once};
 use std::sync::atomic::{AtomicBool, Ordering};
 use sha2::{Sha256, Digest};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::collections::hash_map::Entry;
 use std::borrow::Cow;
 use image::ImageEncoder;
 // RIMOSSO: use notify_rust::Notification;
 
 const MAX_PACKET_SIZE: usize = 50 * 1024 * 1024;
 
-#[derive(serde::Serialize, serde::Deserialize, Debug)]
-enum ClipContent {
-    Text(String),
-    Image(Vec<u8>), 
+type RecentHashes = Arc<Mutex<HashSet<String>>>;
+
+/// Un ring attivo: la sua chiave e i peer trovati dal suo discovery
+#[derive(Clone)]
+pub struct ActiveRing {
+    pub id: String,
+    pub crypto: Arc<CryptoLayer>,
+    pub peers: PeerMap,
 }
 
-type RecentHashes = Arc<Mutex<HashSet<String>>>;
+impl ActiveRing {
+    pub fn new(identity: &RingIdentity, peers: PeerMap) -> Self {
+        Self {
+            id: identity.discovery_id.clone(),
+            crypto: Arc::new(CryptoLayer::new(&identity.shared_secret)),
+            peers,
+        }
+    }
+}
 
+/// Stato condiviso dal monitor e dal broadcast
+struct SyncContext {
+    /// Il nostro device_id, scritto in ogni clip inviato
+    device_id: String,
+    rings: Vec<ActiveRing>,
+    recent_hashes: RecentHashes,
+    config: SharedConfig,
+    metrics: SharedMetrics,
+}
+
+/// Sincronizza la clipboard con i peer di tutti i ring attivi: un solo monitor e un solo listener
 pub async fn start_clipboard_sync(
-    identity: RingIdentity, 
-    peers: PeerMap,
-    config: AppConfig,
+    rings: Vec<ActiveRing>,
+    config: SharedConfig,
     global_pause: Arc<AtomicBool>,
-    tx_event: Option<Sender<CoreEvent>> // NUOVO PARAMS
+    rx_send_to: Receiver<Option<String>>,
+    rx_decisions: Receiver<(u64, bool)>,
+    tx_event: Option<Sender<CoreEvent>>, // NUOVO PARAMS
+    metrics: SharedMetrics,
 ) -> Result<()> {
-    let crypto = Arc::new(CryptoLayer::new(&identity.shared_secret));
     let recent_hashes: RecentHashes = Arc::new(Mutex::new(HashSet::new()));
     let busy_writing = Arc::new(AtomicBool::new(false));
+    let inbox = Inbox {
+        recent_hashes: recent_hashes.clone(),
+        busy_writing: busy_writing.clone(),
+        config: config.clone(),
+        tx_event,
+        pending: Arc::new(Mutex::new(PendingQueue::default())),
+        metrics: metrics.clone(),
+    };
+    // La coda riparte vuota a ogni (ri)avvio dei servizi
+    inbox.publish_pending();
 
-    let server_crypto = crypto.clone();
-    let server_hashes = recent_hashes.clone();
-    let server_busy = busy_writing.clone();
-    let server_config = config.clone();
-    let server_tx = tx_event.clone(); // Clone for server
-    
-    tokio::spawn(async move {
-        if let Err(e) = run_server(server_crypto, server_hashes, server_busy, server_config, server_tx).await {
-            eprintln!("❌ TCP Server Error: {}", e);
+    let server_rings = rings.clone();
+    let server_inbox = inbox.clone();
+    let server = async move {
+        if let Err(e) = run_server(server_rings, server_inbox).await {
+            error!("❌ TCP Server Error: {}", e);
         }
-    });
+        std::future::pending::<()>().await
+    };
 
-    run_monitor(crypto, peers, recent_hashes, busy_writing, global_pause, tx_event).await
+    let ctx = SyncContext { device_id: RingIdentity::get_derived_device_id(), rings, recent_hashes, config, metrics };
+    // Server e monitor vivono insieme: fermare la sincronizzazione libera anche la porta
+    tokio::select! {
+        _ = server => Ok(()),
+        res = run_monitor(ctx, busy_writing, global_pause, rx_send_to, inbox, rx_decisions) => res,
+    }
+}
+
+/// Invia la clipboard attuale ai peer indicati (CLI `push` e `send --to`), senza avviare la sincronizzazione.
+/// Ritorna quanti l'hanno ricevuta (0 anche se il clip è stato bloccato dai filtri).
+pub async fn send_current(rings: &[RingIdentity], recipients: Vec<PeerInfo>, config: AppConfig) -> Result<usize> {
+    let rings: Vec<ActiveRing> = rings.iter().map(|r| ActiveRing::new(r, Arc::new(dashmap::DashMap::new()))).collect();
+    for peer in recipients {
+        if let Some(ring) = rings.iter().find(|r| r.id == peer.ring_id) {
+            ring.peers.insert(peer.device_id.clone(), peer);
+        }
+    }
+    let ctx = SyncContext {
+        device_id: RingIdentity::get_derived_device_id(),
+        rings,
+        recent_hashes: Arc::new(Mutex::new(HashSet::new())),
+        config: Arc::new(std::sync::RwLock::new(config)),
+        metrics: Arc::new(Mutex::new(Metrics::default())),
+    };
+    let Some((_, hash, Some(content))) = tokio::task::spawn_blocking(read_clipboard).await? else {
+        anyhow::bail!("La clipboard è vuota");
+    };
+    let mut filter = None;
+    let mut delivered = 0;
+    for sending in dispatch(&ctx, &mut filter, hash, content, None).await {
+        if sending.await.unwrap_or(false) {
+            delivered += 1;
+        }
+    }
+    Ok(delivered)
 }
 
 async fn run_monitor(
-    crypto: Arc<CryptoLayer>, 
-    peers: PeerMap, 
-    recent_hashes: RecentHashes,
+    ctx: SyncContext,
     busy_writing: Arc<AtomicBool>,
     global_pause: Arc<AtomicBool>,
-    tx_event: Option<Sender<CoreEvent>> // ADDED
+    rx_send_to: Receiver<Option<String>>,
+    inbox: Inbox,
+    rx_decisions: Receiver<(u64, bool)>
 ) -> Result<()> {
-    println!("{}", t!("logs.monitor_active"));
-    if let Some(ref tx) = tx_event {
-        let _ = tx.send(CoreEvent::Log(crate::events::LogEntry::new(&t!("logs.monitor_active"))));
-    }
+    let mut filter: Option<SensitiveFilter> = None;
+
+    info!("{}", t!("logs.monitor_active"));
     
     // --- FIX STARTUP SYNC: Pre-fill hashes with current content ---
     let mut last_text_hash = String::new();
     let mut last_image_hash = String::new();
+    let mut last_primary_hash = String::new();
+    let mut primary_debounce: Debouncer<String> = Debouncer::new(Duration::from_millis(1000));
+    let mut clip_debounce: Debouncer<String> = Debouncer::new(Duration::ZERO);
 
     // Leggiamo lo stato attuale SENZA inviarlo
     if let Ok(mut cb) = Clipboard::new() {
         if let Ok(text) = cb.get_text() {
              if !text.is_empty() {
                  let h = hash_data(text.as_bytes());
-                 recent_hashes.lock().unwrap().insert(h.clone());
+                 ctx.recent_hashes.lock().unwrap().insert(h.clone());
                  last_text_hash = h;
-                 println!("{}", t!("logs.startup_ignore_text", hash = last_text_hash));
+                 debug!("{}", t!("logs.startup_ignore_text", hash = last_text_hash));
              }
         }
         if let Ok(img) = cb.get_image() {
              let raw = img.bytes.clone().into_owned(); // clone necessario perché get_image torna Cow/ImageData
              let h = hash_data(&raw);
-             recent_hashes.lock().unwrap().insert(h.clone());
+             ctx.recent_hashes.lock().unwrap().insert(h.clone());
              last_image_hash = h;
-             println!("{}", t!("logs.startup_ignore_image", hash = last_image_hash));
+             debug!("{}", t!("logs.startup_ignore_image", hash = last_image_hash));
+        }
+        if let Some(text) = primary::get_text(&mut cb) {
+            last_primary_hash = hash_data(text.as_bytes());
         }
     }
     // ---------------------------------------------------------------
@@ -93,205 +178,700 @@ async fn run_monitor(
     loop {
         sleep(Duration::from_millis(500)).await;
 
+        // Invii chiesti dall'utente (push o a un solo peer): valgono anche a sincronizzazione in pausa
+        while let Ok(target) = rx_send_to.try_recv() {
+            if let Some((_, hash, Some(content))) = tokio::task::spawn_blocking(read_clipboard).await? {
+                dispatch(&ctx, &mut filter, hash, content, target.as_deref()).await;
+            }
+        }
+        // Clip in attesa accettati o rifiutati dall'utente
+        while let Ok((id, accept)) = rx_decisions.try_recv() {
+            let inbox = inbox.clone();
+            tokio::spawn(async move { inbox.resolve(id, accept).await });
+        }
+        inbox.expire_pending();
+
         if global_pause.load(Ordering::Relaxed) || busy_writing.load(Ordering::Relaxed) {
             continue;
         }
 
-        let read_result = tokio::task::spawn_blocking(move || {
-             let mut clipboard = match Clipboard::new() { Ok(c) => c, Err(_) => return None };
-             if let Ok(text) = clipboard.get_text() {
-                 if !text.is_empty() {
-                     let hash = hash_data(text.as_bytes());
-                     return Some(("text", hash, Some(ClipContent::Text(text))));
-                 }
-             }
-             if let Ok(img) = clipboard.get_image() {
-                 let hash = hash_data(&img.bytes);
-                 let width = img.width; let height = img.height; let bytes = img.bytes.into_owned();
-                 return Some(("image", hash, Some(ClipContent::Image(encode_raw(width, height, bytes))))); 
-             }
-             None
-        }).await?;
+        let read_result = tokio::task::spawn_blocking(read_clipboard).await?;
+
+        // In una raffica di copie parte solo l'ultimo clip, quando smette di cambiare
+        let debounce_ms = ctx.config.read().unwrap().throttle.debounce_ms;
+        clip_debounce.set_window(Duration::from_millis(debounce_ms as u64));
+        let now = std::time::Instant::now();
+        let read_result = read_result.filter(|(_, hash, _)| {
+            clip_debounce.observe(hash.clone(), now);
+            clip_debounce.ready(now).is_some()
+        });
+
+        // Modalità push: niente invii automatici, si tiene solo traccia dell'ultimo clip visto
+        if ctx.config.read().unwrap().push.manual {
+            match read_result {
+                Some(("text", hash, _)) => last_text_hash = hash,
+                Some((_, hash, _)) => last_image_hash = hash,
+                None => {}
+            }
+            continue;
+        }
 
         if let Some((kind, hash, content_wrapper)) = read_result {
             match kind {
-                "text" => {
-                    if hash != last_text_hash {
-                        let is_new = { !recent_hashes.lock().unwrap().contains(&hash) };
-                        if is_new {
-                            println!("{}", t!("logs.text_detected"));
-                            last_text_hash = hash.clone();
-                            last_image_hash.clear();
-                            if let Some(c) = content_wrapper { broadcast(c, hash, &crypto, &peers, &recent_hashes, tx_event.clone()).await; }
-                        } else { last_text_hash = hash; }
-                    }
+                "text" if hash != last_text_hash => {
+                    let is_new = { !ctx.recent_hashes.lock().unwrap().contains(&hash) };
+                    if is_new {
+                        debug!("{}", t!("logs.text_detected"));
+                        last_text_hash = hash.clone();
+                        last_image_hash.clear();
+                        if let Some(content) = content_wrapper {
+                            dispatch(&ctx, &mut filter, hash, content, None).await;
+                        }
+                    } else { last_text_hash = hash; }
                 },
-                "image" => {
-                    if hash != last_image_hash {
-                        let is_new = { !recent_hashes.lock().unwrap().contains(&hash) };
-                        if is_new {
-                            println!("{}", t!("logs.image_detected"));
-                            if let Some(ClipContent::Image(raw_data_fake)) = content_wrapper {
-                                let (w, h, pixels) = decode_raw(raw_data_fake);
-                                let png_res = tokio::task::spawn_blocking(move || encode_to_png(w, h, &pixels)).await?;
-                                if let Ok(png_bytes) = png_res {
-                                    println!("   PNG: {} bytes", png_bytes.len());
-                                    last_image_hash = hash.clone();
-                                    last_text_hash.clear();
-                                    broadcast(ClipContent::Image(png_bytes), hash, &crypto, &peers, &recent_hashes, tx_event.clone()).await;
-                                }
-                            }
-                        } else { last_image_hash = hash; }
-                    }
+                "image" if hash != last_image_hash => {
+                    let is_new = { !ctx.recent_hashes.lock().unwrap().contains(&hash) };
+                    if is_new {
+                        debug!("{}", t!("logs.image_detected"));
+                        last_image_hash = hash.clone();
+                        last_text_hash.clear();
+                        if let Some(content) = content_wrapper {
+                            dispatch(&ctx, &mut filter, hash, content, None).await;
+                        }
+                    } else { last_image_hash = hash; }
                 },
                 _ => {}
             }
         }
+
+        poll_primary(&ctx, &mut filter, &mut primary_debounce, &mut last_primary_hash).await;
+    }
+}
+
+/// Selezione PRIMARY (Linux, se abilitata): inviata solo quando smette di cambiare
+async fn poll_primary(
+    ctx: &SyncContext,
+    filter: &mut Option<SensitiveFilter>,
+    debounce: &mut Debouncer<String>,
+    last_hash: &mut String
+) {
+    let settings = ctx.config.read().unwrap().primary_selection.clone();
+    if !settings.enabled {
+        return;
+    }
+    debounce.set_window(Duration::from_millis(settings.debounce_ms as u64));
+
+    let read = tokio::task::spawn_blocking(|| primary::get_text(&mut Clipboard::new().ok()?)).await;
+    let Some(text) = read.ok().flatten().filter(|t| !t.is_empty()) else { return };
+    let now = std::time::Instant::now();
+    debounce.observe(text, now);
+    let Some(text) = debounce.ready(now) else { return };
+
+    let hash = hash_data(text.as_bytes());
+    // Già inviato, o appena ricevuto da un peer (niente eco)
+    if hash == *last_hash || ctx.recent_hashes.lock().unwrap().contains(&hash) {
+        *last_hash = hash;
+        return;
+    }
+    *last_hash = hash.clone();
+    debug!("{}", t!("logs.primary_detected"));
+
+    let Some(mut meta) = screen_clip(filter, &ctx.config, Some(&text)).await else { return };
+    if settings.target == PrimaryTarget::Primary {
+        meta.selection = Selection::Primary;
+    }
+    if let Some(content) = transform(&ctx.config, ClipContent::Text(text), Direction::Outgoing) {
+        broadcast(content, meta, hash, None, ctx, None).await;
+    }
+}
+
+/// Legge la clipboard locale: (tipo, hash, contenuto); le immagini restano pixel grezzi
+fn read_clipboard() -> Option<(&'static str, String, Option<ClipContent>)> {
+    let mut clipboard = Clipboard::new().ok()?;
+    if let Ok(text) = clipboard.get_text() {
+        if !text.is_empty() {
+            let hash = hash_data(text.as_bytes());
+            return Some(("text", hash, Some(ClipContent::Text(text))));
+        }
+    }
+    if let Ok(img) = clipboard.get_image() {
+        let hash = hash_data(&img.bytes);
+        let width = img.width; let height = img.height; let bytes = img.bytes.into_owned();
+        return Some(("image", hash, Some(ClipContent::Image(encode_raw(width, height, bytes)))));
+    }
+    None
+}
+
+/// Filtri, trasformazioni e codifica delle immagini, poi l'invio: a tutti i peer o solo a `target`.
+/// Ritorna gli invii in corso (true = consegnato), per chi deve aspettarli.
+async fn dispatch(
+    ctx: &SyncContext,
+    filter: &mut Option<SensitiveFilter>,
+    hash: String,
+    content: ClipContent,
+    target: Option<&str>
+) -> Vec<JoinHandle<bool>> {
+    match content {
+        ClipContent::Text(text) => {
+            let Some(meta) = screen_clip(filter, &ctx.config, Some(&text)).await else { return Vec::new() };
+            match transform(&ctx.config, ClipContent::Text(text), Direction::Outgoing) {
+                Some(content) => broadcast(content, meta, hash, None, ctx, target).await,
+                None => Vec::new(),
+            }
+        },
+        ClipContent::Image(raw_data_fake) => {
+            let Some(meta) = screen_clip(filter, &ctx.config, None).await else { return Vec::new() };
+            let (w, h, pixels) = decode_raw(raw_data_fake);
+            let raw = Arc::new(RawImage { width: w as u32, height: h as u32, rgba: pixels });
+            let settings = ImageSettings::from_config(&ctx.config.read().unwrap().image);
+            let source = raw.clone();
+            let encoded = tokio::task::spawn_blocking(move || imaging::encode(&source, &settings)).await;
+            match encoded {
+                Ok(Ok(bytes)) => {
+                    debug!("   {}: {} bytes", imaging::codec_name(settings.codec), bytes.len());
+                    match transform(&ctx.config, ClipContent::Image(bytes), Direction::Outgoing) {
+                        Some(content) => broadcast(content, meta, hash, Some(raw), ctx, target).await,
+                        None => Vec::new(),
+                    }
+                },
+                Ok(Err(e)) => {
+                    warn!("{}", t!("logs.image_encode_failed", err = e));
+                    Vec::new()
+                },
+                Err(_) => Vec::new(),
+            }
+        }
     }
 }
 
+/// Ring a cui appartiene un frame in arrivo (quello la cui chiave lo decifra) e il clip in chiaro
+pub fn open_for_ring<'a>(rings: &'a [ActiveRing], header: [u8; 4], body: &[u8]) -> Option<(&'a ActiveRing, Vec<u8>)> {
+    rings.iter().find_map(|r| protocol::open_frame(header, body, &r.crypto).ok().map(|clip| (r, clip)))
+}
+
+/// Listener unico per tutti i ring: il ring di un frame è quello la cui chiave lo decifra
 async fn run_server(
-    crypto: Arc<CryptoLayer>, 
-    recent_hashes: RecentHashes,
-    busy_writing: Arc<AtomicBool>,
-    config: AppConfig,
-    tx_event: Option<Sender<CoreEvent>>
+    rings: Vec<ActiveRing>,
+    inbox: Inbox
 ) -> Result<()> {
-    let listener = TcpListener::bind("0.0.0.0:5566").await?;
+    // Dopo un riavvio dei servizi il listener precedente può impiegare un attimo a chiudersi
+    let mut attempts = 0;
+    let listener = loop {
+        match TcpListener::bind("0.0.0.0:5566").await {
+            Ok(listener) => break listener,
+            Err(_) if attempts < 10 => {
+                attempts += 1;
+                sleep(Duration::from_millis(200)).await;
+            },
+            Err(e) => return Err(e.into()),
+        }
+    };
+    // Per indirizzo prima di decifrare (solo contro le raffiche), per mittente dopo
+    let ip_limiter: Arc<Mutex<RateLimiter<std::net::IpAddr>>> = Arc::new(Mutex::new(RateLimiter::new(0, Duration::from_secs(60))));
+    let peer_limiter: Arc<Mutex<RateLimiter<String>>> = Arc::new(Mutex::new(RateLimiter::new(0, Duration::from_secs(60))));
+    let my_device_id: Arc<str> = RingIdentity::get_derived_device_id().into();
     
     loop {
-        let (mut socket, _) = listener.accept().await?;
-        let crypto_ref = crypto.clone();
-        let hashes_ref = recent_hashes.clone();
-        let busy_ref = busy_writing.clone();
-        let config_ref = config.clone();
-        let tx_ref = tx_event.clone(); 
+        let (mut socket, sender) = listener.accept().await?;
+        let config = inbox.config.clone();
+        let rings = rings.clone();
+        let inbox = inbox.clone();
+        let ip_limiter = ip_limiter.clone();
+        let peer_limiter = peer_limiter.clone();
+        let my_device_id = my_device_id.clone();
+        let metrics = inbox.metrics.clone();
 
         tokio::spawn(async move {
-            let mut len_buf = [0u8; 4];
-            if socket.read_exact(&mut len_buf).await.is_err() { return; }
-            let len = u32::from_be_bytes(len_buf) as usize;
-            if len > MAX_PACKET_SIZE { return; }
+            let mut header = [0u8; 4];
+            if socket.read_exact(&mut header).await.is_err() { return; }
+            let Ok((len, _)) = protocol::parse_frame_header(header) else { return };
+
+            // Handshake: chiunque può chiederci chi siamo, ma risponde solo chi ha la chiave del ring
+            if protocol::is_hello(header) {
+                if len > handshake::MAX_HELLO_SIZE { return; }
+                let mut buf = vec![0u8; len];
+                if socket.read_exact(&mut buf).await.is_err() { return; }
+                let name = announce::announced_name(&config.read().unwrap().device_name);
+                if let Some(reply) = rings.iter().find_map(|r| handshake::answer(&buf, &r.crypto, &my_device_id, &name).ok()) {
+                    let _ = socket.write_all(&reply).await;
+                }
+                return;
+            }
+
+            // Limite largo per indirizzo: più peer possono stare dietro lo stesso IP (NAT)
+            let limit = config.read().unwrap().throttle.max_incoming_per_minute;
+            {
+                let mut limiter = ip_limiter.lock().unwrap();
+                limiter.set_limit(throttle::address_limit(limit));
+                if !limiter.allow(sender.ip(), std::time::Instant::now()) {
+                    info!("{}", t!("logs.rate_limited", ip = sender.ip()));
+                    metrics.lock().unwrap().record_failure(Failure::RateLimited);
+                    return;
+                }
+            }
+            if len > MAX_PACKET_SIZE {
+                metrics.lock().unwrap().record_failure(Failure::Incomplete);
+                return;
+            }
 
             let mut buf = vec![0u8; len];
-            if socket.read_exact(&mut buf).await.is_err() { return; }
-
-            if let Ok(decrypted) = crypto_ref.decrypt(&buf) {
-                if let Ok(content) = bincode::deserialize::<ClipContent>(&decrypted) {
-                    
-                    busy_ref.store(true, Ordering::Relaxed);
-                    
-                    let _ = tokio::task::spawn_blocking(move || {
-                        std::thread::sleep(std::time::Duration::from_millis(100));
-
-                        match Clipboard::new() {
-                            Ok(mut cb) => {
-                                match content {
-                                    ClipContent::Text(text) => {
-                                        let hash = hash_data(text.as_bytes());
-                                        hashes_ref.lock().unwrap().insert(hash);
-                                        // Take substring for log if too long
-                                        let short_text = if text.len() > 20 { format!("{}...", &text[..20]) } else { text.clone() };
-                                        println!("{}", t!("logs.rx_text", text = short_text));
-                                        let _ = cb.set_text(text);
-                                        if config_ref.notifications_enabled {
-                                            if let Some(tx) = tx_ref {
-                                                let _ = tx.send(CoreEvent::Notify { 
-                                                    title: t!("notify.title").to_string(), 
-                                                    body: t!("notify.body_text").to_string() 
-                                                });
-                                            }
-                                        }
-                                    },
-                                    ClipContent::Image(png_bytes) => {
-                                        println!("{}", t!("logs.rx_image", size = png_bytes.len()));
-                                        if let Ok(image) = image::load_from_memory(&png_bytes) {
-                                            let w = image.width() as usize;
-                                            let h = image.height() as usize;
-                                            let raw = image.to_rgba8().into_raw();
-                                            let hash = hash_data(&raw);
-                                            hashes_ref.lock().unwrap().insert(hash);
-                                            let img_data = ImageData { width: w, height: h, bytes: Cow::from(raw) };
-                                            if let Err(e) = cb.set_image(img_data) {
-                                                eprintln!("{}", t!("logs.err_write_clip", err = e));
-                                            } else {
-                                                println!("{}", t!("logs.img_pasted"));
-                                                if config_ref.notifications_enabled {
-                                                    if let Some(tx) = tx_ref {
-                                                        let _ = tx.send(CoreEvent::Notify { 
-                                                            title: t!("notify.title").to_string(), 
-                                                            body: t!("notify.body_image").to_string() 
-                                                        });
-                                                    }
-                                                }
-                                            }
-                                        }
+            if socket.read_exact(&mut buf).await.is_err() {
+                metrics.lock().unwrap().record_failure(Failure::Incomplete);
+                return;
+            }
+
+            // Nessuna chiave lo apre: ring sbagliato, frame manomesso o timestamp fuori finestra
+            let Some((ring, decrypted)) = open_for_ring(&rings, header, &buf) else {
+                metrics.lock().unwrap().record_decrypt_rejected();
+                return;
+            };
+            let Ok((content, meta)) = protocol::decode_clip(&decrypted) else {
+                metrics.lock().unwrap().record_failure(Failure::Malformed);
+                return;
+            };
+
+            // Il mittente è il device_id scritto dentro il frame cifrato, non l'IP da cui arriva
+            // (None = peer di una versione che non lo invia)
+            let sender_id = meta.sender.clone();
+            // Nome dalla PeerMap del ring; un mittente che non conosciamo compare col suo IP
+            let name = sender_id.as_ref()
+                .and_then(|id| ring.peers.get(id).map(|p| p.name.clone()))
+                .unwrap_or_else(|| sender.ip().to_string());
+            let key = sender_id.clone().unwrap_or_else(|| sender.ip().to_string());
+
+            // Un peer che invia a raffica non deve poter sommergere la clipboard
+            {
+                let mut limiter = peer_limiter.lock().unwrap();
+                limiter.set_limit(limit);
+                if !limiter.allow(key.clone(), std::time::Instant::now()) {
+                    info!("{}", t!("logs.rate_limited_peer", name = name));
+                    metrics.lock().unwrap().record_failure(Failure::RateLimited);
+                    return;
+                }
+            }
+            if !config.read().unwrap().direction.receives_from(sender_id.as_deref()) {
+                info!("{}", t!("logs.direction_rejected", ip = sender.ip()));
+                metrics.lock().unwrap().record_failure(Failure::Direction);
+                return;
+            }
+            metrics.lock().unwrap().record_received(&key, &name, header.len() + len);
+
+            let Some(content) = transform(&config, content, Direction::Incoming) else {
+                metrics.lock().unwrap().record_failure(Failure::Dropped);
+                return;
+            };
+            let policy = config.read().unwrap().approval.for_peer(sender_id.as_deref());
+            match policy {
+                ApprovalPolicy::Auto => deliver(&inbox, content, meta, sender.ip()).await,
+                ApprovalPolicy::Ask => inbox.hold(name, sender.ip(), content, meta),
+            }
+        });
+    }
+}
+
+/// Stato condiviso dal server TCP e dalla coda di approvazione
+#[derive(Clone)]
+struct Inbox {
+    recent_hashes: RecentHashes,
+    busy_writing: Arc<AtomicBool>,
+    config: SharedConfig,
+    tx_event: Option<Sender<CoreEvent>>,
+    pending: Arc<Mutex<PendingQueue>>,
+    metrics: SharedMetrics,
+}
+
+impl Inbox {
+    /// Mette il clip in attesa di approvazione e avvisa l'utente
+    fn hold(&self, from: String, sender: std::net::IpAddr, content: ClipContent, meta: ClipMeta) {
+        let (_, dropped) = self.pending.lock().unwrap().push(from.clone(), sender, content, meta);
+        if let Some(old) = dropped {
+            warn!("{}", t!("logs.pending_dropped", name = old.from));
+            self.metrics.lock().unwrap().record_failure(Failure::Dropped);
+        }
+        info!("{}", t!("logs.pending_held", name = from));
+        self.publish_pending();
+        if self.config.read().unwrap().notifications_enabled {
+            if let Some(tx) = &self.tx_event {
+                let _ = tx.send(CoreEvent::Notify {
+                    title: t!("notify.title").to_string(),
+                    body: t!("notify.body_pending", name = from).to_string()
+                });
+            }
+        }
+    }
+
+    /// Accetta (scrive nella clipboard) o scarta un clip in attesa
+    async fn resolve(&self, id: u64, accept: bool) {
+        let clip = self.pending.lock().unwrap().take(id);
+        self.publish_pending();
+        match clip {
+            Some(clip) if accept => deliver(self, clip.content, clip.meta, clip.sender).await,
+            Some(clip) => {
+                info!("{}", t!("logs.pending_rejected", name = clip.from));
+                self.metrics.lock().unwrap().record_failure(Failure::Dropped);
+            },
+            None => {}
+        }
+    }
+
+    /// Scarta i clip in attesa oltre la loro scadenza, come se fossero stati rifiutati
+    fn expire_pending(&self) {
+        let expired = self.pending.lock().unwrap().expire(std::time::Instant::now());
+        if expired.is_empty() {
+            return;
+        }
+        for clip in &expired {
+            info!("{}", t!("logs.pending_expired", name = clip.from));
+            self.metrics.lock().unwrap().record_failure(Failure::Dropped);
+        }
+        self.publish_pending();
+    }
+
+    fn publish_pending(&self) {
+        if let Some(tx) = &self.tx_event {
+            let _ = tx.send(CoreEvent::PendingClips(self.pending.lock().unwrap().infos()));
+        }
+    }
+}
+
+/// Hook di ricezione e scrittura nella clipboard (o nella PRIMARY) di un clip ricevuto
+async fn deliver(inbox: &Inbox, content: ClipContent, meta: ClipMeta, sender: std::net::IpAddr) {
+    let Some(content) = apply_hook(&inbox.config, content, &meta, HookEvent::Receive, vec![sender.to_string()]).await else {
+        inbox.metrics.lock().unwrap().record_failure(Failure::Dropped);
+        return;
+    };
+    let hashes = inbox.recent_hashes.clone();
+    let busy = inbox.busy_writing.clone();
+    let tx_ref = inbox.tx_event.clone();
+
+    busy.store(true, Ordering::Relaxed);
+    let (notifications_enabled, clear_secs) = {
+        let cfg = inbox.config.read().unwrap();
+        (cfg.notifications_enabled, clear_delay(&cfg.auto_clear, &meta))
+    };
+    let selection = meta.selection;
+    
+    let written = tokio::task::spawn_blocking(move || {
+        let mut written = None;
+        std::thread::sleep(std::time::Duration::from_millis(100));
+
+        match Clipboard::new() {
+            Ok(mut cb) => {
+                match content {
+                    ClipContent::Text(text) => {
+                        let hash = hash_data(text.as_bytes());
+                        hashes.lock().unwrap().insert(hash.clone());
+                        written = Some(hash);
+                        // Il contenuto non finisce nei log (che vanno anche su file): solo la lunghezza
+                        debug!("{}", t!("logs.rx_text", chars = text.chars().count()));
+                        let _ = match selection {
+                            Selection::Primary => primary::set_text(&mut cb, text),
+                            Selection::Clipboard => cb.set_text(text),
+                        };
+                        // La PRIMARY cambia a ogni selezione: niente notifiche
+                        if notifications_enabled && selection == Selection::Clipboard {
+                            if let Some(tx) = tx_ref {
+                                let _ = tx.send(CoreEvent::Notify { 
+                                    title: t!("notify.title").to_string(), 
+                                    body: t!("notify.body_text").to_string() 
+                                });
+                            }
+                        }
+                    },
+                    ClipContent::Image(bytes) => {
+                        debug!("{}", t!("logs.rx_image", size = bytes.len()));
+                        if let Ok(image) = image::load_from_memory(&bytes) {
+                            let w = image.width() as usize;
+                            let h = image.height() as usize;
+                            let raw = image.to_rgba8().into_raw();
+                            let hash = hash_data(&raw);
+                            hashes.lock().unwrap().insert(hash.clone());
+                            let img_data = ImageData { width: w, height: h, bytes: Cow::from(raw) };
+                            if let Err(e) = cb.set_image(img_data) {
+                                error!("{}", t!("logs.err_write_clip", err = e));
+                            } else {
+                                debug!("{}", t!("logs.img_pasted"));
+                                written = Some(hash);
+                                if notifications_enabled {
+                                    if let Some(tx) = tx_ref {
+                                        let _ = tx.send(CoreEvent::Notify { 
+                                            title: t!("notify.title").to_string(), 
+                                            body: t!("notify.body_image").to_string() 
+                                        });
                                     }
                                 }
-                            },
-                            Err(e) => eprintln!("{}", t!("logs.err_open_clip", err = e)),
+                            }
                         }
-                        
-                        std::thread::sleep(std::time::Duration::from_millis(500));
-                        busy_ref.store(false, Ordering::Relaxed);
-                    }).await;
+                    }
                 }
-            }
-        });
+            },
+            Err(e) => error!("{}", t!("logs.err_open_clip", err = e)),
+        }
+        
+        std::thread::sleep(std::time::Duration::from_millis(500));
+        busy.store(false, Ordering::Relaxed);
+        written
+    }).await;
+
+    // Scadenza chiesta dal mittente o impostata localmente
+    if let (Ok(Some(hash)), Some(secs)) = (written, clear_secs) {
+        clear_after(hash, secs, selection);
     }
 }
 
 // --- UTILS ---
 
+/// Avviso sul blocco dei clip sensibili, mostrato al primo blocco dopo l'avvio
+static BLOCK_NOTICE: Once = Once::new();
+
+/// Applica le regole per applicazione e il filtro dei contenuti sensibili.
+/// None = il clip non va inviato.
+async fn screen_clip(
+    filter: &mut Option<SensitiveFilter>,
+    config: &SharedConfig,
+    text: Option<&str>,
+) -> Option<ClipMeta> {
+    let rules = config.read().unwrap().app_rules.clone();
+    if !rules.deny.is_empty() || !rules.allow.is_empty() {
+        let app = tokio::task::spawn_blocking(source::current_source_app).await.ok().flatten();
+        if !source::is_allowed(&rules, app.as_ref()) {
+            let name = app.as_ref().map(|a| a.display_name().to_string()).unwrap_or_else(|| "?".into());
+            info!("{}", t!("logs.app_blocked", app = name));
+            return None;
+        }
+    }
+
+    // Ricompiliamo le regex solo se la config è cambiata
+    let current = config.read().unwrap().sensitive.clone();
+    if filter.as_ref().map(|f| f.config() != &current).unwrap_or(true) {
+        let (compiled, invalid) = SensitiveFilter::new(&current);
+        for pattern in invalid {
+            warn!("{}", t!("logs.sensitive_bad_pattern", pattern = pattern));
+        }
+        *filter = Some(compiled);
+    }
+    let filter = filter.as_ref()?;
+
+    let hinted = current.platform_hints
+        && tokio::task::spawn_blocking(sensitive::platform_hint).await.unwrap_or(false);
+
+    match filter.check(text, hinted) {
+        Verdict::Allow => Some(ClipMeta::default()),
+        Verdict::Block { reason } => {
+            info!("{}", t!("logs.sensitive_blocked", reason = reason));
+            // Il blocco è il default anche per chi aggiorna: una volta per avvio spieghiamo perché
+            BLOCK_NOTICE.call_once(|| warn!("{}", t!("logs.sensitive_block_notice")));
+            None
+        },
+        Verdict::Expire { secs, reason } => {
+            info!("{}", t!("logs.sensitive_expire", reason = reason, secs = secs));
+            Some(ClipMeta { expire_secs: Some(secs), sensitive: true, ..ClipMeta::default() })
+        }
+    }
+}
+
+/// Passa il clip nella pipeline configurata. None = scartato da uno stage.
+fn transform(config: &SharedConfig, content: ClipContent, direction: Direction) -> Option<ClipContent> {
+    let pipeline = Pipeline::from_config(&config.read().unwrap().pipeline);
+    match pipeline.run(content, direction) {
+        Ok(content) => Some(content),
+        Err(stage) => {
+            info!("{}", t!("logs.pipeline_dropped", stage = stage));
+            None
+        }
+    }
+}
+
+/// Esegue il hook utente dell'evento, se configurato. None = veto dello script.
+/// Un hook che fallisce o scade viene segnalato ma non blocca la sincronizzazione.
+async fn apply_hook(
+    config: &SharedConfig,
+    content: ClipContent,
+    meta: &ClipMeta,
+    event: HookEvent,
+    peers: Vec<String>,
+) -> Option<ClipContent> {
+    let (hook, timeout, device_name) = {
+        let cfg = config.read().unwrap();
+        let hook = match event {
+            HookEvent::Send => cfg.hooks.on_send.clone(),
+            HookEvent::Receive => cfg.hooks.on_receive.clone(),
+        };
+        (hook, Duration::from_secs(cfg.hooks.timeout_secs.max(1) as u64), cfg.device_name.clone())
+    };
+    if hook.command.trim().is_empty() {
+        return Some(content);
+    }
+
+    let ctx = HookContext { event, device_name, peers, sensitive: meta.sensitive, expire_secs: meta.expire_secs };
+    let (result, content) = tokio::task::spawn_blocking(move || {
+        (hooks::run_hook(&hook, timeout, &content, &ctx), content)
+    }).await.ok()?;
+
+    match result {
+        Ok(HookOutcome::Keep) => Some(content),
+        Ok(HookOutcome::Replace(text)) => Some(ClipContent::Text(text)),
+        Ok(HookOutcome::Veto(code)) => {
+            info!("{}", t!("logs.hook_veto", code = code));
+            None
+        },
+        Err(e) => {
+            warn!("{}", t!("logs.hook_failed", err = e));
+            Some(content)
+        }
+    }
+}
+
+/// Dopo quanti secondi cancellare un clip ricevuto (None = mai).
+/// Vince il termine più breve tra quello del mittente e quello locale.
+pub fn clear_delay(auto_clear: &AutoClearConfig, meta: &ClipMeta) -> Option<u32> {
+    let local = match auto_clear.mode {
+        AutoClearMode::All => Some(auto_clear.secs),
+        AutoClearMode::Sensitive if meta.sensitive => Some(auto_clear.secs),
+        _ => None,
+    };
+    match (meta.expire_secs, local) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Dopo `secs` secondi svuota la clipboard (o la PRIMARY), ma solo se contiene ancora il clip indicato
+fn clear_after(hash: String, secs: u32, selection: Selection) {
+    tokio::spawn(async move {
+        sleep(Duration::from_secs(secs as u64)).await;
+        let cleared = tokio::task::spawn_blocking(move || {
+            let mut cb = Clipboard::new().ok()?;
+            let current = match selection {
+                Selection::Primary => hash_data(primary::get_text(&mut cb)?.as_bytes()),
+                Selection::Clipboard => match cb.get_text() {
+                    Ok(text) if !text.is_empty() => hash_data(text.as_bytes()),
+                    _ => hash_data(&cb.get_image().ok()?.bytes),
+                },
+            };
+            (current == hash).then(|| match selection {
+                Selection::Primary => primary::clear(&mut cb),
+                Selection::Clipboard => cb.clear().is_ok(),
+            })
+        }).await.ok().flatten().unwrap_or(false);
+        if cleared {
+            info!("{}", t!("logs.clip_expired"));
+        }
+    });
+}
+
+/// Invia il clip ai peer verso cui il verso configurato lo consente (solo a `target`, se indicato).
+/// Per le immagini `source` sono i pixel originali: ai peer che hanno annunciato un formato
+/// diverso dal nostro l'immagine viene ricodificata.
 async fn broadcast(
     content: ClipContent, 
+    meta: ClipMeta,
     hash: String, 
-    crypto: &CryptoLayer, 
-    peers: &PeerMap, 
-    hashes: &RecentHashes,
-    tx_event: Option<Sender<CoreEvent>>
-) {
-    hashes.lock().unwrap().insert(hash);
-    let raw = match bincode::serialize(&content) { Ok(r) => r, Err(_) => return };
-    let enc = match crypto.encrypt(&raw) { Ok(e) => e, Err(_) => return };
+    source: Option<Arc<RawImage>>,
+    ctx: &SyncContext,
+    target: Option<&str>
+) -> Vec<JoinHandle<bool>> {
+    let SyncContext { device_id: my_device_id, rings, recent_hashes, config, metrics } = ctx;
+    let mut sending = Vec::new();
+    recent_hashes.lock().unwrap().insert(hash);
+    let direction = config.read().unwrap().direction.clone();
+    // Copia delle PeerMap: ricodifica e compressione sono asincrone e non vanno fatte tenendo lock sulle mappe.
+    // Un dispositivo presente in più ring riceve il clip una volta sola, dal primo ring.
+    let mut recipients: Vec<(&ActiveRing, String, PeerInfo)> = Vec::new();
+    for ring in rings {
+        for p in ring.peers.iter() {
+            if target.is_none_or(|t| t == p.key()) && direction.sends_to(p.key())
+                && !recipients.iter().any(|(_, id, _)| id == p.key()) {
+                recipients.push((ring, p.key().clone(), p.value().clone()));
+            }
+        }
+    }
+    if let (Some(device_id), true) = (target, recipients.is_empty()) {
+        warn!("{}", t!("logs.target_unavailable", id = device_id));
+        return sending;
+    }
+    if !direction.global.sends() {
+        return sending;
+    }
+    let targets: Vec<String> = recipients.iter().map(|(_, _, p)| p.name.clone()).collect();
+    // Il hook decide una volta per tutti i peer; se riscrive il clip in testo non ci sono più varianti immagine
+    let Some(content) = apply_hook(config, content, &meta, HookEvent::Send, targets).await else { return sending };
+    let meta = ClipMeta { sender: Some(my_device_id.clone()), ..meta };
+    let raw = match protocol::encode_clip(&content, &meta) { Ok(r) => r, Err(_) => return sending };
+
+    let (local_settings, compression) = {
+        let cfg = config.read().unwrap();
+        (ImageSettings::from_config(&cfg.image), cfg.compression.clone())
+    };
+    // Un frame per ogni combinazione (ring, formato immagine, compressione) richiesta dai peer
+    let mut frames: HashMap<(&str, ImageSettings, Option<CompressionAlgorithm>), Option<Vec<u8>>> = HashMap::new();
     
-    // Iteriamo sulla PeerMap (key=device_id, value=PeerInfo)
-    for item in peers.iter() {
-        let peer_info = item.value().clone();
-        let device_id = item.key().clone();
-        
+    for (ring, device_id, peer_info) in recipients {
+        // La PRIMARY va solo a chi la può scrivere
+        if meta.selection == Selection::Primary && !peer_info.primary_selection {
+            continue;
+        }
+        let is_image = matches!(content, ClipContent::Image(_)) && source.is_some();
+        let settings = if is_image { local_settings.for_peer(peer_info.image_pref.as_ref()) } else { local_settings };
+        let algorithm = compress::negotiate(&compression, &peer_info.compression);
+
+        let key = (ring.id.as_str(), settings, algorithm);
+        if let Entry::Vacant(slot) = frames.entry(key) {
+            let clip = match &source {
+                Some(pixels) if is_image && settings != local_settings => encode_variant(pixels.clone(), settings, &meta, config).await,
+                _ => Some(raw.clone()),
+            };
+            if clip.is_none() {
+                warn!("{}", t!("logs.image_variant_failed", name = peer_info.name));
+            }
+            let config = compression.clone();
+            let crypto = ring.crypto.clone();
+            let frame = match clip {
+                Some(clip) => tokio::task::spawn_blocking(move || protocol::seal_frame(&clip, algorithm, &config, &crypto)).await.ok().and_then(|f| f.ok()),
+                None => None,
+            };
+            slot.insert(frame);
+        }
+        let Some(data) = frames[&key].clone() else {
+            metrics.lock().unwrap().record_failure(Failure::Encode);
+            continue;
+        };
         let addr = peer_info.ip;
-        let data = enc.clone();
-        
-        let peers_ref = peers.clone(); 
         
-        let tx_clone = tx_event.clone(); 
+        let peers_ref = ring.peers.clone(); 
+        let metrics = metrics.clone();
         
-        tokio::spawn(async move {
+        sending.push(tokio::spawn(async move {
+            let bytes = data.len();
+            let started = std::time::Instant::now();
             // Se fallisce l'invio, rimuoviamo il peer
-            if let Err(_) = send_data(addr, data).await { 
-                let msg = t!("logs.conn_failed", name = peer_info.name, id = device_id).to_string();
-                println!("{}", msg);
-                if let Some(tx) = &tx_clone {
-                    let _ = tx.send(CoreEvent::Log(crate::events::LogEntry::new(&msg)));
-                }
+            if send_data(addr, data).await.is_err() {
+                warn!("{}", t!("logs.conn_failed", name = peer_info.name, id = device_id));
+                metrics.lock().unwrap().record_failure(Failure::Connect);
                 // Rimozione immediata per evitare timeout successivi
                 peers_ref.remove(&device_id);
+                false
             } else { 
-                let msg = t!("logs.sent_to", name = peer_info.name).to_string();
-                println!("{}", msg); 
-                if let Some(tx) = &tx_clone {
-                    let _ = tx.send(CoreEvent::Log(crate::events::LogEntry::new(&msg)));
-                }
+                info!("{}", t!("logs.sent_to", name = peer_info.name));
+                metrics.lock().unwrap().record_sent(&device_id, &peer_info.name, bytes, started.elapsed());
+                true
             }
-        });
+        }));
     }
+    sending
+}
+
+/// Ricodifica l'immagine con le impostazioni chieste da un peer.
+/// La variante passa dalla stessa pipeline in uscita dell'originale, come se fosse stata catturata così.
+async fn encode_variant(pixels: Arc<RawImage>, settings: ImageSettings, meta: &ClipMeta, config: &SharedConfig) -> Option<Vec<u8>> {
+    let bytes = tokio::task::spawn_blocking(move || imaging::encode(&pixels, &settings)).await.ok()?.ok()?;
+    let content = transform(config, ClipContent::Image(bytes), Direction::Outgoing)?;
+    protocol::encode_clip(&content, meta).ok()
 }
 
 async fn send_data(addr: std::net::SocketAddr, data: Vec<u8>) -> Result<()> {
     let mut stream = tokio::time::timeout(Duration::from_secs(5), TcpStream::connect(addr)).await??;
-    let len = data.len() as u32;
-    stream.write_all(&len.to_be_bytes()).await?;
+    // `data` è un frame completo (header + corpo cifrato)
     stream.write_all(&data).await?;
     Ok(())
 }
@@ -319,6 +899,41 @@ pub fn encode_to_png(width: usize, height: usize, raw: &[u8]) -> Result<Vec<u8>>
     Ok(png_buffer)
 }
 
+/// Selezione PRIMARY di X11/Wayland; sugli altri sistemi non esiste
+#[cfg(target_os = "linux")]
+mod primary {
+    use arboard::{Clipboard, ClearExtLinux, GetExtLinux, LinuxClipboardKind, SetExtLinux};
+
+    pub fn get_text(cb: &mut Clipboard) -> Option<String> {
+        cb.get().clipboard(LinuxClipboardKind::Primary).text().ok()
+    }
+
+    pub fn set_text(cb: &mut Clipboard, text: String) -> Result<(), arboard::Error> {
+        cb.set().clipboard(LinuxClipboardKind::Primary).text(text)
+    }
+
+    pub fn clear(cb: &mut Clipboard) -> bool {
+        cb.clear_with().clipboard(LinuxClipboardKind::Primary).is_ok()
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod primary {
+    use arboard::Clipboard;
+
+    pub fn get_text(_cb: &mut Clipboard) -> Option<String> {
+        None
+    }
+
+    pub fn set_text(_cb: &mut Clipboard, _text: String) -> Result<(), arboard::Error> {
+        Err(arboard::Error::ClipboardNotSupported)
+    }
+
+    pub fn clear(_cb: &mut Clipboard) -> bool {
+        false
+    }
+}
+
 pub fn hash_data(data: &[u8]) -> String {
     let mut hasher = Sha256::new();
     hasher.update(data);
@@ -0,0 +1,83 @@
+use crate::core::config::{CompressionAlgorithm, CompressionConfig};
+use anyhow::{Result, anyhow};
+
+// Compressione opzionale del clip serializzato, prima della cifratura.
+// L'algoritmo usato viaggia nei bit alti dell'header di lunghezza (vedi protocol::frame_header).
+// Si comprime solo verso i peer che annunciano il supporto (proprietà TXT `zip`):
+// i peer vecchi ricevono sempre il formato originale.
+
+/// Algoritmi che questa versione sa decomprimere, in ordine di preferenza
+pub const SUPPORTED: &[CompressionAlgorithm] = &[CompressionAlgorithm::Zstd, CompressionAlgorithm::Lz4];
+
+/// Limite alla dimensione decompressa: protegge da "zip bomb" (oltre il MAX_PACKET_SIZE del server)
+pub const MAX_DECOMPRESSED: usize = 64 * 1024 * 1024;
+
+pub fn algorithm_name(algorithm: CompressionAlgorithm) -> &'static str {
+    match algorithm {
+        CompressionAlgorithm::Off => "off",
+        CompressionAlgorithm::Zstd => "zstd",
+        CompressionAlgorithm::Lz4 => "lz4",
+    }
+}
+
+/// Valore della proprietà TXT mDNS `zip`: "zstd,lz4"
+pub fn supported_txt() -> String {
+    SUPPORTED.iter().map(|a| algorithm_name(*a)).collect::<Vec<_>>().join(",")
+}
+
+/// Algoritmi annunciati da un peer (i nomi sconosciuti vengono ignorati)
+pub fn parse_supported(txt: &str) -> Vec<CompressionAlgorithm> {
+    txt.split(',')
+        .filter_map(|name| SUPPORTED.iter().copied().find(|a| algorithm_name(*a) == name.trim()))
+        .collect()
+}
+
+/// Algoritmo da usare verso un peer: quello configurato, se il peer lo supporta
+pub fn negotiate(config: &CompressionConfig, peer: &[CompressionAlgorithm]) -> Option<CompressionAlgorithm> {
+    (config.algorithm != CompressionAlgorithm::Off && peer.contains(&config.algorithm)).then_some(config.algorithm)
+}
+
+/// Comprime se il clip supera la soglia e se conviene davvero.
+/// Restituisce i byte da cifrare e l'algoritmo effettivamente applicato.
+pub fn maybe_compress(data: &[u8], algorithm: Option<CompressionAlgorithm>, config: &CompressionConfig) -> (Vec<u8>, Option<CompressionAlgorithm>) {
+    let Some(algorithm) = algorithm else { return (data.to_vec(), None) };
+    if data.len() < config.threshold_bytes as usize {
+        return (data.to_vec(), None);
+    }
+    match compress(data, algorithm, config) {
+        // PNG, JPEG e WebP sono già compressi: spesso non si guadagna nulla
+        Ok(packed) if packed.len() < data.len() => (packed, Some(algorithm)),
+        _ => (data.to_vec(), None),
+    }
+}
+
+pub fn compress(data: &[u8], algorithm: CompressionAlgorithm, config: &CompressionConfig) -> Result<Vec<u8>> {
+    match algorithm {
+        CompressionAlgorithm::Off => Ok(data.to_vec()),
+        CompressionAlgorithm::Zstd => Ok(zstd::bulk::compress(data, config.zstd_level.clamp(1, 22))?),
+        CompressionAlgorithm::Lz4 => {
+            let mode = match config.lz4_level {
+                level if level > 0 => lz4::block::CompressionMode::HIGHCOMPRESSION(level.min(12)),
+                _ => lz4::block::CompressionMode::DEFAULT,
+            };
+            // prepend_size: i primi 4 byte sono la dimensione originale
+            Ok(lz4::block::compress(data, Some(mode), true)?)
+        },
+    }
+}
+
+pub fn decompress(data: &[u8], algorithm: CompressionAlgorithm) -> Result<Vec<u8>> {
+    match algorithm {
+        CompressionAlgorithm::Off => Ok(data.to_vec()),
+        CompressionAlgorithm::Zstd => Ok(zstd::bulk::decompress(data, MAX_DECOMPRESSED)?),
+        CompressionAlgorithm::Lz4 => {
+            let size = data.get(..4)
+                .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as usize)
+                .ok_or_else(|| anyhow!("Payload LZ4 troncato"))?;
+            if size > MAX_DECOMPRESSED {
+                return Err(anyhow!("Payload LZ4 troppo grande ({} byte)", size));
+            }
+            Ok(lz4::block::decompress(data, None)?)
+        },
+    }
+}
@@ -1,8 +1,13 @@
 use serde::{Serialize, Deserialize};
 use std::path::PathBuf;
+use std::collections::HashMap;
 use std::fs;
 use anyhow::Result;
 use directories::ProjectDirs;
+use std::sync::{Arc, RwLock};
+
+/// Config condivisa tra UI-handler e servizi: le modifiche hanno effetto senza riavvio
+pub type SharedConfig = Arc<RwLock<AppConfig>>;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct AppConfig {
@@ -11,6 +16,441 @@ pub struct AppConfig {
     pub auto_start: bool,
     #[serde(default = "default_language")]
     pub language: String,
+    #[serde(default)]
+    pub sensitive: SensitiveConfig,
+    #[serde(default)]
+    pub auto_clear: AutoClearConfig,
+    #[serde(default)]
+    pub app_rules: AppRules,
+    #[serde(default)]
+    pub pipeline: PipelineConfig,
+    #[serde(default)]
+    pub hooks: HooksConfig,
+    #[serde(default)]
+    pub image: ImageConfig,
+    #[serde(default)]
+    pub compression: CompressionConfig,
+    #[serde(default)]
+    pub primary_selection: PrimarySelectionConfig,
+    #[serde(default)]
+    pub throttle: ThrottleConfig,
+    #[serde(default)]
+    pub direction: DirectionConfig,
+    #[serde(default)]
+    pub push: PushConfig,
+    #[serde(default)]
+    pub approval: ApprovalConfig,
+    #[serde(default)]
+    pub rings: RingsConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+}
+
+/// Livello minimo dei messaggi registrati
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogVerbosity {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    Debug,
+    Trace,
+}
+
+impl LogVerbosity {
+    pub const ALL: [LogVerbosity; 5] = [Self::Error, Self::Warn, Self::Info, Self::Debug, Self::Trace];
+}
+
+/// Log su file nella cartella dati: l'unico visibile nelle build Windows senza console
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub verbosity: LogVerbosity,
+    pub file_enabled: bool,
+    /// File giornalieri conservati; i più vecchi vengono cancellati
+    pub max_files: usize,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self { verbosity: LogVerbosity::Info, file_enabled: true, max_files: 7 }
+    }
+}
+
+/// Endpoint HTTP con le statistiche in formato Prometheus, solo su 127.0.0.1
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub endpoint_enabled: bool,
+    pub port: u16,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        Self { endpoint_enabled: false, port: 9466 }
+    }
+}
+
+/// Nome e stato di un ring; le chiavi stanno cifrate in `identity.enc` / `rings.enc`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RingSettings {
+    pub name: String,
+    pub enabled: bool,
+}
+
+impl Default for RingSettings {
+    fn default() -> Self {
+        Self { name: String::new(), enabled: true }
+    }
+}
+
+/// Impostazioni per ring (chiave = discovery_id); un ring senza voce è attivo
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RingsConfig {
+    pub rings: HashMap<String, RingSettings>,
+}
+
+impl RingsConfig {
+    pub fn is_enabled(&self, discovery_id: &str) -> bool {
+        self.rings.get(discovery_id).map(|r| r.enabled).unwrap_or(true)
+    }
+
+    /// Nome scelto dall'utente, o uno derivato dal discovery_id
+    pub fn name(&self, discovery_id: &str) -> String {
+        match self.rings.get(discovery_id) {
+            Some(r) if !r.name.is_empty() => r.name.clone(),
+            _ => format!("Ring {}", &discovery_id[..discovery_id.len().min(6)]),
+        }
+    }
+
+    pub fn entry(&mut self, discovery_id: &str) -> &mut RingSettings {
+        self.rings.entry(discovery_id.to_string()).or_default()
+    }
+}
+
+/// Cosa fare dei clip ricevuti
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ApprovalPolicy {
+    /// Scritti subito nella clipboard
+    #[default]
+    Auto,
+    /// Messi in coda finché l'utente non li accetta
+    Ask,
+}
+
+/// Politica globale; quella del singolo peer (chiave = device_id), se presente, la sostituisce
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApprovalConfig {
+    pub global: ApprovalPolicy,
+    pub peers: HashMap<String, ApprovalPolicy>,
+}
+
+impl ApprovalConfig {
+    /// None = mittente non riconosciuto: vale la politica globale
+    pub fn for_peer(&self, device_id: Option<&str>) -> ApprovalPolicy {
+        device_id.and_then(|id| self.peers.get(id)).copied().unwrap_or(self.global)
+    }
+}
+
+/// Modalità push: i clip partono solo su richiesta esplicita (scorciatoia, tray o CLI)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PushConfig {
+    pub manual: bool,
+    /// Scorciatoia globale, es. "CmdOrCtrl+Alt+C" (vuota = nessuna)
+    pub hotkey: String,
+}
+
+impl Default for PushConfig {
+    fn default() -> Self {
+        Self { manual: false, hotkey: "CmdOrCtrl+Alt+C".into() }
+    }
+}
+
+/// Verso in cui un dispositivo (o un singolo peer) sincronizza
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SyncDirection {
+    #[default]
+    Both,
+    /// Invia i propri clip ma non accetta quelli altrui (es. una macchina di CI)
+    SendOnly,
+    /// Accetta clip ma non invia i propri (es. il portatile delle presentazioni)
+    ReceiveOnly,
+}
+
+impl SyncDirection {
+    pub fn sends(self) -> bool {
+        self != SyncDirection::ReceiveOnly
+    }
+
+    pub fn receives(self) -> bool {
+        self != SyncDirection::SendOnly
+    }
+}
+
+/// Verso globale, eventualmente ristretto per singolo peer (chiave = device_id)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DirectionConfig {
+    pub global: SyncDirection,
+    pub peers: HashMap<String, SyncDirection>,
+}
+
+impl DirectionConfig {
+    pub fn for_peer(&self, device_id: &str) -> SyncDirection {
+        self.peers.get(device_id).copied().unwrap_or_default()
+    }
+
+    pub fn sends_to(&self, device_id: &str) -> bool {
+        self.global.sends() && self.for_peer(device_id).sends()
+    }
+
+    /// None = mittente non riconosciuto: accettato solo se nessun peer ha la ricezione bloccata,
+    /// altrimenti potrebbe essere proprio quel peer
+    pub fn receives_from(&self, device_id: Option<&str>) -> bool {
+        self.global.receives() && match device_id {
+            Some(id) => self.for_peer(id).receives(),
+            None => self.peers.values().all(|d| d.receives()),
+        }
+    }
+}
+
+/// Limiti di frequenza dei clip in uscita e in entrata
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThrottleConfig {
+    /// Un clip viene inviato solo se la clipboard resta invariata per questo tempo (0 = subito)
+    pub debounce_ms: u32,
+    /// Clip accettati da ogni peer in un minuto; gli altri vengono scartati (0 = nessun limite)
+    pub max_incoming_per_minute: u32,
+}
+
+impl Default for ThrottleConfig {
+    fn default() -> Self {
+        Self { debounce_ms: 0, max_incoming_per_minute: 60 }
+    }
+}
+
+/// Dove finisce, sui peer, la selezione PRIMARY (middle-click) di Linux
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrimaryTarget {
+    /// Nella PRIMARY dei peer Linux (gli altri non la ricevono)
+    Primary,
+    /// Nella clipboard normale di tutti i peer
+    Clipboard,
+}
+
+/// Sincronizzazione della selezione PRIMARY (solo Linux, opzionale)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PrimarySelectionConfig {
+    pub enabled: bool,
+    pub target: PrimaryTarget,
+    /// La selezione viene inviata solo se resta invariata per questo tempo
+    pub debounce_ms: u32,
+}
+
+impl Default for PrimarySelectionConfig {
+    fn default() -> Self {
+        Self { enabled: false, target: PrimaryTarget::Primary, debounce_ms: 1000 }
+    }
+}
+
+/// Algoritmo usato per comprimere i clip prima della cifratura
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionAlgorithm {
+    Off,
+    Zstd,
+    Lz4,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompressionConfig {
+    pub algorithm: CompressionAlgorithm,
+    /// Sotto questa dimensione (byte) il clip viaggia non compresso
+    pub threshold_bytes: u32,
+    /// 1-22 (3 = default di zstd)
+    pub zstd_level: i32,
+    /// 0 = modalità veloce, 1-12 = modalità "high compression"
+    pub lz4_level: i32,
+}
+
+impl Default for CompressionConfig {
+    fn default() -> Self {
+        Self { algorithm: CompressionAlgorithm::Zstd, threshold_bytes: 1024, zstd_level: 3, lz4_level: 0 }
+    }
+}
+
+/// Formato in cui vengono codificate le immagini inviate
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ImageCodec {
+    Png,
+    WebpLossless,
+    WebpLossy,
+    /// Senza trasparenza: il canale alfa viene appiattito su sfondo bianco
+    Jpeg,
+}
+
+/// Preferenza annunciata ai peer per le immagini dirette a questo dispositivo
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImagePreference {
+    pub codec: ImageCodec,
+    pub quality: u8,
+    /// Lato lungo massimo in pixel (0 = nessun limite)
+    pub max_dimension: u32,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ImageConfig {
+    pub codec: ImageCodec,
+    /// Qualità 1-100 dei codec lossy
+    pub quality: u8,
+    /// Lato lungo massimo in pixel (0 = nessun limite); le immagini più grandi vengono ridotte
+    pub max_dimension: u32,
+    /// Dimensione massima dell'immagine codificata in KiB (0 = solo il limite del protocollo)
+    pub max_kib: u32,
+    /// Se presente, i peer la usano al posto delle loro impostazioni
+    pub preferred: Option<ImagePreference>,
+}
+
+impl Default for ImageConfig {
+    fn default() -> Self {
+        Self { codec: ImageCodec::Png, quality: 85, max_dimension: 0, max_kib: 0, preferred: None }
+    }
+}
+
+/// Comando utente (eseguito con `sh -c` / `cmd /C`); vuoto = disattivato
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HookConfig {
+    pub command: String,
+    /// Se vero, lo stdout dello script sostituisce il testo del clip
+    pub rewrite: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HooksConfig {
+    pub on_send: HookConfig,
+    pub on_receive: HookConfig,
+    pub timeout_secs: u32,
+}
+
+impl Default for HooksConfig {
+    fn default() -> Self {
+        Self { on_send: HookConfig::default(), on_receive: HookConfig::default(), timeout_secs: 5 }
+    }
+}
+
+/// Stage integrati della pipeline di trasformazione (vedi core::pipeline)
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PipelineConfig {
+    pub trim_whitespace: bool,
+    pub strip_tracking: bool,
+    /// Parametri di tracking aggiuntivi da rimuovere dagli URL
+    pub extra_tracking_params: Vec<String>,
+    /// LF sul filo, formato nativo in ricezione (CRLF su Windows); opt-in, altrimenti il testo passa com'è
+    pub normalize_line_endings: bool,
+    /// Limiti di dimensione in KiB (0 = nessun limite)
+    pub max_text_kib: u32,
+    pub max_image_kib: u32,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            trim_whitespace: false,
+            strip_tracking: false,
+            extra_tracking_params: Vec::new(),
+            normalize_line_endings: false,
+            max_text_kib: 0,
+            max_image_kib: 0,
+        }
+    }
+}
+
+/// Regole sull'applicazione da cui proviene un clip (match per sottostringa, senza maiuscole)
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppRules {
+    /// Mai inviare clip copiati da queste app
+    pub deny: Vec<String>,
+    /// Se non vuota, inviare SOLO clip copiati da queste app
+    pub allow: Vec<String>,
+}
+
+/// Quali clip ricevuti vanno cancellati automaticamente dalla clipboard
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AutoClearMode {
+    Off,
+    /// Solo quelli marcati come sensibili dal mittente
+    Sensitive,
+    All,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoClearConfig {
+    pub mode: AutoClearMode,
+    pub secs: u32,
+}
+
+impl Default for AutoClearConfig {
+    fn default() -> Self {
+        Self { mode: AutoClearMode::Sensitive, secs: 60 }
+    }
+}
+
+/// Cosa fare di un clip riconosciuto come sensibile
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SensitivePolicy {
+    /// Nessun filtro: tutto viene inviato
+    Off,
+    /// Il clip non lascia il dispositivo
+    Block,
+    /// Il clip viene inviato, ma i peer lo cancellano dopo `expire_secs`
+    Expire,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SensitiveConfig {
+    pub policy: SensitivePolicy,
+    pub expire_secs: u32,
+    /// Rispetta i flag "non sincronizzare" dei password manager
+    pub platform_hints: bool,
+    /// Rilevatori integrati (chiavi AWS, JWT, chiavi private)
+    pub builtin_detectors: bool,
+    /// Regex aggiuntive definite dall'utente
+    pub custom_patterns: Vec<String>,
+}
+
+impl Default for SensitiveConfig {
+    fn default() -> Self {
+        Self {
+            policy: SensitivePolicy::Block,
+            expire_secs: 30,
+            platform_hints: true,
+            builtin_detectors: true,
+            custom_patterns: Vec::new(),
+        }
+    }
 }
 
 fn default_language() -> String {
@@ -32,26 +472,45 @@ impl Default for AppConfig {
             notifications_enabled: true,
             auto_start: false,
             language: default_language(),
+            sensitive: SensitiveConfig::default(),
+            auto_clear: AutoClearConfig::default(),
+            app_rules: AppRules::default(),
+            pipeline: PipelineConfig::default(),
+            hooks: HooksConfig::default(),
+            image: ImageConfig::default(),
+            compression: CompressionConfig::default(),
+            primary_selection: PrimarySelectionConfig::default(),
+            throttle: ThrottleConfig::default(),
+            direction: DirectionConfig::default(),
+            push: PushConfig::default(),
+            approval: ApprovalConfig::default(),
+            rings: RingsConfig::default(),
+            logging: LoggingConfig::default(),
+            metrics: MetricsConfig::default(),
         }
     }
 }
 
+/// Cartella di config dell'app (creata se manca): config, identità, ring e roster stanno qui
+pub fn config_dir() -> Result<PathBuf> {
+    let proj = ProjectDirs::from("com", "rustclip", "rust-clip")
+        .ok_or_else(|| anyhow::anyhow!("Impossibile determinare cartella config"))?;
+
+    let config_dir = proj.config_dir();
+    if !config_dir.exists() {
+        fs::create_dir_all(config_dir)?;
+    }
+    Ok(config_dir.to_path_buf())
+}
+
 impl AppConfig {
     fn get_path() -> Result<PathBuf> {
-        let proj = ProjectDirs::from("com", "rustclip", "rust-clip")
-            .ok_or_else(|| anyhow::anyhow!("Impossibile determinare cartella config"))?;
-        
-        let config_dir = proj.config_dir();
-        if !config_dir.exists() {
-            fs::create_dir_all(config_dir)?;
-        }
-        
-        Ok(config_dir.join("config.json"))
+        Ok(config_dir()?.join("config.json"))
     }
 
     pub fn load() -> Self {
         if let Ok(path) = Self::get_path() {
-            println!("📂 Config Path: {:?}", path);
+            tracing::debug!("📂 Config Path: {:?}", path);
             if let Ok(content) = fs::read_to_string(path) {
                 if let Ok(cfg) = serde_json::from_str(&content) {
                     return cfg;
@@ -61,6 +520,18 @@ impl AppConfig {
         Self::default()
     }
 
+    /// Peer con regole (verso, approvazione) salvate con i vecchi ID a 4 cifre esadecimali:
+    /// non corrispondono più a nessun dispositivo, quindi vanno reimpostate a mano
+    pub fn legacy_peer_rules(&self) -> Vec<&str> {
+        let ids: std::collections::BTreeSet<&str> = self.direction.peers.keys()
+            .chain(self.approval.peers.keys())
+            .map(String::as_str)
+            // Gli ID attuali sono 128 bit in esadecimale
+            .filter(|id| id.len() != 32)
+            .collect();
+        ids.into_iter().collect()
+    }
+
     pub fn save(&self) -> Result<()> {
         let path = Self::get_path()?;
         let json = serde_json::to_string_pretty(self)?;
@@ -6,9 +6,12 @@ use chacha20poly1305::{
 use rand::{RngCore, thread_rng};
 use serde::{Serialize, Deserialize};
 use std::time::{SystemTime, UNIX_EPOCH};
+use std::fmt;
 
 // Validità del pacchetto (es. 60 secondi) per evitare Replay Attacks
-const REPLAY_WINDOW_SECONDS: u64 = 60;
+pub const REPLAY_WINDOW_SECONDS: u64 = 60;
+// Tolleranza per i pacchetti che arrivano "dal futuro" (orologio del mittente avanti)
+pub const MAX_FUTURE_SECONDS: u64 = 5;
 
 #[derive(Serialize, Deserialize)]
 struct SecurePayload {
@@ -16,10 +19,17 @@ struct SecurePayload {
     data: Vec<u8>,
 }
 
+// La chiave vive solo dentro il cipher, che la azzera da sé al drop
 pub struct CryptoLayer {
     cipher: XChaCha20Poly1305,
 }
 
+impl fmt::Debug for CryptoLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CryptoLayer { key: <redacted> }")
+    }
+}
+
 impl CryptoLayer {
     pub fn new(shared_secret: &[u8; 32]) -> Self {
         let key = Key::from_slice(shared_secret);
@@ -30,10 +40,16 @@ impl CryptoLayer {
     /// Cifra i dati aggiungendo Timestamp e Nonce casuale
     /// Output format: [NONCE (24b)] + [CIPHERTEXT (Variabile)]
     pub fn encrypt(&self, data: &[u8]) -> Result<Vec<u8>> {
-        // 1. Prepara il payload con timestamp
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        self.encrypt_at(data, now)
+    }
+
+    /// Come `encrypt`, con un timestamp scelto dal chiamante (la diagnostica lo sposta
+    /// per farsi rispondere anche da un peer con l'orologio indietro)
+    pub fn encrypt_at(&self, data: &[u8], timestamp: u64) -> Result<Vec<u8>> {
+        // 1. Prepara il payload con timestamp
         let payload = SecurePayload {
-            timestamp: now,
+            timestamp,
             data: data.to_vec(),
         };
         let payload_bytes = bincode::serialize(&payload)?;
@@ -57,6 +73,20 @@ impl CryptoLayer {
 
     /// Decifra e valida Timestamp e Integrità
     pub fn decrypt(&self, packet: &[u8]) -> Result<Vec<u8>> {
+        let (timestamp, data) = self.decrypt_with_time(packet)?;
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        
+        // Controllo Anti-Replay: Accettiamo solo messaggi recenti
+        if timestamp > now + MAX_FUTURE_SECONDS || timestamp < now - REPLAY_WINDOW_SECONDS {
+            return Err(anyhow!("Pacchetto scartato: Timestamp non valido (Replay Attack o orologio disallineato)"));
+        }
+
+        Ok(data)
+    }
+
+    /// Decifra restituendo anche l'ora del mittente, SENZA il controllo anti-replay.
+    /// Solo per la diagnostica (misura dello scarto tra orologi): i dati possono essere una replica.
+    pub fn decrypt_with_time(&self, packet: &[u8]) -> Result<(u64, Vec<u8>)> {
         if packet.len() < 24 {
             return Err(anyhow!("Pacchetto troppo corto"));
         }
@@ -69,16 +99,8 @@ impl CryptoLayer {
         let plaintext = self.cipher.decrypt(nonce, ciphertext)
             .map_err(|_| anyhow!("Decifrazione fallita (Chiave errata o pacchetto manomesso)"))?;
 
-        // 3. Deserializza e controlla Timestamp
+        // 3. Deserializza
         let payload: SecurePayload = bincode::deserialize(&plaintext)?;
-        
-        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
-        
-        // Controllo Anti-Replay: Accettiamo solo messaggi recenti
-        if payload.timestamp > now + 5 || payload.timestamp < now - REPLAY_WINDOW_SECONDS {
-            return Err(anyhow!("Pacchetto scartato: Timestamp non valido (Replay Attack o orologio disallineato)"));
-        }
-
-        Ok(payload.data)
+        Ok((payload.timestamp, payload.data))
     }
 }
\ No newline at end of file
@@ -1,60 +1,105 @@
 use crate::core::identity::RingIdentity;
 use crate::core::config::AppConfig;
+use crate::core::imaging;
+use crate::core::compress;
+use crate::core::roster::{now_secs, SharedRoster};
+use crate::core::identity::tag_epoch;
+use crate::core::crypto::CryptoLayer;
+use crate::core::handshake::{self, HANDSHAKE_TIMEOUT};
+use crate::core::announce::{self, AnnounceMeta};
+use crate::core::protocol::Hello;
 use crate::events::{CoreEvent, PeerInfo};
 use flume::Sender;
 use anyhow::Result;
+use tracing::{info, warn};
 use mdns_sd::{ServiceDaemon, ServiceInfo};
-use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use dashmap::DashMap;
 use std::net::SocketAddr;
 
-const SERVICE_TYPE: &str = "_rustclip._tcp.local.";
-const TCP_PORT: u16 = 5566; 
+pub const SERVICE_TYPE: &str = "_rustclip._tcp.local.";
+pub const TCP_PORT: u16 = 5566;
 
 pub type PeerMap = Arc<DashMap<String, PeerInfo>>;
 
+/// Pausa tra due tentativi di handshake con un peer appena annunciato
+const VERIFY_RETRY: Duration = Duration::from_secs(2);
+/// Dopo tanti tentativi falliti l'annuncio si ignora (peer vecchio, o non del ring)
+const VERIFY_ATTEMPTS: u32 = 5;
+/// Handshake in corso allo stesso tempo: gli altri candidati aspettano il loro turno
+const MAX_PARALLEL_VERIFY: usize = 8;
+
+/// Peer annunciato in mDNS in attesa dell'handshake
+struct Candidate {
+    hint: PeerInfo,
+    attempts: u32,
+    next_try: Instant,
+    /// Handshake in corso su un thread di verifica
+    verifying: bool,
+}
+
+/// Annuncia e cerca i peer di un ring finché `stop` non diventa true
+/// (un'istanza per ogni ring attivo, ognuna con la sua PeerMap). I peer trovati finiscono anche nel registro.
+///
+/// Un peer entra nella PeerMap solo dopo l'handshake: nome e device_id sono quelli che ha
+/// dimostrato, non quelli dell'annuncio. L'annuncio porta un tag del ring che cambia ogni `TAG_PERIOD_SECS`.
 pub fn start_lan_discovery(
     identity: RingIdentity, 
     peers: PeerMap, 
     config: AppConfig, 
-    tx_event: Option<Sender<CoreEvent>>
+    tx_event: Option<Sender<CoreEvent>>,
+    roster: SharedRoster,
+    stop: Arc<AtomicBool>,
 ) -> Result<()> {
-    println!("🌍 Starting LAN Discovery...");
+    info!("🌍 Starting LAN Discovery...");
 
     let my_discovery_id = identity.discovery_id.clone();
     let my_device_id = RingIdentity::get_derived_device_id();
+    let crypto = Arc::new(CryptoLayer::new(&identity.shared_secret));
     let mdns = ServiceDaemon::new()?;
 
-    // --- COSTRUZIONE NOME SERVICE ---
-    // Puliamo il nome scelto dall'utente per renderlo compatibile con mDNS
-    let _safe_name = sanitize_device_name(&config.device_name);
-    // Usiamo direttamente il device_id come hostname parte del service
-    // In questo modo è stabile
-    let instance_name = format!("rustclip-{}", my_device_id);
     let ip = "0.0.0.0"; 
     
-    // Nelle properties mettiamo le info "umane"
-    let properties = [
-        ("version", "1.0"), 
-        ("ring_id", &my_discovery_id),
-        ("device_name", &config.device_name),
-        ("device_id", &my_device_id)
-    ];
-
-    let service_info = ServiceInfo::new(
-        SERVICE_TYPE,
-        &instance_name,
-        &format!("{}.local.", instance_name),
-        ip,
-        TCP_PORT,
-        &properties[..],
-    )?.enable_addr_auto();
+    // Le info "umane" vanno nei metadati cifrati: in chiaro resta solo il tag del ring
+    let meta = AnnounceMeta {
+        device_id: my_device_id.clone(),
+        device_name: announce::announced_name(&config.device_name),
+        zip: compress::supported_txt(),
+        os: std::env::consts::OS.to_string(),
+        app: env!("CARGO_PKG_VERSION").to_string(),
+        // Solo Linux ha la selezione PRIMARY in cui scrivere
+        primary: cfg!(target_os = "linux"),
+        // Preferenza sul formato delle immagini che vogliamo ricevere
+        img: config.image.preferred.as_ref().map(imaging::preference_txt),
+    };
+
+    // Nome del servizio e tag cambiano insieme a ogni periodo
+    let build_service = |epoch: u64| -> Result<(ServiceInfo, String)> {
+        let tag = identity.announce_tag(epoch);
+        let instance_name = announce::random_instance_name();
+        let mut properties = vec![("tag".to_string(), tag.clone())];
+        properties.extend(announce::seal_meta(&identity, &tag, &meta)?);
+
+        let service_info = ServiceInfo::new(
+            SERVICE_TYPE,
+            &instance_name,
+            &format!("{}.local.", instance_name),
+            ip,
+            TCP_PORT,
+            &properties[..],
+        )?.enable_addr_auto();
+        Ok((service_info, instance_name))
+    };
 
+    let mut epoch = tag_epoch(now_secs());
+    let (service_info, instance_name) = build_service(epoch)?;
+    let mut fullname = service_info.get_fullname().to_string();
     mdns.register(service_info)?;
     
-    println!("📢 Announcement active: '{}' (ID: {})", instance_name, my_device_id);
+    info!("📢 Announcement active: '{}' (ID: {})", instance_name, my_device_id);
     
     let receiver = mdns.browse(SERVICE_TYPE)?;
 
@@ -64,121 +109,244 @@ pub fn start_lan_discovery(
                 .iter()
                 .map(|r| r.value().clone())
                 .collect();
-            let _ = tx.send(CoreEvent::PeersUpdated(list));
+            let _ = tx.send(CoreEvent::PeersUpdated { ring_id: my_discovery_id.clone(), peers: list });
         }
     };
 
-    loop {
-        while let Ok(event) = receiver.recv() {
-            match event {
-                mdns_sd::ServiceEvent::ServiceResolved(info) => {
-                    let found_fullname = info.get_fullname();
-                    if found_fullname.contains(&instance_name) { continue; } // Ignora me stesso
-
-                    let props = info.get_properties();
-                    if let Some(other_prop) = props.get("ring_id") {
-                        let clean_prop = |p: &str| p.trim().replace("\"", "").replace("ring_id=", "");
-                        let clean_id = clean_prop(&other_prop.to_string());
-
-                        if clean_id == my_discovery_id {
-                            // Extract metadata
-                            // FIX: Strip "device_name=" if present
-                            let raw_name = props.get("device_name")
-                                .map(|s| s.to_string().replace("\"", ""))
-                                .unwrap_or_else(|| "Unknown".to_string());
-                            
-                            let device_name = raw_name.replace("device_name=", ""); // FIX 1
-                            
-                            let peer_device_id = props.get("device_id")
-                                .map(|s| s.to_string().replace("\"", ""))
-                                .unwrap_or_else(|| found_fullname.to_string());
-
-
-                            // FIND ADDRESS (Prefer IPv4)
-                            let mut target_addr: Option<SocketAddr> = None;
-                            
-                            // Prima cerchiamo esplicitamente IPv4
-                            for ip in info.get_addresses() {
-                                if ip.is_ipv4() {
-                                    target_addr = Some(SocketAddr::new(*ip, info.get_port()));
-                                    break; 
-                                }
-                            }
-                            // Se non c'è IPv4, accettiamo IPv6 ma è rischioso per link-local scope id
-                            if target_addr.is_none() {
-                                if let Some(ip) = info.get_addresses().iter().next() {
-                                     target_addr = Some(SocketAddr::new(*ip, info.get_port()));
-                                }
-                            }
-
-                            if let Some(addr) = target_addr {
-                                // CHIAVE MAPPA = DEVICE_ID (stabile)
-                                // Se l'abbiamo gia, aggiorniamo IP e nome (se cambiato)
-                                let peer_info = PeerInfo {
-                                    name: device_name.clone(),
-                                    ip: addr,
-                                    device_id: peer_device_id.clone(),
-                                    last_seen: std::time::SystemTime::now(),
-                                };
-
-                                let mut changed = false;
-                                if let Some(mut existing) = peers.get_mut(&peer_device_id) {
-                                    if existing.ip != addr || existing.name != device_name {
-                                        *existing = peer_info.clone();
-                                        changed = true;
-                                    }
-                                } else {
-                                    peers.insert(peer_device_id.clone(), peer_info);
-                                    changed = true;
-                                    let msg = format!("➕ Peer Added: {} ({}) -> {}", device_name, peer_device_id, addr);
-                                    println!("{}", msg);
-                                    if let Some(tx) = &tx_event {
-                                        let _ = tx.send(CoreEvent::Log(crate::events::LogEntry::new(&msg)));
-                                    }
-                                }
-                                
-                                if changed {
-                                    send_update(&peers);
-                                }
-                            }
-                        }
+    // Avvistamento di un peer: il registro si salva solo se è cambiato qualcosa di rilevante
+    let record_seen = |peer: &PeerInfo| {
+        let mut roster = roster.lock().unwrap();
+        if roster.observe(peer, now_secs()) {
+            if let Err(e) = roster.save() {
+                warn!("⚠️ Roster not saved: {}", e);
+            }
+            if let Some(tx) = &tx_event {
+                let _ = tx.send(CoreEvent::RosterUpdated(roster.devices.clone()));
+            }
+        }
+    };
+
+    // Annunci in attesa di handshake, per fullname del servizio
+    let mut candidates: HashMap<String, Candidate> = HashMap::new();
+    // Annunci dei peer verificati: fullname del servizio -> device_id
+    let mut announced: HashMap<String, String> = HashMap::new();
+    // Esiti degli handshake, che girano su thread propri: un peer lento non ferma il loop
+    let (tx_verified, rx_verified) = flume::unbounded::<(String, Result<Hello>)>();
+
+    while !stop.load(Ordering::Relaxed) {
+        // Periodo nuovo: si ritira l'annuncio col tag vecchio e si pubblica quello nuovo
+        let current = tag_epoch(now_secs());
+        if current != epoch {
+            epoch = current;
+            // Prima il nuovo, poi il ritiro del vecchio: i peer non ci vedono mai sparire
+            match build_service(epoch).and_then(|(service_info, _)| {
+                let new_fullname = service_info.get_fullname().to_string();
+                mdns.register(service_info)?;
+                Ok(new_fullname)
+            }) {
+                Ok(new_fullname) => {
+                    let _ = mdns.unregister(&fullname);
+                    fullname = new_fullname;
+                }
+                Err(e) => warn!("⚠️ Announcement not renewed: {}", e),
+            }
+        }
+
+        match receiver.recv_timeout(Duration::from_millis(500)) {
+            Ok(mdns_sd::ServiceEvent::ServiceResolved(info)) => {
+                let Some(hint) = parse_peer(&info, &identity, now_secs()) else { continue };
+                if hint.device_id == my_device_id { continue; } // Ignora me stesso
+
+                // Già verificato a quell'indirizzo e annuncio invariato: niente nuovo handshake
+                if let Some(existing) = peers.get(&hint.device_id) {
+                    if same_announcement(&existing, &hint) {
+                        record_seen(&existing);
+                        announced.insert(info.get_fullname().to_string(), hint.device_id.clone());
+                        continue;
                     }
                 }
-                mdns_sd::ServiceEvent::ServiceRemoved(_, fullname) => {
-                    // Questa rimozione è basata sul fullname del servizio mDNS, ma noi usiamo device_id come chiave.
-                    // Dobbiamo trovare quale device_id corrisponde a questo service fullname? 
-                    // Purtroppo ServiceRemoved non ci da le property.
-                    // Tuttavia, abbiamo costruito il nome del servizio come "rustclip-{device_id}".
-                    // Proviamo a estrarlo se possibile, altrimenti potremmo dover fare una ricerca inversa.
-                    
-                    // Se il fullname contiene rustclip-UUID...
-                    if let Some(start) = fullname.find("rustclip-") {
-                         let rest = &fullname[start + 9..];
-                         let end = rest.find('.').unwrap_or(rest.len());
-                         let extracted_id = &rest[..end];
-                         
-                         if peers.contains_key(extracted_id) {
-                             println!("➖ Servizio mDNS Rimosso: {} ({})", fullname, extracted_id);
-                             // peers.remove(extracted_id); // NON RIMUOVIAMO SUBITO! 
-                             // Il problema 1 dice: "i peer quando si disconnettono non vengono rimossi dalla lista".
-                             // Ma se rimuoviamo qui, risolviamo quel problema?
-                             // mDNS dice che il servizio è andato via (es. sleep o chiusura corretta).
-                             // Se crasha, non manda ServiceRemoved.
-                             // Quindi: se riceviamo questo, è sicuro rimuovere? Sì.
-                             if peers.remove(extracted_id).is_some() {
-                                 send_update(&peers);
-                             }
-                         }
+                candidates.insert(info.get_fullname().to_string(), Candidate { hint, attempts: 0, next_try: Instant::now(), verifying: false });
+            }
+            Ok(mdns_sd::ServiceEvent::ServiceRemoved(_, removed)) => {
+                candidates.remove(&removed);
+                // Il nome del servizio è casuale: il device_id si ricava dagli annunci già visti.
+                // Un peer che cambia periodo annuncia il nome nuovo prima di ritirare il vecchio,
+                // quindi se ne va solo quando non gli resta nessun annuncio.
+                let Some(device_id) = announced.remove(&removed) else { continue };
+                if announced.values().any(|d| *d == device_id) { continue; }
+
+                // mDNS dice che il servizio è andato via (es. sleep o chiusura corretta).
+                // Se crasha, non manda ServiceRemoved.
+                if let Some((_, peer)) = peers.remove(&device_id) {
+                    info!("➖ Servizio mDNS Rimosso: {} ({})", removed, device_id);
+                    record_seen(&peer);
+                    send_update(&peers);
+                }
+            }
+            Ok(_) | Err(flume::RecvTimeoutError::Timeout) => {}
+            Err(flume::RecvTimeoutError::Disconnected) => break,
+        }
+
+        // Handshake con gli annunci in attesa: il listener del peer può non essere ancora pronto
+        let now = Instant::now();
+        let mut running = candidates.values().filter(|c| c.verifying).count();
+        for (key, candidate) in candidates.iter_mut() {
+            if running >= MAX_PARALLEL_VERIFY {
+                break;
+            }
+            if candidate.verifying || candidate.next_try > now {
+                continue;
+            }
+            candidate.verifying = true;
+            running += 1;
+            let (key, addr, crypto, tx) = (key.clone(), candidate.hint.ip, crypto.clone(), tx_verified.clone());
+            let (device_id, device_name) = (my_device_id.clone(), config.device_name.clone());
+            std::thread::spawn(move || {
+                let _ = tx.send((key, handshake::verify_peer(addr, &crypto, &device_id, &device_name, HANDSHAKE_TIMEOUT)));
+            });
+        }
+
+        // Esiti arrivati; un annuncio ritirato nel frattempo non ha più un candidato e si ignora
+        while let Ok((key, result)) = rx_verified.try_recv() {
+            let hello = match result {
+                Ok(hello) => hello,
+                Err(e) => {
+                    let Some(candidate) = candidates.get_mut(&key) else { continue };
+                    candidate.verifying = false;
+                    candidate.attempts += 1;
+                    candidate.next_try = Instant::now() + VERIFY_RETRY;
+                    if candidate.attempts >= VERIFY_ATTEMPTS {
+                        warn!("⚠️ Peer not verified: {} -> {} ({})", candidate.hint.name, candidate.hint.ip, e);
+                        candidates.remove(&key);
                     }
+                    continue;
                 }
-                _ => {} 
+            };
+            let Some(candidate) = candidates.remove(&key) else { continue };
+            announced.insert(key, hello.device_id.clone());
+            // Nome e device_id dall'handshake; dall'annuncio solo indirizzo e preferenze
+            let peer_info = PeerInfo { device_id: hello.device_id, name: hello.device_name, ..candidate.hint };
+            let peer_device_id = peer_info.device_id.clone();
+            record_seen(&peer_info);
+
+            // CHIAVE MAPPA = DEVICE_ID (stabile)
+            // Se l'abbiamo gia, aggiorniamo IP e nome (se cambiato)
+            let mut changed = false;
+            if let Some(mut existing) = peers.get_mut(&peer_device_id) {
+                if !same_announcement(&existing, &peer_info) {
+                    *existing = peer_info;
+                    changed = true;
+                }
+            } else {
+                info!("➕ Peer Added: {} ({}) -> {}", peer_info.name, peer_device_id, peer_info.ip);
+                peers.insert(peer_device_id.clone(), peer_info);
+                changed = true;
+            }
+
+            if changed {
+                send_update(&peers);
+            }
+        }
+    }
+
+    // Ring disattivato o servizi riavviati: ritiriamo l'annuncio (goodbye ai peer) e svuotiamo la lista in UI
+    if let Ok(status) = mdns.unregister(&fullname) {
+        let _ = status.recv_timeout(Duration::from_secs(1));
+    }
+    let _ = mdns.shutdown();
+    for peer in peers.iter() {
+        record_seen(&peer);
+    }
+    peers.clear();
+    send_update(&peers);
+    Ok(())
+}
+
+/// True se un annuncio non porta nulla di nuovo rispetto al peer già verificato
+fn same_announcement(known: &PeerInfo, hint: &PeerInfo) -> bool {
+    known.ip == hint.ip && known.name == hint.name
+        && known.image_pref == hint.image_pref && known.compression == hint.compression
+        && known.primary_selection == hint.primary_selection
+        && known.os == hint.os && known.app_version == hint.app_version
+}
+
+/// Estrae da un servizio mDNS risolto l'indizio di un peer del ring; None se il tag
+/// non è del ring all'istante `now` o i metadati non si decifrano. Nome e device_id vanno confermati con l'handshake.
+pub fn parse_peer(info: &ServiceInfo, ring: &RingIdentity, now: u64) -> Option<PeerInfo> {
+    let tag = info.get_property_val_str("tag")?;
+    if !ring.accepted_tags(now).iter().any(|t| t == tag) {
+        return None;
+    }
+    let meta = announce::open_meta(ring, tag, |key| info.get_property_val_str(key))?;
+
+    // FIND ADDRESS (Prefer IPv4)
+    // Prima cerchiamo esplicitamente IPv4
+    // Se non c'è IPv4, accettiamo IPv6 ma è rischioso per link-local scope id
+    let ip = info.get_addresses().iter().find(|ip| ip.is_ipv4())
+        .or_else(|| info.get_addresses().iter().next())?;
+
+    Some(PeerInfo {
+        name: meta.device_name,
+        ip: SocketAddr::new(*ip, info.get_port()),
+        device_id: meta.device_id,
+        last_seen: std::time::SystemTime::now(),
+        image_pref: meta.img.as_deref().and_then(imaging::parse_preference),
+        compression: compress::parse_supported(&meta.zip),
+        primary_selection: meta.primary,
+        ring_id: ring.discovery_id.clone(),
+        os: Some(meta.os),
+        app_version: Some(meta.app),
+    })
+}
+
+/// Cerca un peer dei ring per nome o device_id, senza annunciarsi (usato dalla CLI).
+/// Si arrende dopo `timeout`. `my_name` è il nome con cui presentarsi nell'handshake.
+pub fn find_peer(rings: &[RingIdentity], my_name: &str, device: &str, timeout: Duration) -> Result<PeerInfo> {
+    let matches = |peer: &PeerInfo| peer.device_id == device || peer.name.eq_ignore_ascii_case(device);
+    browse_peers(rings, my_name, timeout, matches)?
+        .pop()
+        .filter(matches)
+        .ok_or_else(|| anyhow::anyhow!("Dispositivo '{}' non trovato nel ring", device))
+}
+
+/// Tutti i peer dei ring che rispondono entro `timeout`, senza annunciarsi (usato dalla CLI)
+pub fn find_peers(rings: &[RingIdentity], my_name: &str, timeout: Duration) -> Result<Vec<PeerInfo>> {
+    browse_peers(rings, my_name, timeout, |_| false)
+}
+
+/// Raccoglie i peer verificati dei ring fino a `timeout`, o finché `found` non ne accetta uno (che è l'ultimo).
+/// Un dispositivo presente in più ring compare una volta sola.
+fn browse_peers(rings: &[RingIdentity], my_name: &str, timeout: Duration, found: impl Fn(&PeerInfo) -> bool) -> Result<Vec<PeerInfo>> {
+    let my_device_id = RingIdentity::get_derived_device_id();
+    let mdns = ServiceDaemon::new()?;
+    let receiver = mdns.browse(SERVICE_TYPE)?;
+    let deadline = std::time::Instant::now() + timeout;
+    let mut peers: Vec<PeerInfo> = Vec::new();
+
+    while let Ok(event) = receiver.recv_deadline(deadline) {
+        let mdns_sd::ServiceEvent::ServiceResolved(info) = event else { continue };
+        let Some((ring, hint)) = rings.iter().find_map(|r| parse_peer(&info, r, now_secs()).map(|p| (r, p))) else { continue };
+        if hint.device_id == my_device_id || peers.iter().any(|p| p.ip == hint.ip) {
+            continue;
+        }
+        let crypto = CryptoLayer::new(&ring.shared_secret);
+        let hello = match handshake::verify_peer(hint.ip, &crypto, &my_device_id, my_name, HANDSHAKE_TIMEOUT) {
+            Ok(hello) => hello,
+            Err(e) => {
+                warn!("⚠️ {} ({}) non verificato: {}", hint.name, hint.ip, e);
+                continue;
             }
+        };
+        let peer = PeerInfo { device_id: hello.device_id, name: hello.device_name, ..hint };
+        peers.retain(|p| p.device_id != peer.device_id);
+        let done = found(&peer);
+        peers.push(peer);
+        if done {
+            break;
         }
-        thread::sleep(Duration::from_millis(500));
-        
-        // OPZIONALE: Cleanup peer vecchi?
-        // Per ora ci basiamo sull'errore di connessione implementato in clipboard.rs e su mDNS remove.
     }
+    let _ = mdns.shutdown();
+    Ok(peers)
 }
 
 pub fn sanitize_device_name(name: &str) -> String {
@@ -0,0 +1,322 @@
+use crate::core::announce;
+use crate::core::config::{AppConfig, CompressionAlgorithm, CompressionConfig};
+use crate::core::crypto::{CryptoLayer, MAX_FUTURE_SECONDS};
+use crate::core::discovery::{self, SERVICE_TYPE, TCP_PORT};
+use crate::core::handshake::{self, HANDSHAKE_TIMEOUT};
+use crate::core::identity::{KeyProtection, RingIdentity};
+use crate::core::protocol::{self, ClipContent, ClipMeta};
+use crate::core::roster::now_secs;
+use crate::events::PeerInfo;
+use anyhow::{Result, ensure};
+use arboard::Clipboard;
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io::ErrorKind;
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::time::{Duration, Instant};
+
+// Diagnostica (`rust-clip doctor` e pagina della UI): controlla una per una le cause
+// più comuni di una sincronizzazione che non funziona e le riassume in un report.
+// È bloccante e dura qualche secondo (soprattutto l'attesa degli annunci mDNS).
+// Non invia clip a nessuno e non scrive nella clipboard: la legge soltanto.
+
+/// Tipo di servizio usato per la prova mDNS, diverso da quello vero: i peer non lo vedono
+const DOCTOR_SERVICE_TYPE: &str = "_rustclip-doctor._udp.local.";
+/// Attesa del nostro annuncio di prova
+const MDNS_WAIT: Duration = Duration::from_secs(3);
+/// Attesa degli annunci dei peer
+const BROWSE_WAIT: Duration = Duration::from_secs(3);
+/// Secondo tentativo dell'handshake con il timestamp indietro di tanto: un peer con
+/// l'orologio indietro rifiuta la prima apertura, che per lui viene "dal futuro"
+const PROBE_SHIFT_SECS: i64 = -50;
+/// Scarto d'orologio che non preoccupa (la misura ha la risoluzione del secondo)
+const CLOCK_OK_SECS: u64 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Status {
+    Ok,
+    Warn,
+    Fail,
+}
+
+impl Status {
+    pub fn icon(self) -> &'static str {
+        match self {
+            Status::Ok => "✅",
+            Status::Warn => "⚠️",
+            Status::Fail => "❌",
+        }
+    }
+}
+
+/// Esito di un singolo controllo
+#[derive(Debug, Clone)]
+pub struct Check {
+    pub name: String,
+    pub status: Status,
+    pub detail: String,
+}
+
+impl Check {
+    pub fn new(status: Status, name: impl Into<String>, detail: impl Into<String>) -> Self {
+        Self { name: name.into(), status, detail: detail.into() }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub checks: Vec<Check>,
+}
+
+impl Report {
+    /// Esito peggiore tra i controlli (Ok se non ce ne sono)
+    pub fn worst(&self) -> Status {
+        self.checks.iter().map(|c| c.status).max().unwrap_or(Status::Ok)
+    }
+
+    pub fn failures(&self) -> usize {
+        self.checks.iter().filter(|c| c.status == Status::Fail).count()
+    }
+
+    /// Report leggibile, una riga per controllo (stampato dalla CLI, copiato dalla UI)
+    pub fn render(&self) -> String {
+        let mut out = format!("{}\n\n", t!("doctor.title"));
+        for check in &self.checks {
+            out.push_str(&format!("{} {}: {}\n", check.status.icon(), check.name, check.detail));
+        }
+        out
+    }
+}
+
+/// Esegue tutti i controlli con l'identità salvata, sbloccata con `protection`
+pub fn run(protection: &KeyProtection, config: &AppConfig) -> Report {
+    let mut report = Report::default();
+    let my_device_id = RingIdentity::get_derived_device_id();
+
+    let rings = check_identity(&mut report, protection, config);
+    report.checks.push(check_port(&rings, &my_device_id));
+    report.checks.extend(check_mdns());
+    let (ring_check, peers) = browse_rings(&rings, &my_device_id);
+    report.checks.push(ring_check);
+    for (ring, peer) in &peers {
+        report.checks.push(check_clock(&rings[*ring], peer, &my_device_id));
+    }
+    report.checks.push(check_clipboard());
+    for ring in &rings {
+        let name = t!("doctor.loopback", ring = config.rings.name(&ring.discovery_id));
+        report.checks.push(match loopback(ring, &my_device_id) {
+            Ok(()) => Check::new(Status::Ok, name, t!("doctor.loopback_ok")),
+            Err(e) => Check::new(Status::Fail, name, e.to_string()),
+        });
+    }
+    report
+}
+
+/// Decifra identità e ring aggiuntivi; restituisce i ring attivi per gli altri controlli
+fn check_identity(report: &mut Report, protection: &KeyProtection, config: &AppConfig) -> Vec<RingIdentity> {
+    let identity = match RingIdentity::load_with(protection) {
+        Ok(identity) => {
+            report.checks.push(Check::new(Status::Ok, t!("doctor.identity"), t!("doctor.identity_ok")));
+            identity
+        }
+        Err(e) => {
+            report.checks.push(Check::new(Status::Fail, t!("doctor.identity"), e.to_string()));
+            return Vec::new();
+        }
+    };
+    let extra = match RingIdentity::load_extra_rings(protection) {
+        Ok(extra) => {
+            report.checks.push(Check::new(Status::Ok, t!("doctor.extra_rings"), t!("doctor.extra_rings_ok", count = extra.len())));
+            extra
+        }
+        Err(e) => {
+            report.checks.push(Check::new(Status::Fail, t!("doctor.extra_rings"), e.to_string()));
+            Vec::new()
+        }
+    };
+    let rings: Vec<RingIdentity> = std::iter::once(identity).chain(extra)
+        .filter(|r| config.rings.is_enabled(&r.discovery_id))
+        .collect();
+    if rings.is_empty() {
+        report.checks.push(Check::new(Status::Warn, t!("doctor.active_rings"), t!("doctor.no_active_rings")));
+    }
+    rings
+}
+
+/// La porta del listener è libera, oppure è di un RustClip in esecuzione (che risponde all'handshake)
+fn check_port(rings: &[RingIdentity], my_device_id: &str) -> Check {
+    let name = t!("doctor.port", port = TCP_PORT);
+    match TcpListener::bind(("0.0.0.0", TCP_PORT)) {
+        Ok(_) => Check::new(Status::Ok, name, t!("doctor.port_free")),
+        Err(e) if e.kind() == ErrorKind::AddrInUse => {
+            let local = SocketAddr::from(([127, 0, 0, 1], TCP_PORT));
+            let ours = rings.iter().any(|ring| {
+                handshake::probe(local, &CryptoLayer::new(&ring.shared_secret), my_device_id, 0, HANDSHAKE_TIMEOUT)
+                    .is_ok_and(|(hello, _)| hello.device_id == my_device_id)
+            });
+            if ours {
+                Check::new(Status::Ok, name, t!("doctor.port_ours"))
+            } else {
+                Check::new(Status::Fail, name, t!("doctor.port_taken"))
+            }
+        }
+        Err(e) => Check::new(Status::Fail, name, t!("doctor.port_error", err = e)),
+    }
+}
+
+/// Pubblica un servizio di prova e controlla da quali interfacce torna indietro:
+/// un'interfaccia che non lo riceve non vede nemmeno gli annunci dei peer
+fn check_mdns() -> Vec<Check> {
+    let interfaces: Vec<(String, IpAddr)> = local_ip_address::list_afinet_netifas()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|(_, ip)| ip.is_ipv4() && !ip.is_loopback())
+        .collect();
+    if interfaces.is_empty() {
+        return vec![Check::new(Status::Fail, "mDNS", t!("doctor.mdns_no_interfaces"))];
+    }
+    let received = match echo_announcement(interfaces.len()) {
+        Ok(received) => received,
+        Err(e) => return vec![Check::new(Status::Fail, "mDNS", t!("doctor.mdns_error", err = e))],
+    };
+    // Se non torna da nessuna parte è bloccato tutto il multicast, non una sola interfaccia
+    let missing = if received.is_empty() { Status::Fail } else { Status::Warn };
+    interfaces.into_iter()
+        .map(|(iface, ip)| {
+            let name = t!("doctor.mdns", iface = iface, ip = ip);
+            if received.contains(&ip) {
+                Check::new(Status::Ok, name, t!("doctor.mdns_ok"))
+            } else {
+                Check::new(missing, name, t!("doctor.mdns_silent"))
+            }
+        })
+        .collect()
+}
+
+/// Indirizzi con cui il nostro annuncio di prova è stato risolto
+fn echo_announcement(expected: usize) -> Result<HashSet<IpAddr>> {
+    let mdns = ServiceDaemon::new()?;
+    let instance_name = announce::random_instance_name();
+    let service = ServiceInfo::new(
+        DOCTOR_SERVICE_TYPE,
+        &instance_name,
+        &format!("{}.local.", instance_name),
+        "0.0.0.0",
+        TCP_PORT,
+        None::<HashMap<String, String>>,
+    )?.enable_addr_auto();
+    let fullname = service.get_fullname().to_string();
+    let receiver = mdns.browse(DOCTOR_SERVICE_TYPE)?;
+    mdns.register(service)?;
+
+    let deadline = Instant::now() + MDNS_WAIT;
+    let mut received = HashSet::new();
+    while let Ok(event) = receiver.recv_deadline(deadline) {
+        if let ServiceEvent::ServiceResolved(info) = event {
+            if info.get_fullname() == fullname {
+                received.extend(info.get_addresses().iter().copied());
+                if received.len() >= expected {
+                    break;
+                }
+            }
+        }
+    }
+    let _ = mdns.unregister(&fullname);
+    let _ = mdns.shutdown();
+    Ok(received)
+}
+
+/// Cerca gli annunci RustClip: quelli dei nostri ring (indice del ring e indizio del peer)
+/// e, se non ce n'è nessuno, quanti appartengono ad altri ring
+fn browse_rings(rings: &[RingIdentity], my_device_id: &str) -> (Check, Vec<(usize, PeerInfo)>) {
+    let name = t!("doctor.ring");
+    let browse = || -> Result<(Vec<(usize, PeerInfo)>, usize)> {
+        let mdns = ServiceDaemon::new()?;
+        let receiver = mdns.browse(SERVICE_TYPE)?;
+        let deadline = Instant::now() + BROWSE_WAIT;
+        let mut peers: Vec<(usize, PeerInfo)> = Vec::new();
+        let mut others = BTreeSet::new();
+        while let Ok(event) = receiver.recv_deadline(deadline) {
+            let ServiceEvent::ServiceResolved(info) = event else { continue };
+            let found = rings.iter().enumerate()
+                .find_map(|(i, ring)| discovery::parse_peer(&info, ring, now_secs()).map(|p| (i, p)));
+            match found {
+                Some((_, hint)) if hint.device_id == my_device_id => {}
+                Some((i, hint)) => {
+                    if !peers.iter().any(|(_, p)| p.ip == hint.ip) {
+                        peers.push((i, hint));
+                    }
+                }
+                None => {
+                    others.insert(info.get_fullname().to_string());
+                }
+            }
+        }
+        let _ = mdns.shutdown();
+        Ok((peers, others.len()))
+    };
+    match browse() {
+        Ok((peers, _)) if !peers.is_empty() => {
+            let check = Check::new(Status::Ok, name, t!("doctor.ring_peers", count = peers.len()));
+            (check, peers)
+        }
+        Ok((_, 0)) => (Check::new(Status::Warn, name, t!("doctor.ring_alone")), Vec::new()),
+        Ok((_, others)) => (Check::new(Status::Fail, name, t!("doctor.ring_mismatch", count = others)), Vec::new()),
+        Err(e) => (Check::new(Status::Fail, name, t!("doctor.mdns_error", err = e)), Vec::new()),
+    }
+}
+
+/// Scarto tra il nostro orologio e quello del peer, letto dal timestamp cifrato della sua risposta.
+/// Oltre `MAX_FUTURE_SECONDS` i clip del più avanti dei due vengono scartati come repliche.
+fn check_clock(ring: &RingIdentity, peer: &PeerInfo, my_device_id: &str) -> Check {
+    let crypto = CryptoLayer::new(&ring.shared_secret);
+    let measured = [0, PROBE_SHIFT_SECS].into_iter()
+        .find_map(|shift| handshake::probe(peer.ip, &crypto, my_device_id, shift, HANDSHAKE_TIMEOUT).ok());
+    let Some((hello, peer_time)) = measured else {
+        return Check::new(Status::Warn, t!("doctor.clock", name = peer.name), t!("doctor.clock_unreachable", ip = peer.ip));
+    };
+    let offset = peer_time as i64 - now_secs() as i64;
+    let status = match offset.unsigned_abs() {
+        secs if secs <= CLOCK_OK_SECS => Status::Ok,
+        secs if secs <= MAX_FUTURE_SECONDS => Status::Warn,
+        _ => Status::Fail,
+    };
+    let detail = t!("doctor.clock_offset", offset = format!("{:+}", offset), max = MAX_FUTURE_SECONDS);
+    Check::new(status, t!("doctor.clock", name = hello.device_name), detail)
+}
+
+/// Legge la clipboard (testo, altrimenti immagine) senza mai scriverci
+fn check_clipboard() -> Check {
+    let name = t!("doctor.clipboard");
+    let mut cb = match Clipboard::new() {
+        Ok(cb) => cb,
+        Err(e) => return Check::new(Status::Fail, name, e.to_string()),
+    };
+    let read = match cb.get_text() {
+        Err(arboard::Error::ContentNotAvailable) => cb.get_image().map(|_| ()),
+        other => other.map(|_| ()),
+    };
+    match read {
+        Ok(()) => Check::new(Status::Ok, name, t!("doctor.clipboard_ok")),
+        // Vuota o con un formato che non sincronizziamo: l'accesso comunque funziona
+        Err(arboard::Error::ContentNotAvailable) => Check::new(Status::Ok, name, t!("doctor.clipboard_empty")),
+        Err(e) => Check::new(Status::Fail, name, e.to_string()),
+    }
+}
+
+/// Un clip e un handshake cifrati e riaperti con la chiave del ring, senza passare dalla rete
+pub fn loopback(ring: &RingIdentity, my_device_id: &str) -> Result<()> {
+    let crypto = CryptoLayer::new(&ring.shared_secret);
+    // Abbastanza lungo da superare la soglia di compressione
+    let sample = ClipContent::Text("RustClip doctor ".repeat(128));
+    let clip = protocol::encode_clip(&sample, &ClipMeta::default())?;
+    let frame = protocol::seal_frame(&clip, Some(CompressionAlgorithm::Zstd), &CompressionConfig::default(), &crypto)?;
+    let header: [u8; 4] = frame[..4].try_into()?;
+    let (content, _) = protocol::decode_clip(&protocol::open_frame(header, &frame[4..], &crypto)?)?;
+    ensure!(content == sample, "Il clip riaperto non coincide con l'originale");
+
+    let hello = handshake::opening(my_device_id, "");
+    let sealed = protocol::seal_hello(&hello, &crypto)?;
+    ensure!(protocol::open_hello(&sealed[4..], &crypto)? == hello, "L'handshake riaperto non coincide con l'originale");
+    Ok(())
+}
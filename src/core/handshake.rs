@@ -0,0 +1,93 @@
+use crate::core::crypto::CryptoLayer;
+use crate::core::protocol::{self, Hello};
+use crate::core::roster::now_secs;
+use anyhow::{Result, anyhow};
+use rand::{RngCore, thread_rng};
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+// Handshake di presentazione: chi trova un peer in mDNS si connette e gli chiede chi è.
+// I due messaggi sono cifrati con la chiave del ring e la risposta rimanda il nonce
+// dell'apertura, quindi prova che dall'altra parte c'è, adesso, un membro del ring.
+// Nome e device_id annunciati in mDNS restano solo un indizio su dove cercare.
+//
+// Si fida solo chi apre: una presentazione ricevuta senza averla chiesta
+// potrebbe essere la copia di un'altra, e chi la riceve ne verifica il mittente aprendo a sua volta.
+
+/// Tempo concesso al peer per rispondere
+pub const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Un hello legittimo sta in poche centinaia di byte
+pub const MAX_HELLO_SIZE: usize = 4096;
+
+/// Messaggio di apertura con un nonce fresco
+pub fn opening(device_id: &str, device_name: &str) -> Hello {
+    let mut nonce = [0u8; 16];
+    thread_rng().fill_bytes(&mut nonce);
+    Hello { device_id: device_id.to_string(), device_name: device_name.to_string(), nonce, echo: None }
+}
+
+/// Risposta del listener a un hello ricevuto nel frame `body`, cifrata con la stessa chiave.
+/// Fallisce se il frame non è un'apertura di questo ring.
+pub fn answer(body: &[u8], crypto: &CryptoLayer, device_id: &str, device_name: &str) -> Result<Vec<u8>> {
+    let hello = protocol::open_hello(body, crypto)?;
+    if hello.echo.is_some() {
+        return Err(anyhow!("Atteso un messaggio di apertura"));
+    }
+    let reply = Hello { echo: Some(hello.nonce), ..opening(device_id, device_name) };
+    protocol::seal_hello(&reply, crypto)
+}
+
+/// Controlla che `reply` risponda proprio alla nostra apertura e non sia la nostra stessa risposta rimbalzata
+pub fn check_reply(opening: &Hello, reply: &Hello) -> Result<()> {
+    if reply.echo != Some(opening.nonce) {
+        return Err(anyhow!("La risposta non corrisponde all'handshake"));
+    }
+    if reply.device_id == opening.device_id {
+        return Err(anyhow!("Handshake con sé stessi"));
+    }
+    Ok(())
+}
+
+/// Si presenta al peer in `addr` e restituisce la sua identità verificata (bloccante)
+pub fn verify_peer(addr: SocketAddr, crypto: &CryptoLayer, device_id: &str, device_name: &str, timeout: Duration) -> Result<Hello> {
+    let hello = opening(device_id, device_name);
+    let body = exchange(addr, &protocol::seal_hello(&hello, crypto)?, timeout)?;
+    let reply = protocol::open_hello(&body, crypto)?;
+    check_reply(&hello, &reply)?;
+    Ok(reply)
+}
+
+/// Handshake diagnostico: restituisce la risposta e l'ora dell'orologio del peer.
+/// `shift_secs` sposta il timestamp della nostra apertura, per farsi accettare anche da un
+/// orologio indietro; la risposta non passa dal controllo anti-replay e può venire da noi stessi.
+pub fn probe(addr: SocketAddr, crypto: &CryptoLayer, device_id: &str, shift_secs: i64, timeout: Duration) -> Result<(Hello, u64)> {
+    let hello = opening(device_id, "");
+    let timestamp = now_secs().saturating_add_signed(shift_secs);
+    let body = exchange(addr, &protocol::seal_hello_at(&hello, crypto, timestamp)?, timeout)?;
+    let (peer_time, plain) = crypto.decrypt_with_time(&body)?;
+    let reply = protocol::decode_hello(&plain)?;
+    if reply.echo != Some(hello.nonce) {
+        return Err(anyhow!("La risposta non corrisponde all'handshake"));
+    }
+    Ok((reply, peer_time))
+}
+
+/// Invia un'apertura già cifrata e legge il corpo della risposta
+fn exchange(addr: SocketAddr, frame: &[u8], timeout: Duration) -> Result<Vec<u8>> {
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    stream.write_all(frame)?;
+
+    let mut header = [0u8; 4];
+    stream.read_exact(&mut header)?;
+    let (len, _) = protocol::parse_frame_header(header)?;
+    if !protocol::is_hello(header) || len > MAX_HELLO_SIZE {
+        return Err(anyhow!("Risposta di handshake non valida"));
+    }
+    let mut body = vec![0u8; len];
+    stream.read_exact(&mut body)?;
+    Ok(body)
+}
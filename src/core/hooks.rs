@@ -0,0 +1,224 @@
+use crate::core::config::HookConfig;
+use crate::core::protocol::ClipContent;
+use anyhow::{Context, Result, anyhow};
+use rand::{Rng, thread_rng};
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::process::{Child, Command, Stdio};
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+// Hook utente eseguiti all'invio (broadcast) e alla ricezione (run_server) di un clip.
+// Contratto con lo script:
+//   - stdin: il contenuto (testo UTF-8 o immagine codificata)
+//   - env:   RUSTCLIP_EVENT, RUSTCLIP_KIND, RUSTCLIP_SIZE, ... (vedi `HookContext`)
+//   - exit 0 = ok; se `rewrite` è attivo e stdout non è vuoto, stdout sostituisce il testo
+//   - exit != 0 = veto, il clip non viene inviato / scritto
+// Il comando gira in una cartella temporanea vuota, cancellata alla fine.
+// Shell e figli stanno in un gruppo di processi (job object su Windows): allo scadere del
+// timeout viene terminato l'intero gruppo, non solo la shell.
+
+const MAX_OUTPUT: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookEvent {
+    Send,
+    Receive,
+}
+
+impl HookEvent {
+    fn as_str(&self) -> &'static str {
+        match self {
+            HookEvent::Send => "send",
+            HookEvent::Receive => "receive",
+        }
+    }
+}
+
+/// Metadati passati allo script come variabili d'ambiente
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub event: HookEvent,
+    pub device_name: String,
+    /// Send: nomi dei peer destinatari; Receive: indirizzo del mittente
+    pub peers: Vec<String>,
+    pub sensitive: bool,
+    pub expire_secs: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookOutcome {
+    /// Il clip prosegue invariato
+    Keep,
+    /// Il clip prosegue con il testo riscritto dallo script
+    Replace(String),
+    /// Lo script ha rifiutato il clip (exit code)
+    Veto(i32),
+}
+
+/// Esegue il hook. Bloccante: da chiamare in spawn_blocking.
+/// Err = hook non eseguibile o scaduto (il chiamante decide se proseguire).
+pub fn run_hook(hook: &HookConfig, timeout: Duration, content: &ClipContent, ctx: &HookContext) -> Result<HookOutcome> {
+    let (kind, input): (&str, &[u8]) = match content {
+        ClipContent::Text(text) => ("text", text.as_bytes()),
+        ClipContent::Image(png) => ("image", png.as_slice()),
+    };
+
+    let workdir = Sandbox::create()?;
+    let mut child = shell(&hook.command)
+        .current_dir(&workdir.0)
+        .env("RUSTCLIP_EVENT", ctx.event.as_str())
+        .env("RUSTCLIP_KIND", kind)
+        .env("RUSTCLIP_SIZE", input.len().to_string())
+        .env("RUSTCLIP_DEVICE", &ctx.device_name)
+        .env("RUSTCLIP_PEERS", ctx.peers.join(","))
+        .env("RUSTCLIP_SENSITIVE", if ctx.sensitive { "1" } else { "0" })
+        .env("RUSTCLIP_EXPIRE_SECS", ctx.expire_secs.map(|s| s.to_string()).unwrap_or_default())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Impossibile avviare il hook '{}'", hook.command))?;
+    let group = ProcessGroup::attach(&child);
+
+    // stdin e stdout in thread separati: uno script che non legge l'input non deve bloccarci
+    let mut stdin = child.stdin.take().ok_or_else(|| anyhow!("stdin non disponibile"))?;
+    let data = input.to_vec();
+    // Nessun join sul writer: un figlio che eredita stdin senza leggerlo lo terrebbe bloccato
+    std::thread::spawn(move || { let _ = stdin.write_all(&data); });
+    let mut stdout = child.stdout.take().ok_or_else(|| anyhow!("stdout non disponibile"))?;
+    let (tx_output, rx_output) = flume::bounded(1);
+    std::thread::spawn(move || {
+        let mut out = Vec::new();
+        let _ = (&mut stdout).take(MAX_OUTPUT as u64).read_to_end(&mut out);
+        let _ = tx_output.send(out);
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() > deadline {
+            group.kill(&mut child);
+            let _ = child.wait();
+            return Err(anyhow!("Hook scaduto dopo {} s", timeout.as_secs()));
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    };
+    // La shell è uscita ma un figlio in background può tenere aperto stdout:
+    // stessa scadenza, poi il gruppo viene terminato e l'output scartato
+    let output = match rx_output.recv_deadline(deadline) {
+        Ok(out) => out,
+        Err(_) => {
+            group.kill(&mut child);
+            warn!("Hook: stdout ancora aperto alla scadenza, output scartato");
+            Vec::new()
+        }
+    };
+
+    if !status.success() {
+        return Ok(HookOutcome::Veto(status.code().unwrap_or(-1)));
+    }
+    if hook.rewrite && kind == "text" && !output.is_empty() {
+        let text = String::from_utf8(output).context("Output del hook non UTF-8")?;
+        return Ok(HookOutcome::Replace(text.trim_end_matches(['\r', '\n']).to_string()));
+    }
+    Ok(HookOutcome::Keep)
+}
+
+#[cfg(unix)]
+fn shell(command: &str) -> Command {
+    use std::os::unix::process::CommandExt;
+    let mut cmd = Command::new("sh");
+    // Gruppo nuovo con pgid = pid della shell
+    cmd.arg("-c").arg(command).process_group(0);
+    cmd
+}
+
+#[cfg(windows)]
+fn shell(command: &str) -> Command {
+    use std::os::windows::process::CommandExt;
+    const CREATE_NO_WINDOW: u32 = 0x0800_0000;
+    let mut cmd = Command::new("cmd");
+    cmd.arg("/C").arg(command).creation_flags(CREATE_NO_WINDOW);
+    cmd
+}
+
+/// Gruppo di processi del hook: la shell e tutto ciò che avvia
+#[cfg(unix)]
+struct ProcessGroup(libc::pid_t);
+
+#[cfg(unix)]
+impl ProcessGroup {
+    fn attach(child: &Child) -> Self {
+        Self(child.id() as libc::pid_t)
+    }
+
+    fn kill(&self, child: &mut Child) {
+        // pid negativo = tutto il gruppo
+        if unsafe { libc::kill(-self.0, libc::SIGKILL) } != 0 {
+            let _ = child.kill();
+        }
+    }
+}
+
+/// Job object con il processo del hook: i figli lanciati da cmd ci entrano automaticamente
+#[cfg(windows)]
+struct ProcessGroup(Option<windows::Win32::Foundation::HANDLE>);
+
+#[cfg(windows)]
+impl ProcessGroup {
+    fn attach(child: &Child) -> Self {
+        use std::os::windows::io::AsRawHandle;
+        use windows::Win32::Foundation::{CloseHandle, HANDLE};
+        use windows::Win32::System::JobObjects::{AssignProcessToJobObject, CreateJobObjectW};
+        unsafe {
+            let Ok(job) = CreateJobObjectW(None, windows::core::PCWSTR::null()) else { return Self(None) };
+            if AssignProcessToJobObject(job, HANDLE(child.as_raw_handle() as isize)).is_err() {
+                let _ = CloseHandle(job);
+                return Self(None);
+            }
+            Self(Some(job))
+        }
+    }
+
+    fn kill(&self, child: &mut Child) {
+        use windows::Win32::System::JobObjects::TerminateJobObject;
+        match self.0 {
+            Some(job) if unsafe { TerminateJobObject(job, 1) }.is_ok() => {}
+            _ => { let _ = child.kill(); }
+        }
+    }
+}
+
+#[cfg(windows)]
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        if let Some(job) = self.0 {
+            let _ = unsafe { windows::Win32::Foundation::CloseHandle(job) };
+        }
+    }
+}
+
+/// Cartella di lavoro usa e getta (rimossa al drop)
+struct Sandbox(PathBuf);
+
+impl Sandbox {
+    fn create() -> Result<Self> {
+        let dir = std::env::temp_dir().join(format!("rustclip-hook-{:016x}", thread_rng().gen::<u64>()));
+        std::fs::create_dir(&dir).context("Impossibile creare la cartella del hook")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700))?;
+        }
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}
@@ -6,45 +6,140 @@ use std::fs;
 use std::path::PathBuf;
 use sha2::{Sha256, Digest};
 use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
 use aes_gcm::{
     aead::{Aead, KeyInit}, 
     Aes256Gcm, Nonce 
 };
 use machine_uid;
-use directories::ProjectDirs;
+use directories::UserDirs;
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+use std::fmt;
+use crate::core::{config, vault};
+use tracing::{debug, info};
 
-#[derive(Clone, Debug)]
+// Magic del file identità protetto da passphrase (i file legacy iniziano direttamente col nonce)
+const PASSPHRASE_MAGIC: &[u8; 4] = b"RCI1";
+// Magic dei file di backup portabili
+const BACKUP_MAGIC: &[u8; 4] = b"RCB1";
+const BACKUP_VERSION: u32 = 1;
+
+/// Durata di un tag di annuncio del ring: poi cambia, e chi non è nel ring non può collegare i due
+pub const TAG_PERIOD_SECS: u64 = 15 * 60;
+
+// I segreti vengono azzerati quando l'ultima copia viene droppata
+#[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct RingIdentity {
     pub mnemonic: String,
     pub discovery_id: String,     
     pub shared_secret: [u8; 32],  
 }
 
-#[derive(Serialize, Deserialize)]
+impl fmt::Debug for RingIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RingIdentity")
+            .field("discovery_id", &self.discovery_id)
+            .field("mnemonic", &"<redacted>")
+            .field("shared_secret", &"<redacted>")
+            .finish()
+    }
+}
+
+/// Stringa segreta (passphrase, mnemonic): azzerata al drop e mai stampata in Debug
+#[derive(Clone, Default, Zeroize, ZeroizeOnDrop)]
+pub struct SecretString(String);
+
+impl SecretString {
+    pub fn new(value: String) -> Self {
+        Self(value)
+    }
+
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl fmt::Debug for SecretString {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretString(<redacted>)")
+    }
+}
+
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
 struct StoredIdentity {
     mnemonic: String,
 }
 
-impl RingIdentity {
-    pub fn create_new() -> Result<Self> {
-        let mut entropy = [0u8; 32];
-        thread_rng().fill_bytes(&mut entropy);
+/// Ring aggiuntivi, attivi insieme a quello principale (`rings.enc`)
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct StoredRings {
+    mnemonics: Vec<String>,
+}
 
-        let mnemonic = Mnemonic::from_entropy_in(Language::English, &entropy)?;
-        let phrase = mnemonic.to_string();
+/// Contenuto (in chiaro) di un backup portabile dell'identità
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+pub struct IdentityBackup {
+    pub version: u32,
+    pub mnemonic: String,
+    pub device_name: String,
+    /// Mnemonic dei ring aggiuntivi (None = backup di una versione che non li salvava)
+    #[serde(default)]
+    pub extra_rings: Option<Vec<String>>,
+}
 
-        println!("Nuovo Ring Creato");
-        
-        let identity = Self::from_mnemonic(&phrase)?;
+impl IdentityBackup {
+    /// Ring aggiuntivi del backup; None se il backup non li contiene
+    pub fn extra_rings(&self) -> Result<Option<Vec<RingIdentity>>> {
+        self.extra_rings.as_ref()
+            .map(|mnemonics| mnemonics.iter().map(|m| RingIdentity::from_mnemonic(m)).collect())
+            .transpose()
+    }
+}
+
+/// Come viene protetto `identity.enc` su disco
+#[derive(Clone, Debug)]
+pub enum KeyProtection {
+    /// Chiave derivata dal Machine ID (default, nessun prompt)
+    Machine,
+    /// Chiave derivata da una passphrase utente tramite Argon2id
+    Passphrase(SecretString),
+}
+
+impl KeyProtection {
+    pub fn is_passphrase(&self) -> bool {
+        matches!(self, KeyProtection::Passphrase(_))
+    }
+}
+
+impl RingIdentity {
+    pub fn create_new() -> Result<Self> {
+        let identity = Self::generate()?;
         identity.save()?;
         Ok(identity)
     }
 
+    /// Genera un nuovo Ring senza salvarlo (il chiamante sceglie la protezione)
+    pub fn generate() -> Result<Self> {
+        let mut entropy = Zeroizing::new([0u8; 32]);
+        thread_rng().fill_bytes(entropy.as_mut());
+
+        let mnemonic = Mnemonic::from_entropy_in(Language::English, entropy.as_slice())?;
+        let phrase = Zeroizing::new(mnemonic.to_string());
+
+        info!("Nuovo Ring Creato");
+
+        Self::from_mnemonic(&phrase)
+    }
+
     pub fn from_mnemonic(phrase: &str) -> Result<Self> {
         let mnemonic = Mnemonic::parse_in_normalized(Language::English, phrase)
             .context("Parole non valide")?;
         
-        let entropy = mnemonic.to_entropy(); 
+        let entropy = Zeroizing::new(mnemonic.to_entropy()); 
 
         let hkdf = Hkdf::<Sha256>::new(None, &entropy);
         let mut discovery_bytes = [0u8; 32];
@@ -53,24 +148,42 @@ impl RingIdentity {
         
         let discovery_id = hex::encode(&discovery_bytes[0..16]);
 
-        let mut secret_bytes = [0u8; 32];
-        hkdf.expand(b"rustclip_secret_v1", &mut secret_bytes)
+        let mut secret_bytes = Zeroizing::new([0u8; 32]);
+        hkdf.expand(b"rustclip_secret_v1", secret_bytes.as_mut())
             .map_err(|_| anyhow!("HKDF error"))?;
 
         Ok(RingIdentity {
             mnemonic: phrase.to_string(),
             discovery_id,
-            shared_secret: secret_bytes,
+            shared_secret: *secret_bytes,
         })
     }
 
-    fn get_machine_key() -> Result<[u8; 32]> {
+    /// Tag del ring per il periodo `epoch` (vedi `tag_epoch`): HMAC con una chiave derivata
+    /// dal segreto, quindi solo i membri sanno calcolarlo e riconoscerlo
+    pub fn announce_tag(&self, epoch: u64) -> String {
+        let hkdf = Hkdf::<Sha256>::new(None, &self.shared_secret);
+        let mut key = Zeroizing::new([0u8; 32]);
+        hkdf.expand(b"rustclip_announce_v1", key.as_mut())
+            .expect("32 byte sono una lunghezza valida per HKDF");
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(key.as_ref()).expect("HMAC accetta chiavi di ogni lunghezza");
+        mac.update(&epoch.to_be_bytes());
+        hex::encode(&mac.finalize().into_bytes()[..8])
+    }
+
+    /// Tag accettati all'istante `now`: anche quelli dei periodi accanto, per gli orologi non allineati
+    pub fn accepted_tags(&self, now: u64) -> [String; 3] {
+        let epoch = tag_epoch(now);
+        [self.announce_tag(epoch), self.announce_tag(epoch.saturating_sub(1)), self.announce_tag(epoch + 1)]
+    }
+
+    fn get_machine_key() -> Result<Zeroizing<[u8; 32]>> {
         let machine_id = machine_uid::get()
             .map_err(|e| anyhow!("Impossibile leggere Machine ID: {}", e))?;
         
         let hkdf = Hkdf::<Sha256>::new(None, machine_id.as_bytes());
-        let mut key = [0u8; 32];
-        hkdf.expand(b"rustclip_storage_key", &mut key)
+        let mut key = Zeroizing::new([0u8; 32]);
+        hkdf.expand(b"rustclip_storage_key", key.as_mut())
             .map_err(|_| anyhow!("Key expansion failed"))?;
         
         Ok(key)
@@ -78,91 +191,212 @@ impl RingIdentity {
 
     // Funzione per ottenere il percorso assoluto e stabile su tutti gli OS
     fn get_identity_path() -> Result<PathBuf> {
-        let proj = ProjectDirs::from("com", "rustclip", "rust-clip")
-            .ok_or_else(|| anyhow::anyhow!("Impossibile determinare cartella home"))?;
-        
-        let config_dir = proj.config_dir();
-        if !config_dir.exists() {
-            fs::create_dir_all(config_dir)?;
-        }
-        
-        Ok(config_dir.join("identity.enc"))
+        Ok(config::config_dir()?.join("identity.enc"))
     }
 
-    // --- FUNZIONE CHE MANCAVA ---
+    /// ID stabile di questo dispositivo (vedi `device_id_from_machine`).
+    /// Viaggia solo cifrato con la chiave del ring: negli annunci, nell'handshake e nei clip.
     pub fn get_derived_device_id() -> String {
         let machine_id = machine_uid::get().unwrap_or_else(|_| "unknown_device".to_string());
-        
-        let mut hasher = Sha256::new();
-        hasher.update(machine_id.as_bytes());
-        let result = hasher.finalize();
-        
-        // Primi 4 caratteri hex dell'hash
-        hex::encode(&result[0..2])
+        device_id_from_machine(&machine_id)
     }
-    // ----------------------------
 
     pub fn save(&self) -> Result<()> {
+        self.save_with(&KeyProtection::Machine)
+    }
+
+    /// Salva l'identità con la protezione indicata (usato anche per migrare tra le modalità)
+    pub fn save_with(&self, protection: &KeyProtection) -> Result<()> {
         let stored = StoredIdentity { mnemonic: self.mnemonic.clone() };
-        let json = serde_json::to_string(&stored)?;
+        let json = Zeroizing::new(serde_json::to_string(&stored)?);
+        let file_content = Self::seal_file(json.as_bytes(), protection)?;
 
-        let key_bytes = Self::get_machine_key()?;
-        let cipher = Aes256Gcm::new(&key_bytes.into());
-        
-        let mut nonce_bytes = [0u8; 12];
-        thread_rng().fill_bytes(&mut nonce_bytes);
-        let nonce = Nonce::from_slice(&nonce_bytes);
+        let path = Self::get_identity_path()?;
+        write_private_file(&path, &file_content)?;
 
-        let ciphertext = cipher.encrypt(nonce, json.as_bytes())
-            .map_err(|_| anyhow!("File encryption error"))?;
+        info!("🔒 Identità salvata in {:?}", path);
+        Ok(())
+    }
 
-        let mut file_content = Vec::new();
-        file_content.extend_from_slice(&nonce_bytes);
-        file_content.extend_from_slice(&ciphertext);
+    /// Salva i ring aggiuntivi, con la stessa protezione dell'identità principale
+    pub fn save_extra_rings(rings: &[RingIdentity], protection: &KeyProtection) -> Result<()> {
+        let stored = StoredRings { mnemonics: rings.iter().map(|r| r.mnemonic.clone()).collect() };
+        let json = Zeroizing::new(serde_json::to_string(&stored)?);
+        let file_content = Self::seal_file(json.as_bytes(), protection)?;
+        write_private_file(&Self::get_rings_path()?, &file_content)
+    }
 
+    /// Passa alla nuova protezione identità e ring aggiuntivi insieme: `rings.enc` viene
+    /// riaperto con la vecchia protezione e risigillato prima di riscrivere `identity.enc`.
+    pub fn change_protection(&self, old: &KeyProtection, new: &KeyProtection) -> Result<()> {
+        let rings = Self::load_extra_rings(old).context("Impossibile aprire i ring aggiuntivi")?;
+        Self::save_extra_rings(&rings, new)?;
+        self.save_with(new)
+    }
+
+    /// Ring aggiuntivi salvati (nessun file = nessun ring aggiuntivo)
+    pub fn load_extra_rings(protection: &KeyProtection) -> Result<Vec<RingIdentity>> {
+        let path = Self::get_rings_path()?;
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let plaintext = Self::open_file(&fs::read(path)?, protection)?;
+        let stored: StoredRings = serde_json::from_slice(&plaintext)?;
+        stored.mnemonics.iter().map(|m| Self::from_mnemonic(m)).collect()
+    }
+
+    fn get_rings_path() -> Result<PathBuf> {
+        Ok(Self::get_identity_path()?.with_file_name("rings.enc"))
+    }
+
+    /// Cifra il contenuto di un file segreto con la chiave macchina o con la passphrase
+    fn seal_file(json: &[u8], protection: &KeyProtection) -> Result<Vec<u8>> {
+        let file_content = match protection {
+            KeyProtection::Machine => {
+                let key_bytes = Self::get_machine_key()?;
+                let cipher = Aes256Gcm::new(key_bytes.as_slice().into());
+
+                let mut nonce_bytes = [0u8; 12];
+                thread_rng().fill_bytes(&mut nonce_bytes);
+                let nonce = Nonce::from_slice(&nonce_bytes);
+
+                let ciphertext = cipher.encrypt(nonce, json)
+                    .map_err(|_| anyhow!("File encryption error"))?;
+
+                let mut file_content = Vec::new();
+                file_content.extend_from_slice(&nonce_bytes);
+                file_content.extend_from_slice(&ciphertext);
+                file_content
+            },
+            KeyProtection::Passphrase(pass) => {
+                vault::seal(PASSPHRASE_MAGIC, json, pass.expose(), vault::KdfParams::default())?
+            }
+        };
+        Ok(file_content)
+    }
+
+    /// Inverso di `seal_file`: riconosce dal magic se il file è protetto da passphrase
+    fn open_file(file_content: &[u8], protection: &KeyProtection) -> Result<Zeroizing<Vec<u8>>> {
+        Ok(Zeroizing::new(if vault::has_magic(PASSPHRASE_MAGIC, file_content) {
+            match protection {
+                KeyProtection::Passphrase(pass) => vault::open(PASSPHRASE_MAGIC, file_content, pass.expose())?,
+                KeyProtection::Machine => return Err(anyhow!("Identità protetta da passphrase")),
+            }
+        } else {
+            if file_content.len() < 12 {
+                return Err(anyhow!("File identità corrotto"));
+            }
+
+            let (nonce_bytes, ciphertext) = file_content.split_at(12);
+            let nonce = Nonce::from_slice(nonce_bytes);
+
+            let key_bytes = Self::get_machine_key()?;
+            let cipher = Aes256Gcm::new(key_bytes.as_slice().into());
+
+            cipher.decrypt(nonce, ciphertext)
+                .map_err(|_| anyhow!("Decifrazione fallita!"))?
+        }))
+    }
+
+    /// Vero se su disco esiste già un file identità (protetto o no)
+    pub fn exists() -> Result<bool> {
+        Ok(Self::get_identity_path()?.exists())
+    }
+
+    /// Vero se su disco esiste un'identità protetta da passphrase
+    pub fn is_passphrase_protected() -> Result<bool> {
         let path = Self::get_identity_path()?;
+        if !path.exists() {
+            return Ok(false);
+        }
+        let file_content = fs::read(path)?;
+        Ok(vault::has_magic(PASSPHRASE_MAGIC, &file_content))
+    }
+
+    /// Esporta un backup cifrato con passphrase, da spostare su un altro dispositivo.
+    /// Contiene anche i ring aggiuntivi (`rings.enc`).
+    pub fn export_backup(&self, extra_rings: &[RingIdentity], device_name: &str, passphrase: &str, params: vault::KdfParams) -> Result<Vec<u8>> {
+        let backup = IdentityBackup {
+            version: BACKUP_VERSION,
+            mnemonic: self.mnemonic.clone(),
+            device_name: device_name.to_string(),
+            extra_rings: Some(extra_rings.iter().map(|r| r.mnemonic.clone()).collect()),
+        };
+        let json = Zeroizing::new(serde_json::to_vec(&backup)?);
+        vault::seal(BACKUP_MAGIC, &json, passphrase, params)
+    }
 
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            fs::write(&path, &file_content)?;
-            let mut perms = fs::metadata(&path)?.permissions();
-            perms.set_mode(0o600); 
-            fs::set_permissions(&path, perms)?;
+    /// Decifra un backup e ricostruisce l'identità (validando il mnemonic)
+    pub fn import_backup(data: &[u8], passphrase: &str) -> Result<(Self, IdentityBackup)> {
+        if !vault::has_magic(BACKUP_MAGIC, data) {
+            return Err(anyhow!("Il file non è un backup RustClip"));
         }
-        #[cfg(not(unix))]
-        {
-            fs::write(&path, &file_content)?;
+        let plaintext = Zeroizing::new(vault::open(BACKUP_MAGIC, data, passphrase)?);
+        let backup: IdentityBackup = serde_json::from_slice(&plaintext)
+            .context("Backup corrotto")?;
+        if backup.version > BACKUP_VERSION {
+            return Err(anyhow!("Backup creato da una versione più recente di RustClip"));
         }
+        let identity = Self::from_mnemonic(&backup.mnemonic)?;
+        backup.extra_rings().context("Ring aggiuntivo non valido nel backup")?;
+        Ok((identity, backup))
+    }
 
-        println!("🔒 Identità salvata in {:?}", path);
-        Ok(())
+    /// Percorso proposto per l'export (Documenti se disponibile, altrimenti home)
+    pub fn default_backup_path() -> PathBuf {
+        let dir = UserDirs::new()
+            .map(|u| u.document_dir().unwrap_or(u.home_dir()).to_path_buf())
+            .unwrap_or_default();
+        dir.join("rustclip-identity.rcbackup")
     }
 
     pub fn load() -> Result<Self> {
+        Self::load_with(&KeyProtection::Machine)
+    }
+
+    pub fn load_with(protection: &KeyProtection) -> Result<Self> {
         let path = Self::get_identity_path()?;
-        println!("🔑 Identity Path: {:?}", path);
+        debug!("🔑 Identity Path: {:?}", path);
 
         if !path.exists() {
             return Err(anyhow!("Nessuna identità trovata in {:?}", path));
         }
 
         let file_content = fs::read(path)?;
-        if file_content.len() < 12 {
-            return Err(anyhow!("File identità corrotto"));
-        }
-
-        let (nonce_bytes, ciphertext) = file_content.split_at(12);
-        let nonce = Nonce::from_slice(nonce_bytes);
-
-        let key_bytes = Self::get_machine_key()?;
-        let cipher = Aes256Gcm::new(&key_bytes.into());
-
-        let plaintext = cipher.decrypt(nonce, ciphertext)
-            .map_err(|_| anyhow!("Decifrazione fallita!"))?;
+        let plaintext = Self::open_file(&file_content, protection)?;
 
         let stored: StoredIdentity = serde_json::from_slice(&plaintext)?;
         
         Self::from_mnemonic(&stored.mnemonic)
     }
-}
\ No newline at end of file
+}
+
+/// device_id derivato dal machine id, con un prefisso di dominio (il machine id non va in rete)
+pub fn device_id_from_machine(machine_id: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(b"rustclip-device-id");
+    hasher.update(machine_id.as_bytes());
+    hex::encode(&hasher.finalize()[..16])
+}
+
+/// Periodo di validità dei tag di annuncio in cui cade l'istante `now` (secondi Unix)
+pub fn tag_epoch(now: u64) -> u64 {
+    now / TAG_PERIOD_SECS
+}
+
+/// Scrive un file leggibile solo dall'utente corrente (0600 su Unix)
+pub fn write_private_file(path: &std::path::Path, content: &[u8]) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::write(path, content)?;
+        let mut perms = fs::metadata(path)?.permissions();
+        perms.set_mode(0o600); 
+        fs::set_permissions(path, perms)?;
+    }
+    #[cfg(not(unix))]
+    {
+        fs::write(path, content)?;
+    }
+    Ok(())
+}
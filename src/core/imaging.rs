@@ -0,0 +1,158 @@
+use crate::core::config::{ImageCodec, ImageConfig, ImagePreference};
+use anyhow::{Result, anyhow};
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::imageops::{self, FilterType};
+use image::{ColorType, ImageEncoder, RgbaImage};
+
+// Codifica delle immagini catturate dalla clipboard.
+// Si parte sempre dai pixel originali (RGBA): ridimensionamento al lato massimo,
+// codifica nel formato scelto e, se il risultato supera il limite di byte,
+// nuovi tentativi abbassando prima la qualità (codec lossy) e poi la risoluzione.
+
+/// Limite usato quando la config non ne imposta uno: sta sotto il MAX_PACKET_SIZE del server
+pub const DEFAULT_MAX_BYTES: usize = 48 * 1024 * 1024;
+/// Sotto questo lato non riduciamo oltre: meglio scartare l'immagine
+const MIN_DIMENSION: u32 = 64;
+const MIN_QUALITY: u8 = 40;
+const QUALITY_STEP: u8 = 15;
+
+/// Pixel RGBA8 così come arrivano dalla clipboard
+#[derive(Debug, Clone)]
+pub struct RawImage {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+/// Parametri effettivi di codifica verso un peer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ImageSettings {
+    pub codec: ImageCodec,
+    pub quality: u8,
+    pub max_dimension: u32,
+    pub max_bytes: usize,
+}
+
+impl ImageSettings {
+    pub fn from_config(config: &ImageConfig) -> Self {
+        let max_bytes = match config.max_kib {
+            0 => DEFAULT_MAX_BYTES,
+            kib => (kib as usize * 1024).min(DEFAULT_MAX_BYTES),
+        };
+        Self { codec: config.codec, quality: config.quality.clamp(1, 100), max_dimension: config.max_dimension, max_bytes }
+    }
+
+    /// Codec e qualità li sceglie il peer; per il lato massimo vince il limite più stretto
+    pub fn for_peer(&self, preference: Option<&ImagePreference>) -> Self {
+        let Some(pref) = preference else { return *self };
+        let max_dimension = match (self.max_dimension, pref.max_dimension) {
+            (0, b) => b,
+            (a, 0) => a,
+            (a, b) => a.min(b),
+        };
+        Self { codec: pref.codec, quality: pref.quality.clamp(1, 100), max_dimension, max_bytes: self.max_bytes }
+    }
+}
+
+pub fn codec_name(codec: ImageCodec) -> &'static str {
+    match codec {
+        ImageCodec::Png => "png",
+        ImageCodec::WebpLossless => "webp-lossless",
+        ImageCodec::WebpLossy => "webp-lossy",
+        ImageCodec::Jpeg => "jpeg",
+    }
+}
+
+fn parse_codec(name: &str) -> Option<ImageCodec> {
+    [ImageCodec::Png, ImageCodec::WebpLossless, ImageCodec::WebpLossy, ImageCodec::Jpeg]
+        .into_iter()
+        .find(|c| codec_name(*c) == name)
+}
+
+/// Valore della proprietà TXT mDNS `img`: "<codec>:<qualità>:<lato massimo>"
+pub fn preference_txt(pref: &ImagePreference) -> String {
+    format!("{}:{}:{}", codec_name(pref.codec), pref.quality, pref.max_dimension)
+}
+
+/// Legge la preferenza annunciata da un peer; valori sconosciuti = nessuna preferenza
+pub fn parse_preference(txt: &str) -> Option<ImagePreference> {
+    let mut parts = txt.trim().split(':');
+    let codec = parse_codec(parts.next()?)?;
+    let quality = parts.next().and_then(|q| q.parse::<u8>().ok()).unwrap_or(85).clamp(1, 100);
+    let max_dimension = parts.next().and_then(|d| d.parse::<u32>().ok()).unwrap_or(0);
+    Some(ImagePreference { codec, quality, max_dimension })
+}
+
+/// Codifica l'immagine rispettando lato e dimensione massimi.
+/// Bloccante (ridimensionamento e codifica): da chiamare in spawn_blocking.
+pub fn encode(image: &RawImage, settings: &ImageSettings) -> Result<Vec<u8>> {
+    let original = RgbaImage::from_raw(image.width, image.height, image.rgba.clone())
+        .ok_or_else(|| anyhow!("Buffer immagine non valido ({}x{})", image.width, image.height))?;
+    let lossy = matches!(settings.codec, ImageCodec::WebpLossy | ImageCodec::Jpeg);
+
+    let mut longest = image.width.max(image.height);
+    if settings.max_dimension > 0 {
+        longest = longest.min(settings.max_dimension);
+    }
+    let mut quality = settings.quality.clamp(1, 100);
+
+    loop {
+        let scaled = fit(&original, longest);
+        let bytes = encode_once(&scaled, settings.codec, quality)?;
+        if bytes.len() <= settings.max_bytes {
+            return Ok(bytes);
+        }
+        if lossy && quality > MIN_QUALITY {
+            quality = quality.saturating_sub(QUALITY_STEP).max(MIN_QUALITY);
+            continue;
+        }
+        if longest <= MIN_DIMENSION {
+            return Err(anyhow!("Immagine oltre il limite di {} byte anche a {} px", settings.max_bytes, longest));
+        }
+        longest = (longest * 3 / 4).max(MIN_DIMENSION);
+        quality = settings.quality.clamp(1, 100);
+    }
+}
+
+/// Riduce (mai ingrandisce) l'immagine perché il lato lungo sia al massimo `longest`
+pub fn fit(image: &RgbaImage, longest: u32) -> RgbaImage {
+    let (w, h) = image.dimensions();
+    if w.max(h) <= longest {
+        return image.clone();
+    }
+    let scale = longest as f64 / w.max(h) as f64;
+    let nw = ((w as f64 * scale).round() as u32).max(1);
+    let nh = ((h as f64 * scale).round() as u32).max(1);
+    imageops::resize(image, nw, nh, FilterType::Lanczos3)
+}
+
+fn encode_once(image: &RgbaImage, codec: ImageCodec, quality: u8) -> Result<Vec<u8>> {
+    let (w, h) = image.dimensions();
+    let mut out = Vec::new();
+    match codec {
+        ImageCodec::Png => {
+            PngEncoder::new(&mut out).write_image(image.as_raw(), w, h, ColorType::Rgba8)?;
+        },
+        ImageCodec::WebpLossless => {
+            out.extend_from_slice(&webp::Encoder::from_rgba(image.as_raw(), w, h).encode_lossless());
+        },
+        ImageCodec::WebpLossy => {
+            out.extend_from_slice(&webp::Encoder::from_rgba(image.as_raw(), w, h).encode(quality as f32));
+        },
+        ImageCodec::Jpeg => {
+            let rgb = flatten_on_white(image);
+            JpegEncoder::new_with_quality(&mut out, quality).write_image(&rgb, w, h, ColorType::Rgb8)?;
+        },
+    }
+    Ok(out)
+}
+
+/// JPEG non ha canale alfa: componiamo su bianco (come un incolla in un documento)
+fn flatten_on_white(image: &RgbaImage) -> Vec<u8> {
+    image.pixels().flat_map(|p| {
+        let [r, g, b, a] = p.0;
+        let blend = |c: u8| ((c as u16 * a as u16 + 255 * (255 - a as u16)) / 255) as u8;
+        [blend(r), blend(g), blend(b)]
+    }).collect()
+}
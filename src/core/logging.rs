@@ -0,0 +1,149 @@
+use crate::core::config::{LogVerbosity, LoggingConfig};
+use crate::events::{CoreEvent, LogEntry, LogLevel};
+use anyhow::Result;
+use directories::ProjectDirs;
+use flume::Sender;
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::{LevelFilter, Targets};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt as tfmt, reload, Registry};
+
+// Log dell'app: terminale, file giornalieri nella cartella dati e pannello Log della UI.
+// Il livello scelto vale per i moduli di RustClip; le librerie registrano solo gli avvisi.
+
+/// Prefisso dei moduli dell'app (lib e binario hanno lo stesso nome di crate)
+const APP_TARGET: &str = "rust_clip";
+
+/// Canale verso la UI, presente solo quando c'è una finestra
+static UI_SINK: Mutex<Option<Sender<CoreEvent>>> = Mutex::new(None);
+/// Filtro sostituibile a caldo quando l'utente cambia livello
+static FILTER: OnceLock<reload::Handle<Targets, Registry>> = OnceLock::new();
+
+/// Cartella dei file di log (`logs` nella cartella dati dell'app)
+pub fn log_dir() -> Result<PathBuf> {
+    let proj = ProjectDirs::from("com", "rustclip", "rust-clip")
+        .ok_or_else(|| anyhow::anyhow!("Impossibile determinare cartella dati"))?;
+    Ok(proj.data_dir().join("logs"))
+}
+
+fn targets(verbosity: LogVerbosity) -> Targets {
+    Targets::new()
+        .with_target(APP_TARGET, level_filter(verbosity))
+        .with_default(LevelFilter::WARN)
+}
+
+fn level_filter(verbosity: LogVerbosity) -> LevelFilter {
+    match verbosity {
+        LogVerbosity::Error => LevelFilter::ERROR,
+        LogVerbosity::Warn => LevelFilter::WARN,
+        LogVerbosity::Info => LevelFilter::INFO,
+        LogVerbosity::Debug => LevelFilter::DEBUG,
+        LogVerbosity::Trace => LevelFilter::TRACE,
+    }
+}
+
+/// Installa il subscriber globale. La guardia restituita va tenuta in vita fino all'uscita:
+/// al drop scrive su file gli ultimi messaggi rimasti in coda.
+pub fn init(config: &LoggingConfig) -> Option<WorkerGuard> {
+    let (filter, handle) = reload::Layer::new(targets(config.verbosity));
+
+    let mut file_error = None;
+    let (file_layer, guard) = match config.file_enabled.then(|| open_file(config.max_files)) {
+        Some(Ok(appender)) => {
+            let (writer, guard) = tracing_appender::non_blocking(appender);
+            (Some(tfmt::layer().with_ansi(false).with_writer(writer)), Some(guard))
+        }
+        Some(Err(e)) => {
+            file_error = Some(e);
+            (None, None)
+        }
+        None => (None, None),
+    };
+
+    let installed = tracing_subscriber::registry()
+        .with(filter)
+        .with(tfmt::layer().with_writer(std::io::stderr))
+        .with(file_layer)
+        .with(UiLayer)
+        .try_init()
+        .is_ok();
+    if installed {
+        let _ = FILTER.set(handle);
+    }
+    if let Some(e) = file_error {
+        tracing::warn!("File di log non disponibile: {}", e);
+    }
+    guard
+}
+
+fn open_file(max_files: usize) -> Result<RollingFileAppender> {
+    let dir = log_dir()?;
+    std::fs::create_dir_all(&dir)?;
+    Ok(RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("rust-clip")
+        .filename_suffix("log")
+        .max_log_files(max_files.max(1))
+        .build(dir)?)
+}
+
+/// Cambia il livello senza riavviare (impostazioni salvate dalla UI)
+pub fn set_verbosity(verbosity: LogVerbosity) {
+    if let Some(handle) = FILTER.get() {
+        let _ = handle.reload(targets(verbosity));
+    }
+}
+
+/// Da qui in poi i messaggi dell'app (da Info in su) arrivano anche al pannello Log
+pub fn attach_ui(tx: Sender<CoreEvent>) {
+    *UI_SINK.lock().unwrap() = Some(tx);
+}
+
+/// Livello del pannello Log per un evento di tracing; None = troppo dettagliato per la UI
+pub fn ui_level(level: &Level) -> Option<LogLevel> {
+    match *level {
+        Level::ERROR => Some(LogLevel::Error),
+        Level::WARN => Some(LogLevel::Warn),
+        Level::INFO => Some(LogLevel::Info),
+        _ => None,
+    }
+}
+
+/// Inoltra alla UI i messaggi dei moduli dell'app
+struct UiLayer;
+
+impl<S: Subscriber> Layer<S> for UiLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let meta = event.metadata();
+        let Some(level) = ui_level(meta.level()) else { return };
+        if !meta.target().starts_with(APP_TARGET) {
+            return;
+        }
+        let sink = UI_SINK.lock().unwrap();
+        let Some(tx) = sink.as_ref() else { return };
+        let mut message = MessageVisitor::default();
+        event.record(&mut message);
+        let _ = tx.send(CoreEvent::Log(LogEntry::with_level(level, &message.0)));
+    }
+}
+
+/// Testo dell'evento: il messaggio seguito dagli eventuali campi `chiave=valore`
+#[derive(Default)]
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            self.0.insert_str(0, &format!("{:?}", value));
+        } else {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+}
@@ -0,0 +1,305 @@
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{debug, info};
+
+// Statistiche dei trasferimenti dall'avvio dell'app: clip e byte per peer, latenza degli invii,
+// dimensione dei clip, errori per motivo e frame che nessuna chiave dei ring decifra.
+// Restano solo in memoria; la UI le mostra nella Dashboard e, se abilitato, un endpoint
+// HTTP su 127.0.0.1 le espone in formato Prometheus (`GET /metrics`).
+
+/// Statistiche condivise da listener, invii e UI; sopravvivono al riavvio dei servizi
+pub type SharedMetrics = Arc<Mutex<Metrics>>;
+
+/// Estremi superiori dei bucket di latenza (millisecondi)
+pub const LATENCY_BUCKETS_MS: [u64; 10] = [5, 10, 25, 50, 100, 250, 500, 1000, 2500, 5000];
+/// Estremi superiori dei bucket di dimensione dei frame (byte)
+pub const SIZE_BUCKETS: [u64; 8] = [256, 1024, 4096, 16384, 65536, 262144, 1048576, 8388608];
+
+/// Perché un clip non è stato inviato o consegnato
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Failure {
+    /// Peer non raggiungibile o connessione interrotta durante l'invio
+    Connect,
+    /// Frame non preparato (ricodifica dell'immagine o cifratura fallita)
+    Encode,
+    /// Frame in arrivo troncato o oltre la dimensione massima
+    Incomplete,
+    /// Decifrato ma non è un clip valido
+    Malformed,
+    /// Mittente oltre il limite di clip al minuto
+    RateLimited,
+    /// Mittente da cui la direzione di sincronizzazione non accetta clip
+    Direction,
+    /// Ricevuto ma non scritto: scartato dalla pipeline o dal hook, rifiutato o scaduto in attesa di approvazione
+    Dropped,
+}
+
+impl Failure {
+    pub const ALL: [Failure; 7] = [Self::Connect, Self::Encode, Self::Incomplete, Self::Malformed, Self::RateLimited, Self::Direction, Self::Dropped];
+
+    /// Valore dell'etichetta `reason` nelle metriche
+    pub fn label(self) -> &'static str {
+        match self {
+            Failure::Connect => "connect",
+            Failure::Encode => "encode",
+            Failure::Incomplete => "incomplete",
+            Failure::Malformed => "malformed",
+            Failure::RateLimited => "rate_limited",
+            Failure::Direction => "direction",
+            Failure::Dropped => "dropped",
+        }
+    }
+}
+
+/// Istogramma cumulativo alla Prometheus: `counts[i]` conta i valori <= `bounds[i]`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Histogram {
+    bounds: &'static [u64],
+    counts: Vec<u64>,
+    pub sum: u64,
+    pub count: u64,
+}
+
+impl Histogram {
+    pub fn new(bounds: &'static [u64]) -> Self {
+        Self { bounds, counts: vec![0; bounds.len()], sum: 0, count: 0 }
+    }
+
+    pub fn observe(&mut self, value: u64) {
+        for (bound, count) in self.bounds.iter().zip(&mut self.counts) {
+            if value <= *bound {
+                *count += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    /// Coppie (estremo superiore, valori fino a lì)
+    pub fn buckets(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.bounds.iter().copied().zip(self.counts.iter().copied())
+    }
+
+    pub fn mean(&self) -> Option<u64> {
+        (self.count > 0).then(|| self.sum / self.count)
+    }
+}
+
+/// Contatori di un peer (chiave: device_id, o IP per un mittente non verificato)
+#[derive(Debug, Clone, PartialEq)]
+pub struct PeerStats {
+    pub name: String,
+    pub sent: u64,
+    pub received: u64,
+    pub bytes_sent: u64,
+    pub bytes_received: u64,
+    /// Connessione e scrittura del frame, in millisecondi
+    pub latency_ms: Histogram,
+}
+
+impl PeerStats {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            sent: 0,
+            received: 0,
+            bytes_sent: 0,
+            bytes_received: 0,
+            latency_ms: Histogram::new(&LATENCY_BUCKETS_MS),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metrics {
+    pub peers: BTreeMap<String, PeerStats>,
+    pub failures: BTreeMap<Failure, u64>,
+    /// Frame che nessuna chiave dei ring apre: ring sbagliato, manomissione o timestamp fuori finestra
+    pub decrypt_rejected: u64,
+    pub sent_size: Histogram,
+    pub received_size: Histogram,
+    /// Cresce a ogni cambiamento: la UI riceve una copia solo se è cambiato qualcosa
+    pub revision: u64,
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self {
+            peers: BTreeMap::new(),
+            failures: BTreeMap::new(),
+            decrypt_rejected: 0,
+            sent_size: Histogram::new(&SIZE_BUCKETS),
+            received_size: Histogram::new(&SIZE_BUCKETS),
+            revision: 0,
+        }
+    }
+}
+
+impl Metrics {
+    fn peer(&mut self, key: &str, name: &str) -> &mut PeerStats {
+        self.revision += 1;
+        let peer = self.peers.entry(key.to_string()).or_insert_with(|| PeerStats::new(name));
+        peer.name = name.to_string();
+        peer
+    }
+
+    /// Frame di `bytes` byte consegnato a un peer in `latency`
+    pub fn record_sent(&mut self, key: &str, name: &str, bytes: usize, latency: Duration) {
+        let peer = self.peer(key, name);
+        peer.sent += 1;
+        peer.bytes_sent += bytes as u64;
+        peer.latency_ms.observe(latency.as_millis() as u64);
+        self.sent_size.observe(bytes as u64);
+    }
+
+    /// Frame di `bytes` byte ricevuto e decifrato; se poi non arriva nella clipboard conta anche come `Failure::Dropped`
+    pub fn record_received(&mut self, key: &str, name: &str, bytes: usize) {
+        let peer = self.peer(key, name);
+        peer.received += 1;
+        peer.bytes_received += bytes as u64;
+        self.received_size.observe(bytes as u64);
+    }
+
+    pub fn record_failure(&mut self, reason: Failure) {
+        *self.failures.entry(reason).or_default() += 1;
+        self.revision += 1;
+    }
+
+    pub fn record_decrypt_rejected(&mut self) {
+        self.decrypt_rejected += 1;
+        self.revision += 1;
+    }
+
+    pub fn total_sent(&self) -> u64 {
+        self.peers.values().map(|p| p.sent).sum()
+    }
+
+    pub fn total_received(&self) -> u64 {
+        self.peers.values().map(|p| p.received).sum()
+    }
+
+    /// Testo nel formato di esposizione di Prometheus (0.0.4)
+    pub fn render_prometheus(&self) -> String {
+        let mut out = String::new();
+        let peer_counters: [(&str, &str, fn(&PeerStats) -> u64); 4] = [
+            ("rustclip_clips_sent_total", "Clips delivered to a peer", |p| p.sent),
+            ("rustclip_clips_received_total", "Clips received from a peer", |p| p.received),
+            ("rustclip_bytes_sent_total", "Frame bytes delivered to a peer", |p| p.bytes_sent),
+            ("rustclip_bytes_received_total", "Frame bytes received from a peer", |p| p.bytes_received),
+        ];
+        for (metric, help, value) in peer_counters {
+            header(&mut out, metric, help, "counter");
+            for (key, peer) in &self.peers {
+                let _ = writeln!(out, "{}{{{}}} {}", metric, peer_labels(key, peer), value(peer));
+            }
+        }
+
+        header(&mut out, "rustclip_send_latency_seconds", "Time to connect and write a clip to a peer", "histogram");
+        for (key, peer) in &self.peers {
+            let labels = peer_labels(key, peer);
+            let hist = &peer.latency_ms;
+            for (bound, count) in hist.buckets() {
+                let _ = writeln!(out, "rustclip_send_latency_seconds_bucket{{{},le=\"{}\"}} {}", labels, bound as f64 / 1000.0, count);
+            }
+            let _ = writeln!(out, "rustclip_send_latency_seconds_bucket{{{},le=\"+Inf\"}} {}", labels, hist.count);
+            let _ = writeln!(out, "rustclip_send_latency_seconds_sum{{{}}} {}", labels, hist.sum as f64 / 1000.0);
+            let _ = writeln!(out, "rustclip_send_latency_seconds_count{{{}}} {}", labels, hist.count);
+        }
+
+        header(&mut out, "rustclip_clip_size_bytes", "Size of the frames sent and received", "histogram");
+        for (direction, hist) in [("sent", &self.sent_size), ("received", &self.received_size)] {
+            for (bound, count) in hist.buckets() {
+                let _ = writeln!(out, "rustclip_clip_size_bytes_bucket{{direction=\"{}\",le=\"{}\"}} {}", direction, bound, count);
+            }
+            let _ = writeln!(out, "rustclip_clip_size_bytes_bucket{{direction=\"{}\",le=\"+Inf\"}} {}", direction, hist.count);
+            let _ = writeln!(out, "rustclip_clip_size_bytes_sum{{direction=\"{}\"}} {}", direction, hist.sum);
+            let _ = writeln!(out, "rustclip_clip_size_bytes_count{{direction=\"{}\"}} {}", direction, hist.count);
+        }
+
+        header(&mut out, "rustclip_failures_total", "Clips not sent or not delivered, by reason", "counter");
+        for reason in Failure::ALL {
+            let count = self.failures.get(&reason).copied().unwrap_or(0);
+            let _ = writeln!(out, "rustclip_failures_total{{reason=\"{}\"}} {}", reason.label(), count);
+        }
+
+        header(&mut out, "rustclip_decrypt_rejected_total", "Incoming frames no ring key could decrypt", "counter");
+        let _ = writeln!(out, "rustclip_decrypt_rejected_total {}", self.decrypt_rejected);
+        out
+    }
+}
+
+fn header(out: &mut String, metric: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", metric, help);
+    let _ = writeln!(out, "# TYPE {} {}", metric, kind);
+}
+
+fn peer_labels(key: &str, peer: &PeerStats) -> String {
+    format!("peer=\"{}\",name=\"{}\"", escape_label(key), escape_label(&peer.name))
+}
+
+/// Escape dei valori delle etichette (`\`, `"` e a capo)
+pub fn escape_label(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// Byte in forma leggibile (B, KiB, MiB, GiB)
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{} B", bytes) } else { format!("{:.1} {}", value, UNITS[unit]) }
+}
+
+/// Endpoint Prometheus su 127.0.0.1:`port`, finché il task non viene interrotto.
+/// Risponde solo a `GET /metrics`; il resto riceve 404. Una richiesta con un `Host` diverso da
+/// localhost riceve 403: blocca le pagine web che puntano un loro dominio a 127.0.0.1 (DNS rebinding).
+pub async fn serve(metrics: SharedMetrics, port: u16) -> Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    info!("📈 Metrics endpoint on http://127.0.0.1:{}/metrics", port);
+    loop {
+        let (mut socket, _) = listener.accept().await?;
+        let metrics = metrics.clone();
+        tokio::spawn(async move {
+            // Bastano la riga della richiesta e l'header Host
+            let mut request = [0u8; 1024];
+            let Ok(Ok(n)) = tokio::time::timeout(Duration::from_secs(5), socket.read(&mut request)).await else { return };
+            let request = String::from_utf8_lossy(&request[..n]);
+            let response = if !local_host(&request) {
+                debug!("Metrics endpoint: request for a foreign host rejected");
+                "HTTP/1.1 403 Forbidden\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+            } else if request.starts_with("GET /metrics ") {
+                let body = metrics.lock().unwrap().render_prometheus();
+                format!("HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}", body.len(), body)
+            } else {
+                debug!("Metrics endpoint: unexpected request {:?}", request.lines().next());
+                "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string()
+            };
+            let _ = socket.write_all(response.as_bytes()).await;
+        });
+    }
+}
+
+/// True se l'header `Host` è 127.0.0.1 o localhost (porta facoltativa); senza header è false
+pub fn local_host(request: &str) -> bool {
+    let Some(host) = request.lines()
+        .skip(1)
+        .take_while(|line| !line.is_empty())
+        .find_map(|line| line.split_once(':').filter(|(name, _)| name.trim().eq_ignore_ascii_case("host")).map(|(_, value)| value.trim()))
+    else {
+        return false;
+    };
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+    name == "127.0.0.1" || name.eq_ignore_ascii_case("localhost")
+}
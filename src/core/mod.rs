@@ -3,4 +3,22 @@ pub mod discovery;
 pub mod crypto;
 pub mod identity;
 // pub mod firewall;
-pub mod config;
\ No newline at end of file
+pub mod config;
+pub mod vault;
+pub mod pairing;
+pub mod pake;
+pub mod protocol;
+pub mod sensitive;
+pub mod source;
+pub mod pipeline;
+pub mod hooks;
+pub mod imaging;
+pub mod compress;
+pub mod throttle;
+pub mod approval;
+pub mod roster;
+pub mod handshake;
+pub mod announce;
+pub mod logging;
+pub mod doctor;
+pub mod metrics;
@@ -0,0 +1,92 @@
+use crate::core::identity::RingIdentity;
+use anyhow::{Context, Result, anyhow};
+use bip39::{Mnemonic, Language};
+use qrcode::{QrCode, Color};
+use qrcode::render::unicode::Dense1x2;
+use std::path::Path;
+use zeroize::Zeroizing;
+
+// Payload di pairing: entropia del mnemonic in HEX maiuscolo.
+// Solo caratteri "alfanumerici QR" -> codice più piccolo e facile da leggere anche col telefono.
+const PAIRING_PREFIX: &str = "RUSTCLIP:PAIR:1:";
+
+// Bordo bianco (in moduli) richiesto dai lettori QR
+const QUIET_ZONE: usize = 4;
+
+/// Codifica l'identità in un payload di pairing (contiene il segreto!)
+pub fn pairing_payload(identity: &RingIdentity) -> Result<Zeroizing<String>> {
+    let mnemonic = Mnemonic::parse_in_normalized(Language::English, &identity.mnemonic)
+        .context("Mnemonic non valido")?;
+    let entropy = Zeroizing::new(mnemonic.to_entropy());
+    Ok(Zeroizing::new(format!("{}{}", PAIRING_PREFIX, hex::encode_upper(entropy.as_slice()))))
+}
+
+/// Estrae la frase mnemonic da un payload di pairing
+pub fn parse_pairing_payload(payload: &str) -> Result<Zeroizing<String>> {
+    let hex_part = payload.trim()
+        .strip_prefix(PAIRING_PREFIX)
+        .ok_or_else(|| anyhow!("Payload di pairing non riconosciuto"))?;
+    let entropy = Zeroizing::new(hex::decode(hex_part).context("Payload di pairing corrotto")?);
+    let mnemonic = Mnemonic::from_entropy_in(Language::English, &entropy)
+        .map_err(|e| anyhow!("Payload di pairing non valido: {}", e))?;
+    Ok(Zeroizing::new(mnemonic.to_string()))
+}
+
+/// Accetta sia le 24 parole sia un payload di pairing e restituisce l'identità
+pub fn identity_from_input(input: &str) -> Result<RingIdentity> {
+    let input = input.trim();
+    if input.starts_with(PAIRING_PREFIX) {
+        RingIdentity::from_mnemonic(&parse_pairing_payload(input)?)
+    } else {
+        RingIdentity::from_mnemonic(input)
+    }
+}
+
+/// QR in caratteri "blocco" per il terminale (due righe di moduli per riga di testo)
+pub fn render_qr_terminal(payload: &str) -> Result<String> {
+    let code = QrCode::new(payload.as_bytes())?;
+    // Colori invertiti: la maggior parte dei terminali ha sfondo scuro
+    Ok(code.render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build())
+}
+
+/// QR come matrice in scala di grigi (0 = nero, 255 = bianco), `scale` pixel per modulo.
+/// Restituisce (lato in pixel, pixel).
+pub fn render_qr_pixels(payload: &str, scale: usize) -> Result<(usize, Vec<u8>)> {
+    let code = QrCode::new(payload.as_bytes())?;
+    let modules = code.width();
+    let colors = code.to_colors();
+    let side = (modules + 2 * QUIET_ZONE) * scale;
+
+    let mut pixels = vec![255u8; side * side];
+    for (i, color) in colors.iter().enumerate() {
+        if *color != Color::Dark { continue; }
+        let (mx, my) = (i % modules + QUIET_ZONE, i / modules + QUIET_ZONE);
+        for y in my * scale..(my + 1) * scale {
+            pixels[y * side + mx * scale..y * side + (mx + 1) * scale].fill(0);
+        }
+    }
+    Ok((side, pixels))
+}
+
+/// Legge il primo QR code trovato in un'immagine (foto o screenshot)
+pub fn decode_qr_image(path: &Path) -> Result<String> {
+    let img = image::open(path)
+        .with_context(|| format!("Impossibile aprire {:?}", path))?
+        .to_luma8();
+    decode_qr_luma(img.width() as usize, img.height() as usize, img.as_raw())
+}
+
+/// Come `decode_qr_image`, ma da pixel in scala di grigi già in memoria
+pub fn decode_qr_luma(width: usize, height: usize, pixels: &[u8]) -> Result<String> {
+    let mut prepared = rqrr::PreparedImage::prepare_from_greyscale(width, height, |x, y| pixels[y * width + x]);
+    for grid in prepared.detect_grids() {
+        if let Ok((_, content)) = grid.decode() {
+            return Ok(content);
+        }
+    }
+    Err(anyhow!("Nessun QR code leggibile nell'immagine"))
+}
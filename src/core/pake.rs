@@ -0,0 +1,241 @@
+use crate::core::identity::RingIdentity;
+use crate::core::announce;
+use anyhow::{Context, Result, anyhow};
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use tracing::{debug, info};
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac};
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use rand::{Rng, RngCore, thread_rng};
+use serde::{Serialize, Deserialize};
+use sha2::Sha256;
+use spake2::{Ed25519Group, Identity, Password, Spake2};
+use std::net::SocketAddr;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::{timeout, Duration};
+use zeroize::{Zeroize, ZeroizeOnDrop, Zeroizing};
+
+// Pairing con codice breve: il dispositivo A mostra un codice monouso, B lo digita.
+// SPAKE2 trasforma il codice in una chiave forte; A consegna il mnemonic solo dopo
+// la conferma di chiave da parte di B. Un solo tentativo per sessione: un codice
+// sbagliato chiude l'invito, quindi 6 cifre bastano contro il brute force online.
+// Il segreto viaggia cifrato con la chiave di sessione SPAKE2, senza timestamp: la chiave
+// vale per una sola connessione, e il pairing non deve dipendere dagli orologi.
+// L'annuncio porta un tag di 8 bit derivato dal codice (con un sale casuale), così B prova
+// solo l'invito giusto anche se ce ne sono altri aperti. 8 bit distinguono gli inviti
+// senza rivelare il codice: a chi ascolta restano comunque ~4000 candidati per un tentativo.
+
+pub const PAIRING_PORT: u16 = 5567;
+const PAIRING_SERVICE: &str = "_rustclip-pair._tcp.local.";
+pub const INVITE_TIMEOUT: Duration = Duration::from_secs(120);
+const STEP_TIMEOUT: Duration = Duration::from_secs(30);
+const BROWSE_TIMEOUT: Duration = Duration::from_secs(10);
+const MAX_MESSAGE: usize = 4096;
+const PAIRING_VERSION: &str = "2";
+
+const ID_INVITER: &[u8] = b"rustclip-inviter";
+const ID_JOINER: &[u8] = b"rustclip-joiner";
+
+#[derive(Serialize, Deserialize, Zeroize, ZeroizeOnDrop)]
+struct PairingGrant {
+    mnemonic: String,
+    inviter_name: String,
+}
+
+#[derive(Zeroize, ZeroizeOnDrop)]
+struct SessionKeys {
+    confirm: [u8; 32],
+    enc: [u8; 32],
+}
+
+/// Codice monouso a 6 cifre
+pub fn generate_code() -> String {
+    format!("{:06}", thread_rng().gen_range(0..1_000_000u32))
+}
+
+/// Lato A: annuncia l'invito via mDNS e attende UN tentativo di pairing
+pub async fn invite(identity: &RingIdentity, device_name: &str, code: &str) -> Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", PAIRING_PORT)).await
+        .context("Porta di pairing occupata")?;
+
+    let mdns = ServiceDaemon::new()?;
+    // Come per gli annunci del ring, il nome non deve rivelare il dispositivo
+    let instance = announce::random_instance_name();
+    let salt = random_salt();
+    let service = ServiceInfo::new(
+        PAIRING_SERVICE,
+        &instance,
+        &format!("{}.local.", instance),
+        "0.0.0.0",
+        PAIRING_PORT,
+        &[("version", PAIRING_VERSION), ("salt", salt.as_str()), ("tag", invite_tag(code, &salt).as_str())][..],
+    )?.enable_addr_auto();
+    mdns.register(service)?;
+    info!("📨 Pairing invite active ({})", instance);
+
+    let result = timeout(INVITE_TIMEOUT, serve_invite(listener, identity, device_name, code)).await
+        .unwrap_or_else(|_| Err(anyhow!("Invito scaduto")));
+    let _ = mdns.shutdown();
+    result
+}
+
+/// Esegue il protocollo lato A su un listener già aperto (una sola connessione)
+pub async fn serve_invite(listener: TcpListener, identity: &RingIdentity, device_name: &str, code: &str) -> Result<()> {
+    let (mut socket, addr) = listener.accept().await?;
+    info!("📨 Pairing attempt from {}", addr);
+
+    let (spake, msg_a) = Spake2::<Ed25519Group>::start_a(
+        &Password::new(code.as_bytes()), &Identity::new(ID_INVITER), &Identity::new(ID_JOINER));
+
+    let msg_b = read_message(&mut socket).await?;
+    write_message(&mut socket, &msg_a).await?;
+    let shared = Zeroizing::new(spake.finish(&msg_b).map_err(|_| anyhow!("Messaggio SPAKE2 non valido"))?);
+    let keys = session_keys(&shared)?;
+
+    // B deve dimostrare di conoscere il codice prima di ricevere il segreto
+    let confirm_b = read_message(&mut socket).await?;
+    confirm_mac(&keys, ID_JOINER).verify_slice(&confirm_b)
+        .map_err(|_| anyhow!("Codice di pairing errato"))?;
+
+    let grant = PairingGrant {
+        mnemonic: identity.mnemonic.clone(),
+        inviter_name: device_name.to_string(),
+    };
+    let sealed = seal_grant(&keys, &Zeroizing::new(serde_json::to_vec(&grant)?))?;
+    write_message(&mut socket, &sealed).await?;
+    Ok(())
+}
+
+/// Lato B: cerca un invito sulla LAN e riceve le credenziali del Ring.
+/// Restituisce l'identità e il nome del dispositivo che ha invitato.
+pub async fn join(code: &str) -> Result<(RingIdentity, String)> {
+    let addr = find_inviter(code).await?;
+    join_at(addr, code).await
+}
+
+/// Esegue il protocollo lato B verso un indirizzo noto
+pub async fn join_at(addr: SocketAddr, code: &str) -> Result<(RingIdentity, String)> {
+    let mut socket = timeout(STEP_TIMEOUT, TcpStream::connect(addr)).await??;
+
+    let (spake, msg_b) = Spake2::<Ed25519Group>::start_b(
+        &Password::new(code.trim().as_bytes()), &Identity::new(ID_INVITER), &Identity::new(ID_JOINER));
+
+    write_message(&mut socket, &msg_b).await?;
+    let msg_a = read_message(&mut socket).await?;
+    let shared = Zeroizing::new(spake.finish(&msg_a).map_err(|_| anyhow!("Messaggio SPAKE2 non valido"))?);
+    let keys = session_keys(&shared)?;
+
+    write_message(&mut socket, &confirm_mac(&keys, ID_JOINER).finalize().into_bytes()).await?;
+
+    // Se il codice è sbagliato A chiude la connessione senza inviare nulla
+    let sealed = read_message(&mut socket).await.map_err(|_| anyhow!("Codice di pairing errato o invito scaduto"))?;
+    let plaintext = open_grant(&keys, &sealed)?;
+    let grant: PairingGrant = serde_json::from_slice(&plaintext)?;
+
+    let identity = RingIdentity::from_mnemonic(&grant.mnemonic)?;
+    Ok((identity, grant.inviter_name.clone()))
+}
+
+/// Tag dell'invito: 8 bit di HMAC(codice, sale), in esadecimale
+pub fn invite_tag(code: &str, salt: &str) -> String {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(code.trim().as_bytes()).expect("HMAC accetta chiavi di ogni lunghezza");
+    mac.update(b"rustclip_pake_tag_v1");
+    mac.update(salt.as_bytes());
+    hex::encode(&mac.finalize().into_bytes()[..1])
+}
+
+fn random_salt() -> String {
+    let mut salt = [0u8; 8];
+    thread_rng().fill_bytes(&mut salt);
+    hex::encode(salt)
+}
+
+/// Cerca l'invito il cui tag corrisponde al codice; gli altri inviti aperti vengono ignorati
+async fn find_inviter(code: &str) -> Result<SocketAddr> {
+    let mdns = ServiceDaemon::new()?;
+    let receiver = mdns.browse(PAIRING_SERVICE)?;
+
+    let found = timeout(BROWSE_TIMEOUT, async {
+        while let Ok(event) = receiver.recv_async().await {
+            if let ServiceEvent::ServiceResolved(info) = event {
+                let matches = info.get_property_val_str("version") == Some(PAIRING_VERSION)
+                    && info.get_property_val_str("salt").zip(info.get_property_val_str("tag"))
+                        .is_some_and(|(salt, tag)| invite_tag(code, salt) == tag);
+                if !matches {
+                    debug!("Pairing invite {} skipped: different code or version", info.get_fullname());
+                    continue;
+                }
+                // Preferiamo IPv4, come in discovery
+                let ip = info.get_addresses().iter().find(|ip| ip.is_ipv4())
+                    .or_else(|| info.get_addresses().iter().next())
+                    .copied();
+                if let Some(ip) = ip {
+                    return Some(SocketAddr::new(ip, info.get_port()));
+                }
+            }
+        }
+        None
+    }).await;
+
+    let _ = mdns.shutdown();
+    found.ok().flatten().ok_or_else(|| anyhow!("Nessun invito di pairing trovato sulla rete"))
+}
+
+fn session_keys(shared: &[u8]) -> Result<SessionKeys> {
+    let hkdf = Hkdf::<Sha256>::new(None, shared);
+    let mut keys = SessionKeys { confirm: [0u8; 32], enc: [0u8; 32] };
+    hkdf.expand(b"rustclip_pake_confirm_v1", &mut keys.confirm)
+        .map_err(|_| anyhow!("HKDF error"))?;
+    hkdf.expand(b"rustclip_pake_enc_v1", &mut keys.enc)
+        .map_err(|_| anyhow!("HKDF error"))?;
+    Ok(keys)
+}
+
+/// Il segreto è cifrato una sola volta con `enc`: nonce casuale, nessun timestamp
+fn seal_grant(keys: &SessionKeys, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; 24];
+    thread_rng().fill_bytes(&mut nonce);
+    let ciphertext = XChaCha20Poly1305::new(keys.enc.as_slice().into())
+        .encrypt(XNonce::from_slice(&nonce), plaintext)
+        .map_err(|_| anyhow!("Cifratura del pairing fallita"))?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+fn open_grant(keys: &SessionKeys, sealed: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    if sealed.len() < 24 {
+        return Err(anyhow!("Messaggio di pairing troppo corto"));
+    }
+    let (nonce, ciphertext) = sealed.split_at(24);
+    XChaCha20Poly1305::new(keys.enc.as_slice().into())
+        .decrypt(XNonce::from_slice(nonce), ciphertext)
+        .map(Zeroizing::new)
+        .map_err(|_| anyhow!("Decifrazione del pairing fallita"))
+}
+
+fn confirm_mac(keys: &SessionKeys, role: &[u8]) -> Hmac<Sha256> {
+    let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&keys.confirm).expect("HMAC accetta chiavi di ogni lunghezza");
+    mac.update(role);
+    mac
+}
+
+async fn write_message(socket: &mut TcpStream, data: &[u8]) -> Result<()> {
+    socket.write_all(&(data.len() as u32).to_be_bytes()).await?;
+    socket.write_all(data).await?;
+    Ok(())
+}
+
+async fn read_message(socket: &mut TcpStream) -> Result<Vec<u8>> {
+    timeout(STEP_TIMEOUT, async {
+        let mut len_buf = [0u8; 4];
+        socket.read_exact(&mut len_buf).await?;
+        let len = u32::from_be_bytes(len_buf) as usize;
+        if len > MAX_MESSAGE {
+            return Err(anyhow!("Messaggio di pairing troppo grande"));
+        }
+        let mut buf = vec![0u8; len];
+        socket.read_exact(&mut buf).await?;
+        Ok(buf)
+    }).await?
+}
@@ -0,0 +1,196 @@
+use crate::core::config::PipelineConfig;
+use crate::core::protocol::ClipContent;
+use regex::Regex;
+use std::sync::OnceLock;
+
+// Pipeline di trasformazione dei clip:
+//   Outgoing: cattura -> stage -> broadcast
+//   Incoming: decrypt -> stage -> scrittura in clipboard
+// Ogni stage può modificare il contenuto o scartarlo (None).
+
+/// Parametri di tracking rimossi sempre (oltre a quelli che iniziano con `utm_`)
+const TRACKING_PARAMS: &[&str] = &[
+    "fbclid", "gclid", "dclid", "gbraid", "wbraid", "msclkid", "mc_cid", "mc_eid",
+    "igshid", "yclid", "_hsenc", "_hsmi", "mkt_tok", "si",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Outgoing,
+    Incoming,
+}
+
+pub trait ClipStage: Send + Sync {
+    fn name(&self) -> &'static str;
+    /// Trasforma il clip; None = il clip viene scartato
+    fn apply(&self, content: ClipContent, direction: Direction) -> Option<ClipContent>;
+}
+
+pub struct Pipeline {
+    stages: Vec<Box<dyn ClipStage>>,
+}
+
+impl Pipeline {
+    pub fn new(stages: Vec<Box<dyn ClipStage>>) -> Self {
+        Self { stages }
+    }
+
+    /// Stage integrati abilitati in config, nell'ordine in cui vanno applicati
+    pub fn from_config(config: &PipelineConfig) -> Self {
+        let mut stages: Vec<Box<dyn ClipStage>> = Vec::new();
+        if config.trim_whitespace {
+            stages.push(Box::new(TrimWhitespace));
+        }
+        if config.strip_tracking {
+            stages.push(Box::new(StripTrackingParams::new(config.extra_tracking_params.clone())));
+        }
+        if config.normalize_line_endings {
+            stages.push(Box::new(NormalizeLineEndings::native()));
+        }
+        if config.max_text_kib > 0 || config.max_image_kib > 0 {
+            stages.push(Box::new(SizeCap::from_config(config)));
+        }
+        Self { stages }
+    }
+
+    /// Applica gli stage in ordine. In caso di scarto restituisce il nome dello stage.
+    pub fn run(&self, content: ClipContent, direction: Direction) -> Result<ClipContent, &'static str> {
+        self.stages.iter().try_fold(content, |content, stage| {
+            stage.apply(content, direction).ok_or(stage.name())
+        })
+    }
+}
+
+/// Toglie spazi e righe vuote all'inizio e alla fine del testo (un testo vuoto viene scartato)
+pub struct TrimWhitespace;
+
+impl ClipStage for TrimWhitespace {
+    fn name(&self) -> &'static str { "trim_whitespace" }
+
+    fn apply(&self, content: ClipContent, _direction: Direction) -> Option<ClipContent> {
+        match content {
+            ClipContent::Text(text) => {
+                let trimmed = text.trim();
+                if trimmed.is_empty() { return None; }
+                Some(ClipContent::Text(trimmed.to_string()))
+            },
+            other => Some(other),
+        }
+    }
+}
+
+/// Rimuove i parametri di tracking (utm_*, fbclid, gclid...) dagli URL nel testo
+pub struct StripTrackingParams {
+    extra: Vec<String>,
+}
+
+impl StripTrackingParams {
+    pub fn new(extra: Vec<String>) -> Self {
+        Self { extra: extra.into_iter().map(|p| p.trim().to_lowercase()).filter(|p| !p.is_empty()).collect() }
+    }
+
+    fn is_tracking(&self, key: &str) -> bool {
+        let key = key.to_lowercase();
+        key.starts_with("utm_") || TRACKING_PARAMS.contains(&key.as_str()) || self.extra.contains(&key)
+    }
+
+    pub fn clean_url(&self, url: &str) -> String {
+        let (rest, fragment) = match url.split_once('#') {
+            Some((r, f)) => (r, Some(f)),
+            None => (url, None),
+        };
+        let Some((base, query)) = rest.split_once('?') else { return url.to_string() };
+
+        let kept: Vec<&str> = query.split('&')
+            .filter(|pair| !pair.is_empty() && !self.is_tracking(pair.split('=').next().unwrap_or("")))
+            .collect();
+        let mut out = base.to_string();
+        if !kept.is_empty() {
+            out.push('?');
+            out.push_str(&kept.join("&"));
+        }
+        if let Some(fragment) = fragment {
+            out.push('#');
+            out.push_str(fragment);
+        }
+        out
+    }
+}
+
+impl ClipStage for StripTrackingParams {
+    fn name(&self) -> &'static str { "strip_tracking" }
+
+    fn apply(&self, content: ClipContent, _direction: Direction) -> Option<ClipContent> {
+        static URL: OnceLock<Regex> = OnceLock::new();
+        match content {
+            ClipContent::Text(text) => {
+                let re = URL.get_or_init(|| Regex::new(r"https?://[^\s<>\x22']+").expect("regex URL valida"));
+                let cleaned = re.replace_all(&text, |caps: &regex::Captures| self.clean_url(&caps[0]));
+                Some(ClipContent::Text(cleaned.into_owned()))
+            },
+            other => Some(other),
+        }
+    }
+}
+
+/// Sul filo viaggia sempre LF; in ricezione si converte al formato del sistema locale
+pub struct NormalizeLineEndings {
+    crlf_locally: bool,
+}
+
+impl NormalizeLineEndings {
+    pub fn native() -> Self {
+        Self { crlf_locally: cfg!(windows) }
+    }
+
+    /// Per i test (e per forzare un formato)
+    pub fn with_local_crlf(crlf_locally: bool) -> Self {
+        Self { crlf_locally }
+    }
+}
+
+impl ClipStage for NormalizeLineEndings {
+    fn name(&self) -> &'static str { "normalize_line_endings" }
+
+    fn apply(&self, content: ClipContent, direction: Direction) -> Option<ClipContent> {
+        match content {
+            ClipContent::Text(text) => {
+                let lf = text.replace("\r\n", "\n");
+                let out = if direction == Direction::Incoming && self.crlf_locally {
+                    lf.replace('\n', "\r\n")
+                } else {
+                    lf
+                };
+                Some(ClipContent::Text(out))
+            },
+            other => Some(other),
+        }
+    }
+}
+
+/// Scarta i clip troppo grandi (0 = nessun limite)
+pub struct SizeCap {
+    pub max_text_bytes: usize,
+    pub max_image_bytes: usize,
+}
+
+impl SizeCap {
+    pub fn from_config(config: &PipelineConfig) -> Self {
+        Self {
+            max_text_bytes: config.max_text_kib as usize * 1024,
+            max_image_bytes: config.max_image_kib as usize * 1024,
+        }
+    }
+}
+
+impl ClipStage for SizeCap {
+    fn name(&self) -> &'static str { "size_cap" }
+
+    fn apply(&self, content: ClipContent, _direction: Direction) -> Option<ClipContent> {
+        let (size, max) = match &content {
+            ClipContent::Text(text) => (text.len(), self.max_text_bytes),
+            ClipContent::Image(bytes) => (bytes.len(), self.max_image_bytes),
+        };
+        if max > 0 && size > max { None } else { Some(content) }
+    }
+}
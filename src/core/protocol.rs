@@ -0,0 +1,187 @@
+use crate::core::compress;
+use crate::core::config::{CompressionAlgorithm, CompressionConfig};
+use crate::core::crypto::CryptoLayer;
+use anyhow::{Result, anyhow};
+use serde::{Serialize, Deserialize};
+
+// Contenuto applicativo dei pacchetti (dentro il SecurePayload cifrato).
+// Formato: bincode(ClipContent) + bincode(ClipMeta).
+// I metadati stanno IN CODA: bincode ignora i byte in eccesso, quindi i peer
+// più vecchi leggono il solo contenuto e continuano a funzionare.
+//
+// Frame TCP: [u32 BE header][nonce + ciphertext]. L'header porta la lunghezza nei
+// 26 bit bassi e l'eventuale compressione nei bit alti: un peer vecchio vede una
+// lunghezza oltre MAX_PACKET_SIZE e scarta il frame invece di decifrare dati che non capisce.
+// Il bit 29 marca i messaggi di handshake (vedi `handshake`), che non contengono clip.
+
+const FLAG_ZSTD: u32 = 1 << 31;
+const FLAG_LZ4: u32 = 1 << 30;
+const FLAG_HELLO: u32 = 1 << 29;
+const LEN_MASK: u32 = (1 << 26) - 1;
+// Prefisso in chiaro (dentro la cifratura) dei messaggi di handshake: un hello non si
+// scambia per un clip né viceversa, anche se cifrati con la stessa chiave
+const HELLO_MAGIC: &[u8; 4] = b"RCH1";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum ClipContent {
+    Text(String),
+    Image(Vec<u8>),
+}
+
+/// Selezione di destinazione sul peer (PRIMARY esiste solo su Linux)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Selection {
+    #[default]
+    Clipboard,
+    /// Selezione del middle-click
+    Primary,
+}
+
+/// Metadati opzionali che accompagnano un clip
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ClipMeta {
+    /// Il mittente chiede di cancellare il clip dalla clipboard dopo N secondi
+    pub expire_secs: Option<u32>,
+    /// Il mittente considera il clip sensibile (credenziali, chiavi...)
+    pub sensitive: bool,
+    pub selection: Selection,
+    /// device_id del mittente: sta dentro la cifratura, quindi lo può scrivere solo chi ha la chiave
+    /// del ring (None = peer di una versione che non lo invia)
+    pub sender: Option<String>,
+}
+
+// Sul filo i metadati sono blocchi accodati uno dopo l'altro, uno per ogni estensione
+// del protocollo: chi non conosce un blocco lo ignora, un blocco assente vale default.
+#[derive(Serialize, Deserialize, Default)]
+struct MetaBlock1 {
+    expire_secs: Option<u32>,
+    sensitive: bool,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct MetaBlock2 {
+    selection: Selection,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct MetaBlock3 {
+    sender: Option<String>,
+}
+
+pub fn encode_clip(content: &ClipContent, meta: &ClipMeta) -> Result<Vec<u8>> {
+    let mut out = bincode::serialize(content)?;
+    out.extend_from_slice(&bincode::serialize(&MetaBlock1 { expire_secs: meta.expire_secs, sensitive: meta.sensitive })?);
+    out.extend_from_slice(&bincode::serialize(&MetaBlock2 { selection: meta.selection })?);
+    out.extend_from_slice(&bincode::serialize(&MetaBlock3 { sender: meta.sender.clone() })?);
+    Ok(out)
+}
+
+/// Decodifica un clip; i pacchetti senza metadati (peer vecchi) hanno `ClipMeta::default()`
+pub fn decode_clip(data: &[u8]) -> Result<(ClipContent, ClipMeta)> {
+    let mut reader = data;
+    let content: ClipContent = bincode::deserialize_from(&mut reader)?;
+    let block1: MetaBlock1 = next_block(&mut reader);
+    let block2: MetaBlock2 = next_block(&mut reader);
+    let block3: MetaBlock3 = next_block(&mut reader);
+    let meta = ClipMeta {
+        expire_secs: block1.expire_secs,
+        sensitive: block1.sensitive,
+        selection: block2.selection,
+        sender: block3.sender,
+    };
+    Ok((content, meta))
+}
+
+/// Presentazione di un dispositivo nell'handshake
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Hello {
+    pub device_id: String,
+    pub device_name: String,
+    pub nonce: [u8; 16],
+    /// Nonce del messaggio a cui si risponde (None nel messaggio di apertura)
+    pub echo: Option<[u8; 16]>,
+}
+
+fn next_block<T: serde::de::DeserializeOwned + Default>(reader: &mut &[u8]) -> T {
+    if reader.is_empty() {
+        return T::default();
+    }
+    bincode::deserialize_from(reader).unwrap_or_default()
+}
+
+pub fn frame_header(len: usize, compression: Option<CompressionAlgorithm>) -> Result<[u8; 4]> {
+    let len = u32::try_from(len).ok().filter(|l| *l <= LEN_MASK).ok_or_else(|| anyhow!("Frame troppo grande ({} byte)", len))?;
+    let flags = match compression {
+        Some(CompressionAlgorithm::Zstd) => FLAG_ZSTD,
+        Some(CompressionAlgorithm::Lz4) => FLAG_LZ4,
+        Some(CompressionAlgorithm::Off) | None => 0,
+    };
+    Ok((len | flags).to_be_bytes())
+}
+
+/// Lunghezza del corpo e compressione indicate dall'header
+pub fn parse_frame_header(header: [u8; 4]) -> Result<(usize, Option<CompressionAlgorithm>)> {
+    let raw = u32::from_be_bytes(header);
+    let compression = match raw & !LEN_MASK & !FLAG_HELLO {
+        0 => None,
+        FLAG_ZSTD => Some(CompressionAlgorithm::Zstd),
+        FLAG_LZ4 => Some(CompressionAlgorithm::Lz4),
+        other => return Err(anyhow!("Flag di frame sconosciuti: {:#x}", other)),
+    };
+    Ok(((raw & LEN_MASK) as usize, compression))
+}
+
+/// Comprime (se richiesto e conveniente), cifra e antepone l'header: pronto per il socket
+pub fn seal_frame(clip: &[u8], compression: Option<CompressionAlgorithm>, config: &CompressionConfig, crypto: &CryptoLayer) -> Result<Vec<u8>> {
+    let (payload, applied) = compress::maybe_compress(clip, compression, config);
+    let body = crypto.encrypt(&payload)?;
+    let mut frame = frame_header(body.len(), applied)?.to_vec();
+    frame.extend_from_slice(&body);
+    Ok(frame)
+}
+
+/// Inverso di `seal_frame` dato l'header già letto: restituisce il clip serializzato
+pub fn open_frame(header: [u8; 4], body: &[u8], crypto: &CryptoLayer) -> Result<Vec<u8>> {
+    let (_, compression) = parse_frame_header(header)?;
+    let payload = crypto.decrypt(body)?;
+    match compression {
+        Some(algorithm) => compress::decompress(&payload, algorithm),
+        None => Ok(payload),
+    }
+}
+
+/// True se l'header apre un messaggio di handshake invece di un clip
+pub fn is_hello(header: [u8; 4]) -> bool {
+    u32::from_be_bytes(header) & FLAG_HELLO != 0
+}
+
+/// Cifra un messaggio di handshake e antepone l'header: pronto per il socket
+pub fn seal_hello(hello: &Hello, crypto: &CryptoLayer) -> Result<Vec<u8>> {
+    let mut plain = HELLO_MAGIC.to_vec();
+    plain.extend_from_slice(&bincode::serialize(hello)?);
+    frame_hello(crypto.encrypt(&plain)?)
+}
+
+/// Come `seal_hello`, con il timestamp cifrato scelto dal chiamante (diagnostica)
+pub fn seal_hello_at(hello: &Hello, crypto: &CryptoLayer, timestamp: u64) -> Result<Vec<u8>> {
+    let mut plain = HELLO_MAGIC.to_vec();
+    plain.extend_from_slice(&bincode::serialize(hello)?);
+    frame_hello(crypto.encrypt_at(&plain, timestamp)?)
+}
+
+fn frame_hello(body: Vec<u8>) -> Result<Vec<u8>> {
+    let mut frame = (u32::from_be_bytes(frame_header(body.len(), None)?) | FLAG_HELLO).to_be_bytes().to_vec();
+    frame.extend_from_slice(&body);
+    Ok(frame)
+}
+
+/// Inverso di `seal_hello` dato il corpo del frame; fallisce se la chiave non è quella del ring
+pub fn open_hello(body: &[u8], crypto: &CryptoLayer) -> Result<Hello> {
+    decode_hello(&crypto.decrypt(body)?)
+}
+
+/// Messaggio di handshake già decifrato
+pub fn decode_hello(plain: &[u8]) -> Result<Hello> {
+    let hello = plain.strip_prefix(HELLO_MAGIC.as_slice()).ok_or_else(|| anyhow!("Non è un messaggio di handshake"))?;
+    Ok(bincode::deserialize(hello)?)
+}
@@ -0,0 +1,121 @@
+use crate::core::config;
+use crate::events::PeerInfo;
+use serde::{Serialize, Deserialize};
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Registro dei dispositivi visti in ogni ring, anche quando non sono più online.
+// Salvato in chiaro accanto alla config: non contiene segreti.
+
+/// Registro condiviso dai discovery dei ring attivi
+pub type SharedRoster = Arc<Mutex<Roster>>;
+
+/// Ogni quanto aggiornare su disco un `last_seen` che cambia solo di poco
+const LAST_SEEN_SAVE_SECS: u64 = 60;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct RosterEntry {
+    pub ring_id: String,
+    pub device_id: String,
+    pub name: String,
+    /// None = peer di una versione che non li annuncia
+    pub os: Option<String>,
+    pub app_version: Option<String>,
+    /// Secondi dall'epoch Unix
+    pub first_seen: u64,
+    pub last_seen: u64,
+}
+
+impl RosterEntry {
+    pub fn device_code(&self) -> String {
+        device_code(&self.device_id)
+    }
+}
+
+/// Codice del dispositivo: il device_id a gruppi di 4 caratteri, da confrontare a colpo d'occhio
+/// tra due dispositivi. È solo l'ID in forma leggibile, non un'impronta di una chiave.
+pub fn device_code(device_id: &str) -> String {
+    device_id.as_bytes()
+        .chunks(4)
+        .map(String::from_utf8_lossy)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+pub fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+/// Data e ora locali di un istante in secondi Unix
+pub fn format_time(secs: u64) -> String {
+    chrono::DateTime::from_timestamp(secs as i64, 0)
+        .map(|t| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Roster {
+    pub devices: Vec<RosterEntry>,
+}
+
+impl Roster {
+    fn get_path() -> Result<PathBuf> {
+        Ok(config::config_dir()?.join("roster.json"))
+    }
+
+    pub fn load() -> Self {
+        Self::get_path().ok()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        fs::write(Self::get_path()?, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Registra un avvistamento del peer nel suo ring. True se il registro va salvato:
+    /// dispositivo nuovo, dati cambiati o `last_seen` vecchio di almeno un minuto.
+    pub fn observe(&mut self, peer: &PeerInfo, now: u64) -> bool {
+        let existing = self.devices.iter_mut().find(|d| d.ring_id == peer.ring_id && d.device_id == peer.device_id);
+        let Some(entry) = existing else {
+            self.devices.push(RosterEntry {
+                ring_id: peer.ring_id.clone(),
+                device_id: peer.device_id.clone(),
+                name: peer.name.clone(),
+                os: peer.os.clone(),
+                app_version: peer.app_version.clone(),
+                first_seen: now,
+                last_seen: now,
+            });
+            return true;
+        };
+
+        // last_seen avanza solo insieme a un salvataggio: al minuto è preciso abbastanza
+        let changed = entry.name != peer.name || entry.os != peer.os || entry.app_version != peer.app_version
+            || now.saturating_sub(entry.last_seen) >= LAST_SEEN_SAVE_SECS;
+        if changed {
+            entry.name = peer.name.clone();
+            entry.os = peer.os.clone();
+            entry.app_version = peer.app_version.clone();
+            entry.last_seen = entry.last_seen.max(now);
+        }
+        changed
+    }
+
+    /// Toglie un dispositivo dal registro; torna se lo si rivede in discovery
+    pub fn forget(&mut self, ring_id: &str, device_id: &str) -> bool {
+        let before = self.devices.len();
+        self.devices.retain(|d| !(d.ring_id == ring_id && d.device_id == device_id));
+        self.devices.len() != before
+    }
+
+    pub fn ring<'a>(&'a self, ring_id: &'a str) -> impl Iterator<Item = &'a RosterEntry> + 'a {
+        self.devices.iter().filter(move |d| d.ring_id == ring_id)
+    }
+}
@@ -0,0 +1,125 @@
+use crate::core::config::{SensitiveConfig, SensitivePolicy};
+use regex::Regex;
+
+// Filtro dei contenuti sensibili, applicato in run_monitor PRIMA del broadcast.
+// Due fonti: i flag che i password manager mettono in clipboard ("non sincronizzarmi")
+// e dei rilevatori regex sul testo.
+
+const BUILTIN_DETECTORS: &[(&str, &str)] = &[
+    ("aws_key", r"\b(AKIA|ASIA)[0-9A-Z]{16}\b"),
+    ("jwt", r"\beyJ[A-Za-z0-9_-]{5,}\.eyJ[A-Za-z0-9_-]{5,}\.[A-Za-z0-9_-]+"),
+    ("private_key", r"-----BEGIN [A-Z0-9 ]*PRIVATE KEY( BLOCK)?-----"),
+];
+
+/// Esito del filtro su un clip
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Allow,
+    Block { reason: String },
+    Expire { secs: u32, reason: String },
+}
+
+pub struct SensitiveFilter {
+    config: SensitiveConfig,
+    detectors: Vec<(String, Regex)>,
+}
+
+impl SensitiveFilter {
+    /// Compila i rilevatori. Le regex utente non valide vengono saltate (e restituite).
+    pub fn new(config: &SensitiveConfig) -> (Self, Vec<String>) {
+        let mut detectors = Vec::new();
+        let mut invalid = Vec::new();
+
+        if config.builtin_detectors {
+            for (name, pattern) in BUILTIN_DETECTORS {
+                detectors.push((name.to_string(), Regex::new(pattern).expect("regex integrata valida")));
+            }
+        }
+        for pattern in config.custom_patterns.iter().filter(|p| !p.trim().is_empty()) {
+            match Regex::new(pattern) {
+                Ok(re) => detectors.push((format!("custom:{}", pattern), re)),
+                Err(_) => invalid.push(pattern.clone()),
+            }
+        }
+
+        (Self { config: config.clone(), detectors }, invalid)
+    }
+
+    pub fn config(&self) -> &SensitiveConfig {
+        &self.config
+    }
+
+    /// Nome del primo rilevatore che riconosce il testo
+    pub fn detect(&self, text: &str) -> Option<&str> {
+        self.detectors.iter()
+            .find(|(_, re)| re.is_match(text))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Valuta un clip: `text` è None per le immagini, `hinted` indica un flag di piattaforma
+    pub fn check(&self, text: Option<&str>, hinted: bool) -> Verdict {
+        let reason = if hinted && self.config.platform_hints {
+            "password_manager".to_string()
+        } else {
+            match text.and_then(|t| self.detect(t)) {
+                Some(name) => name.to_string(),
+                None => return Verdict::Allow,
+            }
+        };
+        match self.config.policy {
+            SensitivePolicy::Off => Verdict::Allow,
+            SensitivePolicy::Block => Verdict::Block { reason },
+            SensitivePolicy::Expire => Verdict::Expire { secs: self.config.expire_secs, reason },
+        }
+    }
+}
+
+/// Vero se l'app che ha copiato ha chiesto di escludere il contenuto dai monitor di clipboard.
+/// Bloccante (può lanciare processi esterni): da chiamare in spawn_blocking.
+pub fn platform_hint() -> bool {
+    platform::hint()
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use std::process::Command;
+
+    // Convenzione KDE/KeePassXC: MIME type "x-kde-passwordManagerHint" = "secret".
+    // arboard non espone i target, quindi li chiediamo a wl-paste / xclip se presenti.
+    const HINT_MIME: &str = "x-kde-passwordManagerHint";
+
+    pub fn hint() -> bool {
+        let output = if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            Command::new("wl-paste").args(["--list-types"]).output()
+        } else {
+            Command::new("xclip").args(["-selection", "clipboard", "-t", "TARGETS", "-o"]).output()
+        };
+        match output {
+            Ok(out) if out.status.success() => String::from_utf8_lossy(&out.stdout).lines().any(|l| l.trim() == HINT_MIME),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use windows::core::w;
+    use windows::Win32::System::DataExchange::{IsClipboardFormatAvailable, RegisterClipboardFormatW};
+
+    // Formati registrati dai password manager (KeePass, 1Password, Bitwarden...)
+    pub fn hint() -> bool {
+        unsafe {
+            let exclude = RegisterClipboardFormatW(w!("ExcludeClipboardContentFromMonitorProcessing"));
+            let ignore = RegisterClipboardFormatW(w!("Clipboard Viewer Ignore"));
+            [exclude, ignore].iter().any(|&f| f != 0 && IsClipboardFormatAvailable(f).is_ok())
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows")))]
+mod platform {
+    // TODO: macOS (org.nspasteboard.ConcealedType) richiede NSPasteboard via objc
+    pub fn hint() -> bool {
+        false
+    }
+}
@@ -0,0 +1,160 @@
+use crate::core::config::AppRules;
+
+// Rilevamento dell'applicazione da cui proviene un clip, dove la piattaforma lo permette.
+// È una stima: guardiamo chi possiede la clipboard o, in mancanza, la finestra attiva
+// nel momento in cui il monitor vede il cambiamento.
+
+/// Identificativi dell'app sorgente (nome processo, classe finestra...)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SourceApp {
+    pub names: Vec<String>,
+}
+
+impl SourceApp {
+    pub fn new(names: Vec<String>) -> Self {
+        let mut names: Vec<String> = names.into_iter()
+            .map(|n| n.trim().to_string())
+            .filter(|n| !n.is_empty())
+            .collect();
+        names.dedup();
+        Self { names }
+    }
+
+    /// Nome più leggibile, per log e UI
+    pub fn display_name(&self) -> &str {
+        self.names.last().map(String::as_str).unwrap_or("?")
+    }
+
+    /// Una regola corrisponde se è contenuta (senza maiuscole) in uno degli identificativi
+    pub fn matches(&self, rule: &str) -> bool {
+        let rule = rule.trim().to_lowercase();
+        !rule.is_empty() && self.names.iter().any(|n| n.to_lowercase().contains(&rule))
+    }
+}
+
+/// Vero se un clip copiato da `app` può essere inviato.
+/// Con una allow-list attiva, un'app non rilevabile viene bloccata.
+pub fn is_allowed(rules: &AppRules, app: Option<&SourceApp>) -> bool {
+    if let Some(app) = app {
+        if rules.deny.iter().any(|r| app.matches(r)) {
+            return false;
+        }
+    }
+    let allow: Vec<&String> = rules.allow.iter().filter(|r| !r.trim().is_empty()).collect();
+    if allow.is_empty() {
+        return true;
+    }
+    app.map(|a| allow.iter().any(|r| a.matches(r))).unwrap_or(false)
+}
+
+/// App che ha prodotto il contenuto attuale della clipboard.
+/// Bloccante (può lanciare processi esterni): da chiamare in spawn_blocking.
+pub fn current_source_app() -> Option<SourceApp> {
+    platform::detect().filter(|app| !app.names.is_empty())
+}
+
+#[cfg(target_os = "linux")]
+mod platform {
+    use super::SourceApp;
+    use std::process::Command;
+
+    // Solo X11: _NET_ACTIVE_WINDOW -> WM_CLASS + _NET_WM_PID -> /proc/<pid>/comm.
+    // Su Wayland il compositor non espone la finestra attiva ai client.
+    pub fn detect() -> Option<SourceApp> {
+        if std::env::var_os("DISPLAY").is_none() || std::env::var_os("WAYLAND_DISPLAY").is_some() {
+            return None;
+        }
+        let root = xprop(&["-root", "_NET_ACTIVE_WINDOW"])?;
+        let window = root.split_whitespace().last()?.trim_end_matches(',').to_string();
+        if window == "0x0" {
+            return None;
+        }
+
+        let mut names = Vec::new();
+        if let Some(class) = xprop(&["-id", &window, "WM_CLASS"]) {
+            // WM_CLASS(STRING) = "gnome-terminal-server", "Gnome-terminal"
+            names.extend(class.split('"').skip(1).step_by(2).map(str::to_string));
+        }
+        if let Some(pid) = xprop(&["-id", &window, "_NET_WM_PID"]) {
+            if let Some(pid) = pid.split_whitespace().last().and_then(|p| p.parse::<u32>().ok()) {
+                if let Ok(comm) = std::fs::read_to_string(format!("/proc/{}/comm", pid)) {
+                    names.push(comm);
+                }
+            }
+        }
+        Some(SourceApp::new(names))
+    }
+
+    fn xprop(args: &[&str]) -> Option<String> {
+        let out = Command::new("xprop").args(args).output().ok()?;
+        out.status.success().then(|| String::from_utf8_lossy(&out.stdout).to_string())
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod platform {
+    use super::SourceApp;
+    use windows::core::PWSTR;
+    use windows::Win32::Foundation::{CloseHandle, BOOL};
+    use windows::Win32::System::DataExchange::GetClipboardOwner;
+    use windows::Win32::System::Threading::{OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32, PROCESS_QUERY_LIMITED_INFORMATION};
+    use windows::Win32::UI::WindowsAndMessaging::{GetForegroundWindow, GetWindowThreadProcessId};
+
+    // Proprietario della clipboard, altrimenti finestra in primo piano -> nome dell'eseguibile
+    pub fn detect() -> Option<SourceApp> {
+        unsafe {
+            let mut hwnd = GetClipboardOwner();
+            if hwnd.0 == 0 {
+                hwnd = GetForegroundWindow();
+            }
+            if hwnd.0 == 0 {
+                return None;
+            }
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(hwnd, Some(&mut pid as *mut u32));
+            if pid == 0 {
+                return None;
+            }
+
+            let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, BOOL::from(false), pid).ok()?;
+            let mut buf = [0u16; 260];
+            let mut len = buf.len() as u32;
+            let res = QueryFullProcessImageNameW(process, PROCESS_NAME_WIN32, PWSTR(buf.as_mut_ptr()), &mut len);
+            let _ = CloseHandle(process);
+            res.ok()?;
+
+            let path = String::from_utf16_lossy(&buf[..len as usize]);
+            let exe = path.rsplit('\\').next().unwrap_or(&path);
+            Some(SourceApp::new(vec![exe.trim_end_matches(".exe").to_string(), exe.to_string()]))
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::SourceApp;
+    use std::process::Command;
+
+    // lsappinfo non richiede permessi di accessibilità (a differenza di System Events)
+    pub fn detect() -> Option<SourceApp> {
+        let front = lsappinfo(&["front"])?;
+        let info = lsappinfo(&["info", "-only", "name", front.trim()])?;
+        // "LSDisplayName"="Terminal"
+        let name = info.rsplit('=').next()?.trim().trim_matches('"').to_string();
+        Some(SourceApp::new(vec![name]))
+    }
+
+    fn lsappinfo(args: &[&str]) -> Option<String> {
+        let out = Command::new("lsappinfo").args(args).output().ok()?;
+        out.status.success().then(|| String::from_utf8_lossy(&out.stdout).to_string())
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "windows", target_os = "macos")))]
+mod platform {
+    use super::SourceApp;
+
+    pub fn detect() -> Option<SourceApp> {
+        None
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+
+// Limitatori di frequenza per gli eventi della clipboard.
+
+/// Rilascia un valore solo quando è rimasto invariato per almeno `window`.
+/// Serve per le sorgenti "rumorose" come la selezione PRIMARY, che cambia
+/// a ogni movimento del mouse mentre si trascina una selezione.
+#[derive(Debug)]
+pub struct Debouncer<T> {
+    window: Duration,
+    pending: Option<Pending<T>>,
+}
+
+#[derive(Debug)]
+struct Pending<T> {
+    value: T,
+    since: Instant,
+    fired: bool,
+}
+
+impl<T: PartialEq + Clone> Debouncer<T> {
+    pub fn new(window: Duration) -> Self {
+        Self { window, pending: None }
+    }
+
+    pub fn set_window(&mut self, window: Duration) {
+        self.window = window;
+    }
+
+    /// Registra il valore osservato ora; un valore diverso fa ripartire l'attesa
+    pub fn observe(&mut self, value: T, now: Instant) {
+        match &self.pending {
+            Some(p) if p.value == value => {},
+            _ => self.pending = Some(Pending { value, since: now, fired: false }),
+        }
+    }
+
+    /// Il valore, una sola volta, quando è stabile da almeno `window`
+    pub fn ready(&mut self, now: Instant) -> Option<T> {
+        let pending = self.pending.as_mut()?;
+        if pending.fired || now.duration_since(pending.since) < self.window {
+            return None;
+        }
+        pending.fired = true;
+        Some(pending.value.clone())
+    }
+}
+
+/// Quanti peer dietro lo stesso indirizzo IP (NAT, più ring sullo stesso host) sono
+/// tollerati dal limite per indirizzo, che scatta prima della decifratura
+pub const ADDRESS_LIMIT_FACTOR: u32 = 4;
+
+/// Limite per indirizzo IP derivato da quello per peer (0 = nessun limite, come per il peer)
+pub fn address_limit(per_peer: u32) -> u32 {
+    per_peer.saturating_mul(ADDRESS_LIMIT_FACTOR)
+}
+
+/// Al massimo `limit` eventi per chiave in ogni finestra mobile di `window`.
+/// Lato ricezione, con chiave = peer: uno che invia a raffica non può
+/// sovrascrivere di continuo la clipboard.
+#[derive(Debug)]
+pub struct RateLimiter<K> {
+    window: Duration,
+    limit: u32,
+    events: HashMap<K, VecDeque<Instant>>,
+}
+
+impl<K: Hash + Eq> RateLimiter<K> {
+    /// `limit` = 0 disattiva il limite
+    pub fn new(limit: u32, window: Duration) -> Self {
+        Self { window, limit, events: HashMap::new() }
+    }
+
+    pub fn set_limit(&mut self, limit: u32) {
+        self.limit = limit;
+    }
+
+    /// Vero se l'evento rientra nel limite (e in quel caso viene contato)
+    pub fn allow(&mut self, key: K, now: Instant) -> bool {
+        if self.limit == 0 {
+            return true;
+        }
+        let window = self.window;
+        // Dimentica le chiavi ferme da più di una finestra
+        self.events.retain(|_, seen| {
+            while seen.front().is_some_and(|t| now.duration_since(*t) >= window) {
+                seen.pop_front();
+            }
+            !seen.is_empty()
+        });
+
+        let seen = self.events.entry(key).or_default();
+        if seen.len() >= self.limit as usize {
+            return false;
+        }
+        seen.push_back(now);
+        true
+    }
+}
@@ -0,0 +1,100 @@
+use anyhow::{Result, anyhow};
+use argon2::{Argon2, Algorithm, Version, Params};
+use chacha20poly1305::{
+    XChaCha20Poly1305, Key, XNonce,
+    aead::{Aead, KeyInit}
+};
+use rand::{RngCore, thread_rng};
+use zeroize::Zeroizing;
+
+// Cifratura "a riposo" protetta da passphrase utente.
+// Formato: [MAGIC (4b)] + [m_cost | t_cost | p_cost (3 x u32 BE)] + [SALT (16b)] + [NONCE (24b)] + [CIPHERTEXT]
+// I parametri Argon2id sono salvati nel file, così possiamo alzarli in futuro senza rompere i vecchi file.
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 24;
+const HEADER_LEN: usize = 4 + 12 + SALT_LEN + NONCE_LEN;
+
+// Limiti di sicurezza: un file manomesso non deve poterci far allocare GB di RAM
+const MAX_M_COST_KIB: u32 = 1024 * 1024;
+const MAX_T_COST: u32 = 16;
+const MAX_P_COST: u32 = 8;
+
+#[derive(Clone, Copy, Debug)]
+pub struct KdfParams {
+    pub m_cost_kib: u32,
+    pub t_cost: u32,
+    pub p_cost: u32,
+}
+
+impl Default for KdfParams {
+    fn default() -> Self {
+        // Argon2id, 64 MiB, 3 passate (profilo "interattivo" raccomandato)
+        Self { m_cost_kib: 64 * 1024, t_cost: 3, p_cost: 1 }
+    }
+}
+
+/// Vero se il blob inizia con il magic indicato
+pub fn has_magic(magic: &[u8; 4], blob: &[u8]) -> bool {
+    blob.len() >= 4 && &blob[..4] == magic
+}
+
+/// Cifra `plaintext` con una chiave derivata dalla passphrase (Argon2id)
+pub fn seal(magic: &[u8; 4], plaintext: &[u8], passphrase: &str, params: KdfParams) -> Result<Vec<u8>> {
+    let mut salt = [0u8; SALT_LEN];
+    thread_rng().fill_bytes(&mut salt);
+    let mut nonce_bytes = [0u8; NONCE_LEN];
+    thread_rng().fill_bytes(&mut nonce_bytes);
+
+    let key = derive_key(passphrase, &salt, params)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()));
+    let ciphertext = cipher.encrypt(XNonce::from_slice(&nonce_bytes), plaintext)
+        .map_err(|_| anyhow!("Vault encryption error"))?;
+
+    let mut out = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    out.extend_from_slice(magic);
+    out.extend_from_slice(&params.m_cost_kib.to_be_bytes());
+    out.extend_from_slice(&params.t_cost.to_be_bytes());
+    out.extend_from_slice(&params.p_cost.to_be_bytes());
+    out.extend_from_slice(&salt);
+    out.extend_from_slice(&nonce_bytes);
+    out.extend_from_slice(&ciphertext);
+    Ok(out)
+}
+
+/// Decifra un blob prodotto da `seal`. Fallisce se la passphrase è errata o il file è manomesso.
+pub fn open(magic: &[u8; 4], blob: &[u8], passphrase: &str) -> Result<Vec<u8>> {
+    if !has_magic(magic, blob) || blob.len() < HEADER_LEN {
+        return Err(anyhow!("Formato file non riconosciuto"));
+    }
+
+    let read_u32 = |at: usize| u32::from_be_bytes(blob[at..at + 4].try_into().unwrap());
+    let params = KdfParams {
+        m_cost_kib: read_u32(4),
+        t_cost: read_u32(8),
+        p_cost: read_u32(12),
+    };
+    if params.m_cost_kib > MAX_M_COST_KIB || params.t_cost > MAX_T_COST || params.p_cost > MAX_P_COST {
+        return Err(anyhow!("Parametri KDF non validi"));
+    }
+
+    let salt = &blob[16..16 + SALT_LEN];
+    let nonce_bytes = &blob[16 + SALT_LEN..HEADER_LEN];
+    let ciphertext = &blob[HEADER_LEN..];
+
+    let key = derive_key(passphrase, salt, params)?;
+    let cipher = XChaCha20Poly1305::new(Key::from_slice(key.as_slice()));
+    cipher.decrypt(XNonce::from_slice(nonce_bytes), ciphertext)
+        .map_err(|_| anyhow!("Passphrase errata o file manomesso"))
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], params: KdfParams) -> Result<Zeroizing<[u8; 32]>> {
+    let argon_params = Params::new(params.m_cost_kib, params.t_cost, params.p_cost, Some(32))
+        .map_err(|e| anyhow!("Parametri KDF non validi: {}", e))?;
+    let argon = Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params);
+
+    let mut key = Zeroizing::new([0u8; 32]);
+    argon.hash_password_into(passphrase.as_bytes(), salt, key.as_mut())
+        .map_err(|e| anyhow!("KDF error: {}", e))?;
+    Ok(key)
+}
@@ -1,8 +1,11 @@
 use std::net::SocketAddr;
-use crate::core::identity::RingIdentity;
-use crate::core::config::AppConfig;
+use crate::core::identity::{RingIdentity, SecretString};
+use crate::core::config::{AppConfig, CompressionAlgorithm, ImagePreference};
+use crate::core::roster::RosterEntry;
+use crate::core::doctor::Report;
+use crate::core::metrics::Metrics;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LogLevel {
     Info,
     Success,
@@ -19,10 +22,14 @@ pub struct LogEntry {
 
 impl LogEntry {
     pub fn new(msg: &str) -> Self {
+        Self::with_level(LogLevel::Info, msg)
+    }
+
+    pub fn with_level(level: LogLevel, msg: &str) -> Self {
         let now = chrono::Local::now();
         Self {
             timestamp: now.format("%H:%M:%S").to_string(),
-            level: LogLevel::Info,
+            level,
             message: msg.to_string(),
         }
     }
@@ -30,21 +37,74 @@ impl LogEntry {
 
 
 
+/// Vista non segreta dell'identità, l'unica che arriva alla UI
+#[derive(Debug, Clone)]
+pub struct IdentityInfo {
+    pub discovery_id: String,
+}
+
+impl From<&RingIdentity> for IdentityInfo {
+    fn from(identity: &RingIdentity) -> Self {
+        Self { discovery_id: identity.discovery_id.clone() }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct PeerInfo {
     pub name: String,
     pub ip: SocketAddr,
     pub device_id: String,
     pub last_seen: std::time::SystemTime,
+    /// Formato immagini richiesto dal peer (None = peer vecchio o nessuna preferenza)
+    pub image_pref: Option<ImagePreference>,
+    /// Algoritmi di compressione che il peer sa decomprimere (vuoto = peer vecchio)
+    pub compression: Vec<CompressionAlgorithm>,
+    /// Il peer può scrivere nella selezione PRIMARY (Linux)
+    pub primary_selection: bool,
+    /// discovery_id del ring in cui è stato trovato
+    pub ring_id: String,
+    /// Sistema operativo e versione dell'app (None = peer vecchio)
+    pub os: Option<String>,
+    pub app_version: Option<String>,
+}
+
+/// Clip ricevuto in attesa di approvazione: alla UI arriva solo l'anteprima
+#[derive(Debug, Clone)]
+pub struct PendingClipInfo {
+    pub id: u64,
+    pub from: String,
+    pub received_at: String,
+    pub preview: String,
 }
 
 #[derive(Debug, Clone)]
 pub enum CoreEvent {
     Log(LogEntry),
-    // Updated to transport PeerInfo instead of tuple
-    PeersUpdated(Vec<PeerInfo>),
-    IdentityLoaded(RingIdentity),
+    // Lista completa dei peer di un ring (vuota quando il ring viene fermato)
+    PeersUpdated { ring_id: String, peers: Vec<PeerInfo> },
+    // discovery_id dei ring aggiuntivi (oltre a quello di IdentityLoaded)
+    RingsLoaded(Vec<String>),
+    // Registro completo dei dispositivi visti, di tutti i ring
+    RosterUpdated(Vec<RosterEntry>),
+    // Coda completa dei clip in attesa di approvazione
+    PendingClips(Vec<PendingClipInfo>),
+    IdentityLoaded(IdentityInfo),
+    // Risposta a RevealMnemonic: la UI la tiene solo finché la chiave è visibile
+    MnemonicRevealed(SecretString),
+    // Risposta a RevealRingMnemonic
+    RingMnemonicRevealed { ring_id: String, mnemonic: SecretString },
+    // L'identità è protetta da passphrase: la UI deve chiederla (failed = tentativo precedente errato)
+    UnlockRequired { failed: bool },
+    IdentityProtection { passphrase: bool },
+    // Config modificata dal core (es. import di un backup)
+    ConfigReloaded(Box<AppConfig>),
+    // Codice dell'invito di pairing attivo (None = nessun invito)
+    PairingCode(Option<String>),
     ServiceStateChanged { running: bool },
+    // Esito della diagnostica chiesta con RunDoctor
+    DoctorReport(Report),
+    // Statistiche dei trasferimenti dall'avvio (inviate solo quando cambiano)
+    MetricsUpdated(Box<Metrics>),
     // Decoupled notification request
     Notify { title: String, body: String },
 }
@@ -52,8 +112,32 @@ pub enum CoreEvent {
 #[derive(Debug, Clone)]
 pub enum UiCommand {
     SetPaused(bool),
-    UpdateConfig(AppConfig), // <--- NUOVO: Salva nuova config
-    #[allow(dead_code)] JoinRing(String),
+    UpdateConfig(Box<AppConfig>), // <--- NUOVO: Salva nuova config
+    // Invia la clipboard attuale a un solo peer (device_id) invece che a tutti
+    SendTo(String),
+    // Push manuale: invia subito la clipboard attuale a tutti i peer
+    Push,
+    // Accetta (scrive nella clipboard) o scarta un clip in attesa
+    ResolveClip { id: u64, accept: bool },
+    #[allow(dead_code)] JoinRing(SecretString), // Parole oppure payload di pairing
+    JoinFromQrImage(String),
+    StartInvite,
+    CancelInvite,
+    JoinWithCode(String),
     #[allow(dead_code)] GenerateNewIdentity,
+    // Ring aggiuntivo, attivo insieme a quello principale (phrase None = ring nuovo)
+    AddRing { name: String, phrase: Option<SecretString> },
+    RemoveRing(String),
+    RevealRingMnemonic(String),
+    // Toglie un dispositivo dal registro dei dispositivi visti
+    ForgetDevice { ring_id: String, device_id: String },
+    // L'utente ha chiesto esplicitamente di vedere la chiave segreta
+    RevealMnemonic,
+    // Esegue la diagnostica (qualche secondo): risponde con DoctorReport
+    RunDoctor,
+    Unlock(SecretString),
+    SetProtection(Option<SecretString>), // None = torna alla chiave macchina
+    ExportBackup { path: String, passphrase: SecretString },
+    ImportBackup { path: String, passphrase: SecretString },
     Quit,
 }
\ No newline at end of file
@@ -6,13 +6,19 @@ use rust_clip::events;
 // use rust_i18n::t; // Import t! macro helper if needed via library re-export or direct dependency
 
 use clap::{Parser, Subcommand};
-use core::identity::RingIdentity;
-use core::config::AppConfig;
-use core::{discovery, clipboard};
-use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use core::identity::{RingIdentity, KeyProtection, SecretString};
+use core::vault::KdfParams;
+use core::config::{AppConfig, MetricsConfig, SharedConfig};
+use core::{discovery, clipboard, pairing, pake, logging, doctor, metrics};
+use core::metrics::{Metrics, SharedMetrics};
+use core::roster::{self, Roster, SharedRoster};
+use std::sync::Mutex;
+use std::sync::{Arc, RwLock, atomic::{AtomicBool, Ordering}};
+use std::path::PathBuf;
 use dashmap::DashMap;
 use flume::{Sender, Receiver};
 use events::{UiCommand, CoreEvent};
+use tracing::{info, warn, error};
 
 #[cfg(target_os = "windows")]
 use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
@@ -24,29 +30,205 @@ struct Cli {
 }
 
 #[derive(Subcommand)]
-enum Commands { New, Join, Start, Gui }
+enum Commands {
+    New, Start, Gui, Protect, Unprotect, Pair, Invite,
+    Join {
+        /// Immagine contenente il QR di pairing (invece di digitare le parole)
+        #[arg(long)]
+        qr: Option<PathBuf>,
+        /// Codice monouso mostrato da `rust-clip invite` sull'altro dispositivo
+        #[arg(long)]
+        code: Option<String>,
+    },
+    Export { path: PathBuf },
+    Import { path: PathBuf },
+    /// Invia subito la clipboard attuale a tutti i dispositivi del ring
+    Push,
+    /// Invia la clipboard attuale a un solo dispositivo del ring
+    Send {
+        /// Nome o device_id del destinatario
+        #[arg(long)]
+        to: String,
+    },
+    /// Elenca i dispositivi visti in ogni ring, con impronta, sistema e versione
+    Devices,
+    /// Controlla porta, mDNS, ring, orologi, clipboard e cifratura e stampa un report
+    Doctor,
+    /// Gestisce i ring aggiuntivi, attivi insieme a quello principale
+    Ring {
+        #[command(subcommand)]
+        action: RingAction,
+    },
+}
+
+#[derive(Subcommand)]
+enum RingAction {
+    /// Elenca i ring e il loro stato
+    List,
+    /// Aggiunge un ring chiedendone le parole (o il codice di pairing)
+    Add {
+        #[arg(long)]
+        name: String,
+        /// Crea un ring nuovo invece di unirsi a uno esistente
+        #[arg(long)]
+        new: bool,
+    },
+    /// Rimuove un ring aggiuntivo (nome o discovery_id)
+    Remove { ring: String },
+    Enable { ring: String },
+    Disable { ring: String },
+    /// Mostra le parole di un ring, da inserire sugli altri dispositivi
+    Show { ring: String },
+}
 
 fn main() -> anyhow::Result<()> {
     let args = Cli::parse();
     if args.command.is_some() && !matches!(args.command, Some(Commands::Gui)) {
         attach_console_if_windows();
     }
+    // Tenuta fino all'uscita: al drop scrive su file i messaggi ancora in coda
+    let _log_guard = logging::init(&AppConfig::load().logging);
 
     match args.command {
         Some(Commands::Start) => run_async_backend(None, None)?,
-        Some(Commands::New) => { let _ = RingIdentity::create_new()?; }
-        Some(Commands::Join) => {
-             print!("Inserisci le parole del ring: ");
-             use std::io::{self, Write};
-             io::stdout().flush()?;
-             let mut phrase = String::new();
-             io::stdin().read_line(&mut phrase)?;
-             let id = RingIdentity::from_mnemonic(phrase.trim())?;
-             id.save()?;
+        Some(Commands::New) => {
+             let protection = cli_current_protection()?;
+             RingIdentity::generate()?.save_with(&protection)?;
+        }
+        Some(Commands::Join { qr, code }) => {
+             let id = if let Some(code) = code {
+                 let (id, inviter) = tokio::runtime::Runtime::new()?.block_on(pake::join(&code))?;
+                 println!("🤝 Credenziali ricevute da {}", inviter);
+                 id
+             } else {
+                 let input = match qr {
+                     Some(path) => SecretString::new(pairing::decode_qr_image(&path)?),
+                     None => {
+                         print!("Inserisci le parole del ring (o il codice di pairing): ");
+                         use std::io::{self, Write};
+                         io::stdout().flush()?;
+                         let mut phrase = String::new();
+                         io::stdin().read_line(&mut phrase)?;
+                         SecretString::new(phrase)
+                     }
+                 };
+                 pairing::identity_from_input(input.expose())?
+             };
+             let protection = cli_current_protection()?;
+             id.save_with(&protection)?;
+        }
+        Some(Commands::Protect) => {
+             let (id, protection) = cli_unlock()?;
+             let pass = prompt_new_passphrase()?;
+             id.change_protection(&protection, &KeyProtection::Passphrase(pass))?;
+        }
+        Some(Commands::Unprotect) => {
+             let (id, protection) = cli_unlock()?;
+             if !protection.is_passphrase() {
+                 println!("L'identità non è protetta da passphrase.");
+                 return Ok(());
+             }
+             id.change_protection(&protection, &KeyProtection::Machine)?;
+        }
+        Some(Commands::Pair) => {
+             let (id, _) = cli_unlock()?;
+             let payload = pairing::pairing_payload(&id)?;
+             println!("{}", pairing::render_qr_terminal(&payload)?);
+             println!("Codice di pairing: {}", payload.as_str());
+             println!("⚠️  Chiunque veda questo QR può unirsi al Ring.");
+        }
+        Some(Commands::Invite) => {
+             let (id, _) = cli_unlock()?;
+             let code = pake::generate_code();
+             println!("🔢 Codice di pairing: {}", code);
+             println!("   Sull'altro dispositivo: rust-clip join --code {} (valido {} s)", code, pake::INVITE_TIMEOUT.as_secs());
+             tokio::runtime::Runtime::new()?.block_on(pake::invite(&id, &AppConfig::load().device_name, &code))?;
+             println!("✅ Dispositivo aggiunto al Ring");
+        }
+        Some(Commands::Export { path }) => {
+             let (id, protection) = cli_unlock()?;
+             let extra = RingIdentity::load_extra_rings(&protection)?;
+             println!("Scegli una passphrase per il backup.");
+             let pass = prompt_new_passphrase()?;
+             let data = id.export_backup(&extra, &AppConfig::load().device_name, pass.expose(), KdfParams::default())?;
+             core::identity::write_private_file(&path, &data)?;
+             println!("💾 Backup salvato in {:?}", path);
+        }
+        Some(Commands::Import { path }) => {
+             let data = std::fs::read(&path)?;
+             let pass = SecretString::new(rpassword::prompt_password("Passphrase del backup: ")?);
+             let (id, backup) = RingIdentity::import_backup(&data, pass.expose())?;
+             let protection = cli_current_protection()?;
+             id.save_with(&protection)?;
+             // Un backup di una versione precedente non ha i ring aggiuntivi: restano quelli attuali
+             if let Some(extra) = backup.extra_rings()? {
+                 RingIdentity::save_extra_rings(&extra, &protection)?;
+                 println!("🔗 Ring aggiuntivi ripristinati: {}", extra.len());
+             }
+             let mut config = AppConfig::load();
+             config.device_name = backup.device_name.clone();
+             config.save()?;
+             println!("✅ Backup importato (dispositivo: {})", config.device_name);
+        }
+        Some(Commands::Send { to }) => {
+             let (id, protection) = cli_unlock()?;
+             let config = AppConfig::load();
+             let rings = active_rings(&id, &RingIdentity::load_extra_rings(&protection)?, &config);
+             println!("🔍 Cerco '{}' nella rete locale...", to);
+             let peer = discovery::find_peer(&rings, &config.device_name, &to, std::time::Duration::from_secs(10))?;
+             let name = peer.name.clone();
+             let delivered = tokio::runtime::Runtime::new()?.block_on(clipboard::send_current(&rings, vec![peer], config))?;
+             if delivered == 0 {
+                 anyhow::bail!("Clip non inviato a {}", name);
+             }
+        }
+        Some(Commands::Push) => {
+             let (id, protection) = cli_unlock()?;
+             let config = AppConfig::load();
+             let rings = active_rings(&id, &RingIdentity::load_extra_rings(&protection)?, &config);
+             println!("🔍 Cerco i dispositivi del ring...");
+             let peers = discovery::find_peers(&rings, &config.device_name, std::time::Duration::from_secs(3))?;
+             if peers.is_empty() {
+                 anyhow::bail!("Nessun dispositivo del ring trovato");
+             }
+             let total = peers.len();
+             let delivered = tokio::runtime::Runtime::new()?.block_on(clipboard::send_current(&rings, peers, config))?;
+             println!("📤 Clip inviato a {}/{} dispositivi", delivered, total);
+        }
+        Some(Commands::Ring { action }) => run_ring_command(action)?,
+        Some(Commands::Devices) => {
+             let (id, protection) = cli_unlock()?;
+             let extra = RingIdentity::load_extra_rings(&protection)?;
+             let config = AppConfig::load();
+             let roster = Roster::load();
+             println!("Questo dispositivo: {}", roster::device_code(&RingIdentity::get_derived_device_id()));
+             for ring in std::iter::once(&id).chain(&extra) {
+                 println!("\n{}", config.rings.name(&ring.discovery_id));
+                 for device in roster.ring(&ring.discovery_id) {
+                     println!("  {}  {}  {} {}  (ultimo avvistamento: {})",
+                         device.name,
+                         device.device_code(),
+                         device.os.as_deref().unwrap_or("?"),
+                         device.app_version.as_deref().unwrap_or("?"),
+                         roster::format_time(device.last_seen));
+                 }
+             }
+        }
+        Some(Commands::Doctor) => {
+             let config = AppConfig::load();
+             rust_i18n::set_locale(&config.language);
+             // Un'identità che non si decifra è uno dei problemi da riportare, non un motivo per fermarsi
+             let protection = cli_unlock().map(|(_, p)| p).unwrap_or(KeyProtection::Machine);
+             let report = doctor::run(&protection, &config);
+             print!("{}", report.render());
+             if report.failures() > 0 {
+                 anyhow::bail!("{} controlli falliti", report.failures());
+             }
         }
         None | Some(Commands::Gui) => {
             let (tx_ui, rx_core) = flume::unbounded::<UiCommand>(); 
             let (tx_core, rx_ui) = flume::unbounded::<CoreEvent>(); 
+            logging::attach_ui(tx_core.clone());
             std::thread::spawn(move || {
                 let _ = run_async_backend(Some(rx_core), Some(tx_core));
             });
@@ -67,16 +249,31 @@ fn run_async_backend(rx_cmd: Option<Receiver<UiCommand>>, tx_event: Option<Sende
     let rt = tokio::runtime::Runtime::new()?;
     rt.block_on(async {
         let mut config = AppConfig::load();
+        let legacy = config.legacy_peer_rules();
+        if !legacy.is_empty() {
+            warn!("⚠️ Regole per dispositivo con ID obsoleti, ignorate (da reimpostare nella Dashboard): {}", legacy.join(", "));
+        }
+        // Copia letta dai servizi a runtime (filtri ecc.), aggiornata a ogni UpdateConfig
+        let shared_config: SharedConfig = Arc::new(RwLock::new(config.clone()));
         let paused = Arc::new(AtomicBool::new(false));
-        let peers: discovery::PeerMap = Arc::new(DashMap::new());
+        let roster: SharedRoster = Arc::new(Mutex::new(Roster::load()));
+        // Statistiche dall'avvio: restano anche quando i servizi ripartono
+        let metrics: SharedMetrics = Arc::new(Mutex::new(Metrics::default()));
+        // Invii espliciti eseguiti dal monitor della clipboard: Some = un solo peer, None = tutti (push)
+        let (tx_send_to, rx_send_to) = flume::unbounded::<Option<String>>();
+        // Decisioni dell'utente sui clip in attesa di approvazione: (id, accettato)
+        let (tx_decisions, rx_decisions) = flume::unbounded::<(u64, bool)>();
 
-        // Helper per caricare l'identità
-        let load_ident = || RingIdentity::load().unwrap_or_else(|_| RingIdentity::create_new().unwrap());
-        let mut identity = load_ident();
+        // Carica l'identità (chiede la passphrase se il file è protetto)
+        let (mut identity, mut protection) = unlock_identity(rx_cmd.as_ref(), tx_event.as_ref()).await?;
+        let mut extra_rings = RingIdentity::load_extra_rings(&protection).unwrap_or_else(|e| {
+            warn!("⚠️ Ring aggiuntivi non caricati: {}", e);
+            Vec::new()
+        });
 
         // SE CLI MODE (tx_event is None), creiamo un canale locale per gestire notifiche
         let local_rx = if tx_event.is_none() {
-            let (tx, rx) = flume::unbounded::<CoreEvent>();
+            let (_tx, rx) = flume::unbounded::<CoreEvent>();
             Some(rx)
         } else {
             None
@@ -95,7 +292,10 @@ fn run_async_backend(rx_cmd: Option<Receiver<UiCommand>>, tx_event: Option<Sende
         };
 
         if let Some(tx) = &tx_event {
-            let _ = tx.send(CoreEvent::IdentityLoaded(identity.clone()));
+            let _ = tx.send(CoreEvent::IdentityLoaded((&identity).into()));
+            let _ = tx.send(CoreEvent::IdentityProtection { passphrase: protection.is_passphrase() });
+            let _ = tx.send(CoreEvent::RingsLoaded(ring_ids(&extra_rings)));
+            let _ = tx.send(CoreEvent::RosterUpdated(roster.lock().unwrap().devices.clone()));
         }
 
         // Se abbiamo un listener locale (CLI mode), avviamo handler
@@ -106,7 +306,6 @@ fn run_async_backend(rx_cmd: Option<Receiver<UiCommand>>, tx_event: Option<Sende
                          CoreEvent::Notify { title, body } => {
                              let _ = notify_rust::Notification::new().summary(&title).body(&body).show();
                          },
-                         CoreEvent::Log(l) => println!("[{}] {}", l.timestamp, l.message),
                          _ => {}
                      }
                  }
@@ -114,39 +313,106 @@ fn run_async_backend(rx_cmd: Option<Receiver<UiCommand>>, tx_event: Option<Sende
         }
 
         // --- GESTIONE TASK DINAMICI (Hot Reload) ---
-        let mut discovery_handle: Option<tokio::task::JoinHandle<()>> = None;
+        // Un discovery per ring attivo, ognuno sul suo thread: si fermano col loro flag
+        let mut discovery_threads: Vec<(Arc<AtomicBool>, std::thread::JoinHandle<()>)> = Vec::new();
+        let mut invite_handle: Option<tokio::task::JoinHandle<()>> = None;
+        let mut join_handle: Option<tokio::task::JoinHandle<()>> = None;
+        // Esito dei join con codice, che girano fuori dal loop dei comandi
+        let (tx_joined, rx_joined) = flume::unbounded::<anyhow::Result<(RingIdentity, String)>>();
         let mut sync_handle: Option<tokio::task::JoinHandle<()>> = None;
+        let mut metrics_handle: Option<tokio::task::JoinHandle<()>> = None;
 
         // Macro/Closure per avviare/riavviare tutto
-        let mut restart_services = |id: RingIdentity, cfg: AppConfig, p: discovery::PeerMap, pz: Arc<AtomicBool>, tx: Option<Sender<CoreEvent>>| {
-            println!("🔄 Starting/Restarting core services...");
-            if let Some(h) = discovery_handle.take() { h.abort(); }
+        let mut restart_services = |rings: Vec<RingIdentity>, cfg: AppConfig, pz: Arc<AtomicBool>, tx: Option<Sender<CoreEvent>>| {
+            info!("🔄 Starting/Restarting core services...");
+            for (stop, _) in &discovery_threads { stop.store(true, Ordering::Relaxed); }
+            // Il vecchio annuncio va ritirato prima del nuovo, o i peer ci toglierebbero dalla lista (max ~1,5 s).
+            // L'attesa avviene fuori dal runtime: i nuovi discovery partono quando `retired` si chiude.
+            let old_threads: Vec<_> = discovery_threads.drain(..).map(|(_, handle)| handle).collect();
+            let (retired_tx, retired) = flume::bounded::<()>(0);
+            tokio::task::spawn_blocking(move || {
+                for handle in old_threads { let _ = handle.join(); }
+                drop(retired_tx);
+            });
             if let Some(h) = sync_handle.take() { h.abort(); }
-            p.clear();
-
-            let id_d = id.clone();
-            let p_d = p.clone();
-            let cfg_d = cfg.clone();
-            let tx_d = tx.clone();
-            discovery_handle = Some(tokio::spawn(async move {
-                let _ = discovery::start_lan_discovery(id_d, p_d, cfg_d, tx_d);
-            }));
 
-            let id_s = id.clone();
-            let p_s = p.clone();
-            let cfg_s = cfg.clone();
+            let mut active = Vec::new();
+            for ring in rings {
+                let peers: discovery::PeerMap = Arc::new(DashMap::new());
+                let stop = Arc::new(AtomicBool::new(false));
+                active.push(clipboard::ActiveRing::new(&ring, peers.clone()));
+
+                let cfg_d = cfg.clone();
+                let tx_d = tx.clone();
+                let roster_d = roster.clone();
+                let stop_d = stop.clone();
+                let retired_d = retired.clone();
+                let handle = std::thread::spawn(move || {
+                    let _ = retired_d.recv();
+                    if let Err(e) = discovery::start_lan_discovery(ring, peers, cfg_d, tx_d, roster_d, stop_d) {
+                        error!("❌ Discovery error: {}", e);
+                    }
+                });
+                discovery_threads.push((stop, handle));
+            }
+
+            let cfg_s = shared_config.clone();
             let pz_s = pz.clone();
             let tx_s = tx.clone(); // Passiamo TX anche qui
+            let rx_send_s = rx_send_to.clone();
+            let rx_decisions_s = rx_decisions.clone();
+            let metrics_s = metrics.clone();
             sync_handle = Some(tokio::spawn(async move {
-                let _ = clipboard::start_clipboard_sync(id_s, p_s, cfg_s, pz_s, tx_s).await;
+                let _ = clipboard::start_clipboard_sync(active, cfg_s, pz_s, rx_send_s, rx_decisions_s, tx_s, metrics_s).await;
             }));
         };
 
         // Primo avvio
-        restart_services(identity.clone(), config.clone(), peers.clone(), paused.clone(), Some(tx_internal.clone()));
+        restart_services(active_rings(&identity, &extra_rings, &config), config.clone(), paused.clone(), Some(tx_internal.clone()));
+        restart_metrics_endpoint(&mut metrics_handle, &config.metrics, &metrics);
+
+        // La Dashboard riceve le statistiche ogni pochi secondi, solo se sono cambiate
+        if let Some(tx) = tx_event.clone() {
+            let metrics = metrics.clone();
+            tokio::spawn(async move {
+                let mut published = 0;
+                loop {
+                    tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+                    let snapshot = {
+                        let metrics = metrics.lock().unwrap();
+                        (metrics.revision != published).then(|| metrics.clone())
+                    };
+                    if let Some(snapshot) = snapshot {
+                        published = snapshot.revision;
+                        if tx.send(CoreEvent::MetricsUpdated(Box::new(snapshot))).is_err() { break; }
+                    }
+                }
+            });
+        }
 
         if let Some(rx) = rx_cmd {
-            while let Ok(cmd) = rx.recv_async().await {
+            loop {
+                let cmd = tokio::select! {
+                    cmd = rx.recv_async() => match cmd {
+                        Ok(cmd) => cmd,
+                        Err(_) => break,
+                    },
+                    Ok(joined) = rx_joined.recv_async() => {
+                        match joined {
+                            Ok((id, inviter)) => {
+                                id.save_with(&protection).ok();
+                                identity = id;
+                                info!("🤝 Joined the Ring of {}", inviter);
+                                if let Some(tx) = &tx_event { let _ = tx.send(CoreEvent::IdentityLoaded((&identity).into())); }
+                                restart_services(active_rings(&identity, &extra_rings, &config), config.clone(), paused.clone(), Some(tx_internal.clone()));
+                            },
+                            Err(e) => {
+                                error!("❌ Pairing failed: {}", e);
+                            }
+                        }
+                        continue;
+                    },
+                };
                 match cmd {
                     UiCommand::SetPaused(p) => {
                         paused.store(p, Ordering::Relaxed);
@@ -155,26 +421,253 @@ fn run_async_backend(rx_cmd: Option<Receiver<UiCommand>>, tx_event: Option<Sende
                         }
                     },
                     UiCommand::UpdateConfig(new_cfg) => {
-                        let restart_needed = new_cfg.device_name != config.device_name;
+                        let new_cfg = *new_cfg;
+                        let rings_toggled = std::iter::once(&identity).chain(&extra_rings)
+                            .any(|r| new_cfg.rings.is_enabled(&r.discovery_id) != config.rings.is_enabled(&r.discovery_id));
+                        let restart_needed = new_cfg.device_name != config.device_name
+                            || new_cfg.image.preferred != config.image.preferred
+                            || rings_toggled;
+                        if new_cfg.logging.verbosity != config.logging.verbosity {
+                            logging::set_verbosity(new_cfg.logging.verbosity);
+                        }
+                        if new_cfg.metrics != config.metrics {
+                            restart_metrics_endpoint(&mut metrics_handle, &new_cfg.metrics, &metrics);
+                        }
                         new_cfg.save().ok();
                         config = new_cfg;
+                        *shared_config.write().unwrap() = config.clone();
                         if restart_needed {
-                            restart_services(identity.clone(), config.clone(), peers.clone(), paused.clone(), Some(tx_internal.clone()));
+                            restart_services(active_rings(&identity, &extra_rings, &config), config.clone(), paused.clone(), Some(tx_internal.clone()));
                         }
                     },
+                    UiCommand::SendTo(device_id) => {
+                        let _ = tx_send_to.send(Some(device_id));
+                    },
+                    UiCommand::Push => {
+                        let _ = tx_send_to.send(None);
+                    },
+                    UiCommand::ResolveClip { id, accept } => {
+                        let _ = tx_decisions.send((id, accept));
+                    },
                     UiCommand::JoinRing(phrase) => {
-                        if let Ok(id) = RingIdentity::from_mnemonic(&phrase) {
-                            id.save().ok();
+                        if let Ok(id) = pairing::identity_from_input(phrase.expose()) {
+                            id.save_with(&protection).ok();
                             identity = id;
-                            if let Some(tx) = &tx_event { let _ = tx.send(CoreEvent::IdentityLoaded(identity.clone())); }
-                            restart_services(identity.clone(), config.clone(), peers.clone(), paused.clone(), Some(tx_internal.clone()));
+                            if let Some(tx) = &tx_event { let _ = tx.send(CoreEvent::IdentityLoaded((&identity).into())); }
+                            restart_services(active_rings(&identity, &extra_rings, &config), config.clone(), paused.clone(), Some(tx_internal.clone()));
                         }
                     },
+                    UiCommand::JoinFromQrImage(path) => {
+                        let res = pairing::decode_qr_image(std::path::Path::new(&path))
+                            .map(SecretString::new)
+                            .and_then(|payload| pairing::identity_from_input(payload.expose()));
+                        match res {
+                            Ok(id) => {
+                                id.save_with(&protection).ok();
+                                identity = id;
+                                if let Some(tx) = &tx_event { let _ = tx.send(CoreEvent::IdentityLoaded((&identity).into())); }
+                                restart_services(active_rings(&identity, &extra_rings, &config), config.clone(), paused.clone(), Some(tx_internal.clone()));
+                            },
+                            Err(e) => {
+                                error!("❌ QR pairing failed: {}", e);
+                            }
+                        }
+                    },
+                    UiCommand::StartInvite => {
+                        if let Some(h) = invite_handle.take() { h.abort(); }
+                        let code = pake::generate_code();
+                        if let Some(tx) = &tx_event { let _ = tx.send(CoreEvent::PairingCode(Some(code.clone()))); }
+                        let id = identity.clone();
+                        let name = config.device_name.clone();
+                        let tx_ui = tx_event.clone();
+                        invite_handle = Some(tokio::spawn(async move {
+                            match pake::invite(&id, &name, &code).await {
+                                Ok(()) => info!("🤝 Device paired with code"),
+                                Err(e) => error!("❌ Pairing invite ended: {}", e),
+                            }
+                            if let Some(tx) = &tx_ui { let _ = tx.send(CoreEvent::PairingCode(None)); }
+                        }));
+                    },
+                    UiCommand::CancelInvite => {
+                        if let Some(h) = invite_handle.take() { h.abort(); }
+                        if let Some(tx) = &tx_event { let _ = tx.send(CoreEvent::PairingCode(None)); }
+                    },
+                    UiCommand::JoinWithCode(code) => {
+                        // Attende l'invitante fino al timeout: non blocchiamo gli altri comandi
+                        if let Some(h) = join_handle.take() { h.abort(); }
+                        let tx_joined = tx_joined.clone();
+                        join_handle = Some(tokio::spawn(async move {
+                            let _ = tx_joined.send(pake::join(&code).await);
+                        }));
+                    },
                     UiCommand::GenerateNewIdentity => {
-                        identity = RingIdentity::create_new().unwrap();
-                        if let Some(tx) = &tx_event { let _ = tx.send(CoreEvent::IdentityLoaded(identity.clone())); }
-                        restart_services(identity.clone(), config.clone(), peers.clone(), paused.clone(), Some(tx_internal.clone()));
+                        identity = RingIdentity::generate().unwrap();
+                        identity.save_with(&protection).ok();
+                        if let Some(tx) = &tx_event { let _ = tx.send(CoreEvent::IdentityLoaded((&identity).into())); }
+                        restart_services(active_rings(&identity, &extra_rings, &config), config.clone(), paused.clone(), Some(tx_internal.clone()));
                     }
+                    UiCommand::AddRing { name, phrase } => {
+                        let res = match phrase {
+                            Some(p) => pairing::identity_from_input(p.expose()),
+                            None => RingIdentity::generate(),
+                        };
+                        let ring = match res {
+                            Ok(ring) if std::iter::once(&identity).chain(&extra_rings).any(|r| r.discovery_id == ring.discovery_id) => {
+                                warn!("⚠️ Ring already active on this device");
+                                continue;
+                            },
+                            Ok(ring) => ring,
+                            Err(e) => {
+                                error!("❌ Ring not added: {}", e);
+                                continue;
+                            }
+                        };
+                        extra_rings.push(ring);
+                        let rings = extra_rings.clone();
+                        let np = protection.clone();
+                        // Con la passphrase Argon2 è volutamente lento: non blocchiamo il runtime
+                        if let Ok(Err(e)) = tokio::task::spawn_blocking(move || RingIdentity::save_extra_rings(&rings, &np)).await {
+                            error!("❌ Ring not saved: {}", e);
+                        }
+                        let ring_id = extra_rings.last().map(|r| r.discovery_id.clone()).unwrap_or_default();
+                        config.rings.entry(&ring_id).name = name.trim().to_string();
+                        config.save().ok();
+                        *shared_config.write().unwrap() = config.clone();
+                        info!("➕ Ring added: {}", config.rings.name(&ring_id));
+                        if let Some(tx) = &tx_event {
+                            let _ = tx.send(CoreEvent::RingsLoaded(ring_ids(&extra_rings)));
+                            let _ = tx.send(CoreEvent::ConfigReloaded(Box::new(config.clone())));
+                        }
+                        restart_services(active_rings(&identity, &extra_rings, &config), config.clone(), paused.clone(), Some(tx_internal.clone()));
+                    },
+                    UiCommand::RemoveRing(ring_id) => {
+                        let before = extra_rings.len();
+                        extra_rings.retain(|r| r.discovery_id != ring_id);
+                        if extra_rings.len() == before {
+                            continue;
+                        }
+                        let rings = extra_rings.clone();
+                        let np = protection.clone();
+                        if let Ok(Err(e)) = tokio::task::spawn_blocking(move || RingIdentity::save_extra_rings(&rings, &np)).await {
+                            error!("❌ Ring not saved: {}", e);
+                        }
+                        info!("➖ Ring removed: {}", config.rings.name(&ring_id));
+                        config.rings.rings.remove(&ring_id);
+                        config.save().ok();
+                        *shared_config.write().unwrap() = config.clone();
+                        if let Some(tx) = &tx_event {
+                            let _ = tx.send(CoreEvent::RingsLoaded(ring_ids(&extra_rings)));
+                            let _ = tx.send(CoreEvent::ConfigReloaded(Box::new(config.clone())));
+                        }
+                        restart_services(active_rings(&identity, &extra_rings, &config), config.clone(), paused.clone(), Some(tx_internal.clone()));
+                    },
+                    UiCommand::RevealRingMnemonic(ring_id) => {
+                        if let (Some(tx), Some(ring)) = (&tx_event, extra_rings.iter().find(|r| r.discovery_id == ring_id)) {
+                            let _ = tx.send(CoreEvent::RingMnemonicRevealed { ring_id, mnemonic: SecretString::new(ring.mnemonic.clone()) });
+                        }
+                    },
+                    UiCommand::ForgetDevice { ring_id, device_id } => {
+                        let mut roster = roster.lock().unwrap();
+                        if roster.forget(&ring_id, &device_id) {
+                            roster.save().ok();
+                            if let Some(tx) = &tx_event {
+                                let _ = tx.send(CoreEvent::RosterUpdated(roster.devices.clone()));
+                            }
+                        }
+                    },
+                    UiCommand::SetProtection(pass) => {
+                        let new_protection = match pass {
+                            Some(p) => KeyProtection::Passphrase(p),
+                            None => KeyProtection::Machine,
+                        };
+                        let id = identity.clone();
+                        let rings = extra_rings.clone();
+                        let np = new_protection.clone();
+                        // Argon2 è volutamente lento: non blocchiamo il runtime
+                        let res = tokio::task::spawn_blocking(move || {
+                            id.save_with(&np)?;
+                            RingIdentity::save_extra_rings(&rings, &np)
+                        }).await
+                            .map_err(anyhow::Error::from)
+                            .and_then(|r| r);
+                        match res {
+                            Ok(()) => {
+                                protection = new_protection;
+                                info!("🔐 Identity protection updated");
+                                if let Some(tx) = &tx_event {
+                                    let _ = tx.send(CoreEvent::IdentityProtection { passphrase: protection.is_passphrase() });
+                                }
+                            },
+                            Err(e) => {
+                                error!("❌ Identity protection update failed: {}", e);
+                            }
+                        }
+                    },
+                    UiCommand::ExportBackup { path, passphrase } => {
+                        let id = identity.clone();
+                        let rings = extra_rings.clone();
+                        let name = config.device_name.clone();
+                        let target = PathBuf::from(&path);
+                        let res = tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+                            let data = id.export_backup(&rings, &name, passphrase.expose(), KdfParams::default())?;
+                            core::identity::write_private_file(&target, &data)
+                        }).await.map_err(anyhow::Error::from).and_then(|r| r);
+                        match res {
+                            Ok(()) => info!("💾 Backup saved to {}", path),
+                            Err(e) => error!("❌ Backup export failed: {}", e),
+                        }
+                    },
+                    UiCommand::ImportBackup { path, passphrase } => {
+                        let np = protection.clone();
+                        let res = tokio::task::spawn_blocking(move || {
+                            let data = std::fs::read(&path)?;
+                            let (id, backup) = RingIdentity::import_backup(&data, passphrase.expose())?;
+                            id.save_with(&np)?;
+                            let rings = backup.extra_rings()?;
+                            if let Some(rings) = &rings {
+                                RingIdentity::save_extra_rings(rings, &np)?;
+                            }
+                            Ok((id, backup, rings))
+                        }).await.map_err(anyhow::Error::from).and_then(|r: anyhow::Result<_>| r);
+                        match res {
+                            Ok((id, backup, rings)) => {
+                                identity = id;
+                                // Un backup di una versione precedente non ha i ring aggiuntivi: restano quelli attuali
+                                if let Some(rings) = rings {
+                                    extra_rings = rings;
+                                    if let Some(tx) = &tx_event { let _ = tx.send(CoreEvent::RingsLoaded(ring_ids(&extra_rings))); }
+                                }
+                                config.device_name = backup.device_name.clone();
+                                config.save().ok();
+                                *shared_config.write().unwrap() = config.clone();
+                                info!("✅ Backup imported");
+                                if let Some(tx) = &tx_event {
+                                    let _ = tx.send(CoreEvent::IdentityLoaded((&identity).into()));
+                                    let _ = tx.send(CoreEvent::ConfigReloaded(Box::new(config.clone())));
+                                }
+                                restart_services(active_rings(&identity, &extra_rings, &config), config.clone(), paused.clone(), Some(tx_internal.clone()));
+                            },
+                            Err(e) => {
+                                error!("❌ Backup import failed: {}", e);
+                            }
+                        }
+                    },
+                    UiCommand::RevealMnemonic => {
+                        if let Some(tx) = &tx_event {
+                            let _ = tx.send(CoreEvent::MnemonicRevealed(SecretString::new(identity.mnemonic.clone())));
+                        }
+                    },
+                    UiCommand::RunDoctor => {
+                        let p = protection.clone();
+                        let cfg = config.clone();
+                        let tx_ui = tx_event.clone();
+                        // Dura qualche secondo: non blocchiamo né il runtime né i comandi successivi
+                        tokio::task::spawn_blocking(move || {
+                            let report = doctor::run(&p, &cfg);
+                            if let Some(tx) = &tx_ui { let _ = tx.send(CoreEvent::DoctorReport(report)); }
+                        });
+                    },
+                    UiCommand::Unlock(_) => {} // Già sbloccata
                     UiCommand::Quit => std::process::exit(0),
                 }
             }
@@ -183,6 +676,175 @@ fn run_async_backend(rx_cmd: Option<Receiver<UiCommand>>, tx_event: Option<Sende
     })
 }
 
+/// (Ri)avvia l'endpoint Prometheus secondo la config; spento se disabilitato
+fn restart_metrics_endpoint(handle: &mut Option<tokio::task::JoinHandle<()>>, config: &MetricsConfig, shared: &SharedMetrics) {
+    if let Some(h) = handle.take() { h.abort(); }
+    if config.endpoint_enabled {
+        let shared = shared.clone();
+        let port = config.port;
+        *handle = Some(tokio::spawn(async move {
+            if let Err(e) = metrics::serve(shared, port).await {
+                error!("❌ Metrics endpoint error: {}", e);
+            }
+        }));
+    }
+}
+
+/// Ring da far girare: il principale e gli aggiuntivi, tranne quelli disattivati
+fn active_rings(identity: &RingIdentity, extra: &[RingIdentity], config: &AppConfig) -> Vec<RingIdentity> {
+    std::iter::once(identity).chain(extra)
+        .filter(|r| config.rings.is_enabled(&r.discovery_id))
+        .cloned()
+        .collect()
+}
+
+fn ring_ids(rings: &[RingIdentity]) -> Vec<String> {
+    rings.iter().map(|r| r.discovery_id.clone()).collect()
+}
+
+/// `rust-clip ring ...`: le modifiche valgono dal prossimo avvio del servizio
+fn run_ring_command(action: RingAction) -> anyhow::Result<()> {
+    let (identity, protection) = cli_unlock()?;
+    let mut extra = RingIdentity::load_extra_rings(&protection)?;
+    let mut config = AppConfig::load();
+
+    // Un ring si indica per nome o per (inizio del) discovery_id
+    let find = |extra: &[RingIdentity], config: &AppConfig, query: &str| -> anyhow::Result<String> {
+        std::iter::once(&identity).chain(extra)
+            .map(|r| r.discovery_id.clone())
+            .find(|id| config.rings.name(id).eq_ignore_ascii_case(query) || id.starts_with(query))
+            .ok_or_else(|| anyhow::anyhow!("Ring '{}' non trovato", query))
+    };
+
+    match action {
+        RingAction::List => {
+            for ring in std::iter::once(&identity).chain(&extra) {
+                let id = &ring.discovery_id;
+                let state = if config.rings.is_enabled(id) { "attivo" } else { "disattivato" };
+                let primary = if id == &identity.discovery_id { " (principale)" } else { "" };
+                println!("{}  {}{}  [{}]", &id[..id.len().min(12)], config.rings.name(id), primary, state);
+            }
+        }
+        RingAction::Add { name, new } => {
+            let ring = if new {
+                RingIdentity::generate()?
+            } else {
+                print!("Inserisci le parole del ring (o il codice di pairing): ");
+                use std::io::{self, Write};
+                io::stdout().flush()?;
+                let mut phrase = String::new();
+                io::stdin().read_line(&mut phrase)?;
+                pairing::identity_from_input(SecretString::new(phrase).expose())?
+            };
+            if std::iter::once(&identity).chain(&extra).any(|r| r.discovery_id == ring.discovery_id) {
+                anyhow::bail!("Ring già presente su questo dispositivo");
+            }
+            config.rings.entry(&ring.discovery_id).name = name.clone();
+            extra.push(ring);
+            RingIdentity::save_extra_rings(&extra, &protection)?;
+            config.save()?;
+            println!("➕ Ring '{}' aggiunto", name);
+        }
+        RingAction::Remove { ring } => {
+            let id = find(&extra, &config, &ring)?;
+            if id == identity.discovery_id {
+                anyhow::bail!("Il ring principale non si rimuove: usa `join` o `new` per sostituirlo");
+            }
+            extra.retain(|r| r.discovery_id != id);
+            RingIdentity::save_extra_rings(&extra, &protection)?;
+            config.rings.rings.remove(&id);
+            config.save()?;
+            println!("➖ Ring rimosso");
+        }
+        RingAction::Enable { ring } => {
+            let id = find(&extra, &config, &ring)?;
+            config.rings.entry(&id).enabled = true;
+            config.save()?;
+        }
+        RingAction::Disable { ring } => {
+            let id = find(&extra, &config, &ring)?;
+            config.rings.entry(&id).enabled = false;
+            config.save()?;
+        }
+        RingAction::Show { ring } => {
+            let id = find(&extra, &config, &ring)?;
+            let ring = std::iter::once(&identity).chain(&extra).find(|r| r.discovery_id == id)
+                .ok_or_else(|| anyhow::anyhow!("Ring '{}' non trovato", ring))?;
+            println!("{}", ring.mnemonic);
+            println!("⚠️  Chiunque veda queste parole può unirsi al Ring.");
+        }
+    }
+    Ok(())
+}
+
+/// Carica l'identità all'avvio. Se il file è protetto da passphrase la chiede
+/// alla GUI (via `CoreEvent::UnlockRequired`) oppure sul terminale in modalità CLI.
+async fn unlock_identity(rx_cmd: Option<&Receiver<UiCommand>>, tx_event: Option<&Sender<CoreEvent>>) -> anyhow::Result<(RingIdentity, KeyProtection)> {
+    if !RingIdentity::is_passphrase_protected()? {
+        // Solo al primo avvio si crea un'identità: un file esistente che non si apre è un errore
+        let identity = if RingIdentity::exists()? { RingIdentity::load()? } else { RingIdentity::create_new()? };
+        return Ok((identity, KeyProtection::Machine));
+    }
+
+    let (rx, tx) = match (rx_cmd, tx_event) {
+        (Some(rx), Some(tx)) => (rx, tx),
+        // Prompt e Argon2 sono bloccanti: fuori dal runtime
+        _ => return tokio::task::spawn_blocking(cli_unlock).await?,
+    };
+
+    let _ = tx.send(CoreEvent::UnlockRequired { failed: false });
+    while let Ok(cmd) = rx.recv_async().await {
+        match cmd {
+            UiCommand::Unlock(pass) => {
+                let protection = KeyProtection::Passphrase(pass);
+                let p = protection.clone();
+                match tokio::task::spawn_blocking(move || RingIdentity::load_with(&p)).await? {
+                    Ok(identity) => return Ok((identity, protection)),
+                    Err(_) => { let _ = tx.send(CoreEvent::UnlockRequired { failed: true }); }
+                }
+            },
+            UiCommand::Quit => std::process::exit(0),
+            _ => {} // Finché l'identità è bloccata ignoriamo gli altri comandi
+        }
+    }
+    Err(anyhow::anyhow!("UI channel closed"))
+}
+
+/// Sblocca l'identità da terminale (max 3 tentativi se protetta da passphrase)
+fn cli_unlock() -> anyhow::Result<(RingIdentity, KeyProtection)> {
+    if !RingIdentity::is_passphrase_protected()? {
+        return Ok((RingIdentity::load()?, KeyProtection::Machine));
+    }
+    for _ in 0..3 {
+        let protection = KeyProtection::Passphrase(SecretString::new(rpassword::prompt_password("Passphrase identità: ")?));
+        match RingIdentity::load_with(&protection) {
+            Ok(identity) => return Ok((identity, protection)),
+            Err(e) => eprintln!("❌ {}", e),
+        }
+    }
+    anyhow::bail!("Troppi tentativi falliti")
+}
+
+/// Chiede una nuova passphrase due volte sul terminale
+fn prompt_new_passphrase() -> anyhow::Result<SecretString> {
+    let pass = SecretString::new(rpassword::prompt_password("Nuova passphrase: ")?);
+    let confirm = SecretString::new(rpassword::prompt_password("Conferma passphrase: ")?);
+    if pass.is_empty() || pass.expose() != confirm.expose() {
+        anyhow::bail!("Le passphrase non coincidono o sono vuote");
+    }
+    Ok(pass)
+}
+
+/// Protezione da riusare quando la CLI sovrascrive l'identità (New/Join/Import)
+fn cli_current_protection() -> anyhow::Result<KeyProtection> {
+    if RingIdentity::is_passphrase_protected()? {
+        let (_, protection) = cli_unlock()?;
+        Ok(protection)
+    } else {
+        Ok(KeyProtection::Machine)
+    }
+}
+
 fn attach_console_if_windows() {
     #[cfg(target_os = "windows")]
     unsafe { let _ = AttachConsole(ATTACH_PARENT_PROCESS); }
@@ -1,67 +1,220 @@
 use eframe::egui;
-use crate::events::{UiCommand, CoreEvent, PeerInfo};
+use crate::events::{UiCommand, CoreEvent, PeerInfo, PendingClipInfo, LogEntry, LogLevel};
+use crate::core::roster::{self, RosterEntry};
 use flume::{Sender, Receiver};
 use crate::ui::tray::AppTray;
-use crate::core::config::AppConfig; 
+use crate::ui::hotkey::PushHotkey;
+use crate::core::config::{AppConfig, SensitivePolicy, AutoClearMode, ImageCodec, ImagePreference, CompressionAlgorithm, PrimaryTarget, SyncDirection, ApprovalPolicy, LogVerbosity}; 
+use crate::core::identity::{RingIdentity, SecretString};
+use crate::core::pairing;
+use crate::core::imaging;
+use crate::core::logging;
+use crate::core::doctor::{Report, Status};
+use crate::core::metrics::{self, Metrics, Failure};
 use notify_rust::Notification; // Notification da UI
+use std::collections::BTreeMap;
 
 #[derive(PartialEq)]
-enum Tab { Dashboard, Settings }
+enum Tab { Dashboard, Devices, Doctor, Settings }
 
 pub struct RustClipApp {
     tx: Sender<UiCommand>,
     rx: Receiver<CoreEvent>,
-    _tray: AppTray,
+    tray: AppTray,
+    push_hotkey: Option<PushHotkey>,
     
     current_tab: Tab,
-    logs: Vec<String>,
+    logs: Vec<LogEntry>,
     is_paused: bool,
-    peers: Vec<PeerInfo>, // Updated from Tuple
+    // Peer per ring (chiave = discovery_id)
+    ring_peers: BTreeMap<String, Vec<PeerInfo>>,
+    // Tutti i dispositivi visti, anche offline
+    roster: Vec<RosterEntry>,
+    pending: Vec<PendingClipInfo>,
+    // Statistiche dei trasferimenti dall'avvio
+    metrics: Metrics,
     
     // Dati
     my_ring_id: String,
-    my_mnemonic: String,
+    extra_rings: Vec<String>,
+    // Parole di un ring aggiuntivo, chieste al core col pulsante 🔑
+    revealed_ring: Option<(String, SecretString)>,
+    // Il mnemonic arriva dal core solo su richiesta e resta qui finché è visibile
+    revealed_mnemonic: Option<SecretString>,
     config: AppConfig, 
     
     // UI State
     join_phrase: String,
     show_mnemonic: bool,
     show_confirmation: bool, // NUOVO
+    show_qr: bool,
+    qr_texture: Option<egui::TextureHandle>,
+    join_qr_path: String,
+    join_code: String,
+    invite_code: Option<String>,
+    ring_name_input: String,
+    ring_phrase_input: String,
+
+    // Protezione identità
+    locked: bool,
+    unlock_input: String,
+    unlock_failed: bool,
+    passphrase_protected: bool,
+    new_passphrase: String,
+    confirm_passphrase: String,
+    passphrase_error: bool,
+
+    // Pattern sensibili e regole per app, uno per riga (editati come testo unico)
+    sensitive_patterns: String,
+    app_deny: String,
+    app_allow: String,
+    tracking_params: String,
+
+    // Scorciatoia del push (applicata quando il campo perde il focus)
+    hotkey_input: String,
+    hotkey_error: bool,
+
+    // Diagnostica: ultimo report ricevuto e richiesta in corso
+    doctor_report: Option<Report>,
+    doctor_running: bool,
+
+    // Backup
+    backup_path: String,
+    backup_passphrase: String,
+    backup_confirm: String,
+    backup_error: bool,
 }
 
 impl RustClipApp {
-    pub fn new(_cc: &eframe::CreationContext<'_>, tx: Sender<UiCommand>, rx: Receiver<CoreEvent>, tray: AppTray) -> Self {
+    pub fn new(_cc: &eframe::CreationContext<'_>, tx: Sender<UiCommand>, rx: Receiver<CoreEvent>, tray: AppTray, push_hotkey: Option<PushHotkey>) -> Self {
         let config = AppConfig::load();
-        let app = Self {
+        let mut app = Self {
             tx, rx, 
-            _tray: tray,
+            tray,
+            push_hotkey,
             current_tab: Tab::Dashboard,
             logs: vec![],
             is_paused: false,
-            peers: vec![],
+            ring_peers: BTreeMap::new(),
+            roster: vec![],
+            pending: vec![],
+            metrics: Metrics::default(),
             my_ring_id: "Loading...".into(),
-            my_mnemonic: String::new(),
+            extra_rings: vec![],
+            revealed_ring: None,
+            revealed_mnemonic: None,
             config: config.clone(), 
             join_phrase: String::new(),
             show_mnemonic: false,
             show_confirmation: false,
+            show_qr: false,
+            qr_texture: None,
+            join_qr_path: String::new(),
+            join_code: String::new(),
+            invite_code: None,
+            ring_name_input: String::new(),
+            ring_phrase_input: String::new(),
+            locked: false,
+            unlock_input: String::new(),
+            unlock_failed: false,
+            passphrase_protected: false,
+            new_passphrase: String::new(),
+            confirm_passphrase: String::new(),
+            passphrase_error: false,
+            sensitive_patterns: config.sensitive.custom_patterns.join("\n"),
+            app_deny: config.app_rules.deny.join("\n"),
+            app_allow: config.app_rules.allow.join("\n"),
+            tracking_params: config.pipeline.extra_tracking_params.join("\n"),
+            hotkey_input: config.push.hotkey.clone(),
+            hotkey_error: false,
+            doctor_report: None,
+            doctor_running: false,
+            backup_path: RingIdentity::default_backup_path().to_string_lossy().to_string(),
+            backup_passphrase: String::new(),
+            backup_confirm: String::new(),
+            backup_error: false,
         };
         // Initialize locale
         rust_i18n::set_locale(&config.language);
+        app.hotkey_error = !app.apply_hotkey();
         app
     }
 
+    /// Registra la scorciatoia del push scritta nella config; false se non valida o già in uso.
+    /// Con il push manuale spento la scorciatoia viene tolta, così resta libera per le altre app.
+    /// Senza gestore delle scorciatoie (sistema non supportato) non c'è nulla da registrare.
+    fn apply_hotkey(&mut self) -> bool {
+        let Some(hotkey) = &mut self.push_hotkey else { return true };
+        let accelerator = if self.config.push.manual { self.config.push.hotkey.as_str() } else { "" };
+        match hotkey.set(accelerator) {
+            Ok(()) => true,
+            Err(e) => {
+                tracing::warn!("⚠️ Push hotkey '{}': {}", self.config.push.hotkey, e);
+                false
+            }
+        }
+    }
+
     fn update_state(&mut self) {
         while let Ok(event) = self.rx.try_recv() {
             match event {
-                CoreEvent::Log(entry) => self.logs.push(format!("[{}] {}", entry.timestamp, entry.message)),
-                CoreEvent::PeersUpdated(list) => self.peers = list,
+                CoreEvent::Log(entry) => self.logs.push(entry),
+                CoreEvent::PendingClips(list) => self.pending = list,
+                CoreEvent::PeersUpdated { ring_id, peers } => {
+                    if peers.is_empty() {
+                        self.ring_peers.remove(&ring_id);
+                    } else {
+                        self.ring_peers.insert(ring_id, peers);
+                    }
+                    // Nel tray ogni dispositivo compare una volta, anche se è in più ring
+                    let mut all: Vec<PeerInfo> = Vec::new();
+                    for peer in self.ring_peers.values().flatten() {
+                        if !all.iter().any(|p| p.device_id == peer.device_id) {
+                            all.push(peer.clone());
+                        }
+                    }
+                    self.tray.set_peers(&all);
+                },
+                CoreEvent::RingsLoaded(ids) => {
+                    self.extra_rings = ids;
+                    self.revealed_ring = None;
+                },
+                CoreEvent::RosterUpdated(list) => self.roster = list,
+                CoreEvent::RingMnemonicRevealed { ring_id, mnemonic } => self.revealed_ring = Some((ring_id, mnemonic)),
                 CoreEvent::IdentityLoaded(id) => {
+                    self.locked = false;
                     self.my_ring_id = id.discovery_id;
-                    self.my_mnemonic = id.mnemonic;
+                    self.revealed_mnemonic = None;
+                    self.qr_texture = None;
+                    self.sync_reveal();
+                },
+                CoreEvent::MnemonicRevealed(mnemonic) => {
+                    if self.show_mnemonic || self.show_qr {
+                        self.revealed_mnemonic = Some(mnemonic);
+                    }
+                },
+                CoreEvent::UnlockRequired { failed } => {
+                    self.locked = true;
+                    self.unlock_failed = failed;
+                },
+                CoreEvent::IdentityProtection { passphrase } => self.passphrase_protected = passphrase,
+                CoreEvent::ConfigReloaded(cfg) => {
+                    self.sensitive_patterns = cfg.sensitive.custom_patterns.join("\n");
+                    self.app_deny = cfg.app_rules.deny.join("\n");
+                    self.app_allow = cfg.app_rules.allow.join("\n");
+                    self.tracking_params = cfg.pipeline.extra_tracking_params.join("\n");
+                    self.hotkey_input = cfg.push.hotkey.clone();
+                    self.config = *cfg;
+                    self.hotkey_error = !self.apply_hotkey();
+                },
+                CoreEvent::PairingCode(code) => self.invite_code = code,
+                CoreEvent::MetricsUpdated(metrics) => self.metrics = *metrics,
+                CoreEvent::DoctorReport(report) => {
+                    self.doctor_report = Some(report);
+                    self.doctor_running = false;
                 },
                 CoreEvent::ServiceStateChanged { running } => {
-                    println!("UI: ServiceStateChanged -> running={}", running);
+                    tracing::debug!("UI: ServiceStateChanged -> running={}", running);
                     self.is_paused = !running;
                 },
                 CoreEvent::Notify { title, body } => {
@@ -73,6 +226,744 @@ impl RustClipApp {
     }
 }
 
+impl RustClipApp {
+    /// Chiede il mnemonic al core quando serve mostrarlo e lo scarta appena torna nascosto
+    fn sync_reveal(&mut self) {
+        if !self.show_mnemonic && !self.show_qr {
+            self.revealed_mnemonic = None;
+            self.qr_texture = None;
+        } else if self.revealed_mnemonic.is_none() {
+            let _ = self.tx.send(UiCommand::RevealMnemonic);
+        }
+    }
+
+    fn show_unlock(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        ui.add_space(20.0);
+        ui.heading(t!("unlock.title"));
+        ui.label(t!("unlock.msg"));
+        ui.add_space(10.0);
+
+        let mut submit = false;
+        ui.horizontal(|ui| {
+            ui.label(t!("unlock.passphrase"));
+            let resp = ui.add(egui::TextEdit::singleline(&mut self.unlock_input).password(true));
+            if resp.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) { submit = true; }
+        });
+        if ui.button(t!("unlock.btn")).clicked() { submit = true; }
+
+        if submit && !self.unlock_input.is_empty() {
+            let _ = self.tx.send(UiCommand::Unlock(SecretString::new(std::mem::take(&mut self.unlock_input))));
+            self.unlock_failed = false;
+        }
+        if self.unlock_failed {
+            ui.label(egui::RichText::new(t!("unlock.failed")).color(egui::Color32::RED));
+        }
+    }
+
+    fn show_pairing_qr(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        if let (None, Some(mnemonic)) = (&self.qr_texture, &self.revealed_mnemonic) {
+            let pixels = RingIdentity::from_mnemonic(mnemonic.expose())
+                .and_then(|id| pairing::pairing_payload(&id))
+                .and_then(|payload| pairing::render_qr_pixels(&payload, 4));
+            if let Ok((side, pixels)) = pixels {
+                let image = egui::ColorImage::from_gray([side, side], &pixels);
+                self.qr_texture = Some(ui.ctx().load_texture("pairing_qr", image, egui::TextureOptions::NEAREST));
+            }
+        }
+        if let Some(texture) = &self.qr_texture {
+            ui.add(egui::Image::new(texture).fit_to_exact_size(egui::vec2(220.0, 220.0)));
+            ui.label(egui::RichText::new(t!("settings.qr_warning")).color(egui::Color32::YELLOW));
+        }
+    }
+
+    fn show_protection(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        ui.label(egui::RichText::new(t!("settings.protection")).strong());
+        if self.passphrase_protected {
+            ui.label(t!("settings.protection_passphrase"));
+        } else {
+            ui.label(t!("settings.protection_machine"));
+        }
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.new_passphrase"));
+            ui.add(egui::TextEdit::singleline(&mut self.new_passphrase).password(true));
+        });
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.confirm_passphrase"));
+            ui.add(egui::TextEdit::singleline(&mut self.confirm_passphrase).password(true));
+        });
+        ui.horizontal(|ui| {
+            let label = if self.passphrase_protected { t!("settings.change_passphrase") } else { t!("settings.set_passphrase") };
+            if ui.button(label).clicked() {
+                if self.new_passphrase.is_empty() || self.new_passphrase != self.confirm_passphrase {
+                    self.passphrase_error = true;
+                } else {
+                    let _ = self.tx.send(UiCommand::SetProtection(Some(SecretString::new(std::mem::take(&mut self.new_passphrase)))));
+                    self.confirm_passphrase.clear();
+                    self.passphrase_error = false;
+                }
+            }
+            if self.passphrase_protected && ui.button(t!("settings.remove_passphrase")).clicked() {
+                let _ = self.tx.send(UiCommand::SetProtection(None));
+            }
+        });
+        if self.passphrase_error {
+            ui.label(egui::RichText::new(t!("settings.passphrase_mismatch")).color(egui::Color32::RED));
+        }
+    }
+
+    fn show_sensitive(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let mut changed = false;
+        let sensitive = &mut self.config.sensitive;
+        ui.label(egui::RichText::new(t!("settings.sensitive")).strong());
+        ui.label(t!("settings.sensitive_hint"));
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.sensitive_policy"));
+            for (policy, label) in [
+                (SensitivePolicy::Block, t!("settings.policy_block")),
+                (SensitivePolicy::Expire, t!("settings.policy_expire")),
+                (SensitivePolicy::Off, t!("settings.policy_off")),
+            ] {
+                changed |= ui.radio_value(&mut sensitive.policy, policy, label).changed();
+            }
+        });
+        if sensitive.policy == SensitivePolicy::Expire {
+            ui.horizontal(|ui| {
+                ui.label(t!("settings.expire_secs"));
+                changed |= ui.add(egui::DragValue::new(&mut sensitive.expire_secs).range(5..=3600)).changed();
+            });
+        }
+        changed |= ui.checkbox(&mut sensitive.platform_hints, t!("settings.platform_hints")).changed();
+        changed |= ui.checkbox(&mut sensitive.builtin_detectors, t!("settings.builtin_detectors")).changed();
+        ui.label(t!("settings.custom_patterns"));
+        changed |= edit_lines(ui, &mut self.sensitive_patterns, &mut sensitive.custom_patterns);
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    fn show_app_rules(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let rules = &mut self.config.app_rules;
+        ui.label(egui::RichText::new(t!("settings.app_rules")).strong());
+        ui.label(t!("settings.app_rules_hint"));
+        ui.label(t!("settings.app_deny"));
+        let mut changed = edit_lines(ui, &mut self.app_deny, &mut rules.deny);
+        ui.label(t!("settings.app_allow"));
+        changed |= edit_lines(ui, &mut self.app_allow, &mut rules.allow);
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    fn show_auto_clear(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let mut changed = false;
+        let auto_clear = &mut self.config.auto_clear;
+        ui.label(egui::RichText::new(t!("settings.auto_clear")).strong());
+        ui.label(t!("settings.auto_clear_hint"));
+        ui.horizontal(|ui| {
+            for (mode, label) in [
+                (AutoClearMode::Off, t!("settings.auto_clear_off")),
+                (AutoClearMode::Sensitive, t!("settings.auto_clear_sensitive")),
+                (AutoClearMode::All, t!("settings.auto_clear_all")),
+            ] {
+                changed |= ui.radio_value(&mut auto_clear.mode, mode, label).changed();
+            }
+        });
+        if auto_clear.mode != AutoClearMode::Off {
+            ui.horizontal(|ui| {
+                ui.label(t!("settings.auto_clear_secs"));
+                changed |= ui.add(egui::DragValue::new(&mut auto_clear.secs).range(5..=3600)).changed();
+            });
+        }
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    fn show_pipeline(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let pipeline = &mut self.config.pipeline;
+        ui.label(egui::RichText::new(t!("settings.pipeline")).strong());
+        let mut changed = ui.checkbox(&mut pipeline.trim_whitespace, t!("settings.trim_whitespace")).changed();
+        changed |= ui.checkbox(&mut pipeline.normalize_line_endings, t!("settings.normalize_line_endings")).changed();
+        changed |= ui.checkbox(&mut pipeline.strip_tracking, t!("settings.strip_tracking")).changed();
+        if pipeline.strip_tracking {
+            ui.label(t!("settings.extra_tracking_params"));
+            changed |= edit_lines(ui, &mut self.tracking_params, &mut pipeline.extra_tracking_params);
+        }
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.max_text_kib"));
+            changed |= ui.add(egui::DragValue::new(&mut pipeline.max_text_kib)).changed();
+            ui.label(t!("settings.max_image_kib"));
+            changed |= ui.add(egui::DragValue::new(&mut pipeline.max_image_kib)).changed();
+        });
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    fn show_primary(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let primary = &mut self.config.primary_selection;
+        ui.label(egui::RichText::new(t!("settings.primary")).strong());
+        let mut changed = ui.checkbox(&mut primary.enabled, t!("settings.primary_enabled")).changed();
+        if primary.enabled {
+            ui.horizontal(|ui| {
+                changed |= ui.radio_value(&mut primary.target, PrimaryTarget::Primary, t!("settings.primary_to_primary")).changed();
+                changed |= ui.radio_value(&mut primary.target, PrimaryTarget::Clipboard, t!("settings.primary_to_clipboard")).changed();
+            });
+            ui.horizontal(|ui| {
+                ui.label(t!("settings.primary_debounce"));
+                changed |= ui.add(egui::DragValue::new(&mut primary.debounce_ms).range(200..=10_000)).changed();
+            });
+        }
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    fn show_images(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let image = &mut self.config.image;
+        ui.label(egui::RichText::new(t!("settings.images")).strong());
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.image_codec"));
+            changed |= codec_combo(ui, "image_codec", &mut image.codec);
+            if is_lossy(image.codec) {
+                ui.label(t!("settings.image_quality"));
+                changed |= ui.add(egui::Slider::new(&mut image.quality, 1..=100)).changed();
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.image_max_dimension"));
+            changed |= ui.add(egui::DragValue::new(&mut image.max_dimension).range(0..=16384)).changed();
+            ui.label(t!("settings.image_max_kib"));
+            changed |= ui.add(egui::DragValue::new(&mut image.max_kib)).changed();
+        });
+
+        let mut request = image.preferred.is_some();
+        if ui.checkbox(&mut request, t!("settings.image_request")).changed() {
+            image.preferred = request.then_some(ImagePreference { codec: image.codec, quality: image.quality, max_dimension: image.max_dimension });
+            changed = true;
+        }
+        if let Some(pref) = image.preferred.as_mut() {
+            ui.horizontal(|ui| {
+                changed |= codec_combo(ui, "image_preferred_codec", &mut pref.codec);
+                if is_lossy(pref.codec) {
+                    changed |= ui.add(egui::Slider::new(&mut pref.quality, 1..=100)).changed();
+                }
+                ui.label(t!("settings.image_max_dimension"));
+                changed |= ui.add(egui::DragValue::new(&mut pref.max_dimension).range(0..=16384)).changed();
+            });
+        }
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    fn show_compression(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let mut changed = false;
+        let compression = &mut self.config.compression;
+        ui.label(egui::RichText::new(t!("settings.compression")).strong());
+        ui.label(t!("settings.compression_hint"));
+        ui.horizontal(|ui| {
+            for (algorithm, label) in [
+                (CompressionAlgorithm::Off, t!("settings.compression_off")),
+                (CompressionAlgorithm::Zstd, "zstd".into()),
+                (CompressionAlgorithm::Lz4, "lz4".into()),
+            ] {
+                changed |= ui.radio_value(&mut compression.algorithm, algorithm, label).changed();
+            }
+        });
+        if compression.algorithm != CompressionAlgorithm::Off {
+            ui.horizontal(|ui| {
+                ui.label(t!("settings.compression_threshold"));
+                changed |= ui.add(egui::DragValue::new(&mut compression.threshold_bytes).range(0..=1_048_576)).changed();
+                ui.label(t!("settings.compression_level"));
+                changed |= match compression.algorithm {
+                    CompressionAlgorithm::Zstd => ui.add(egui::Slider::new(&mut compression.zstd_level, 1..=22)).changed(),
+                    _ => ui.add(egui::Slider::new(&mut compression.lz4_level, 0..=12)).changed(),
+                };
+            });
+        }
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    /// Clip ricevuti in attesa: nulla viene incollato finché non si preme ✅
+    fn show_pending(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        ui.label(egui::RichText::new(t!("dashboard.pending", count = self.pending.len())).strong());
+        egui::Frame::group(ui.style()).show(ui, |ui| {
+            for clip in &self.pending {
+                ui.horizontal(|ui| {
+                    if ui.small_button("✅").on_hover_text(t!("dashboard.accept")).clicked() {
+                        let _ = self.tx.send(UiCommand::ResolveClip { id: clip.id, accept: true });
+                    }
+                    if ui.small_button("❌").on_hover_text(t!("dashboard.reject")).clicked() {
+                        let _ = self.tx.send(UiCommand::ResolveClip { id: clip.id, accept: false });
+                    }
+                    ui.label(format!("[{}] {}:", clip.received_at, clip.from));
+                    ui.add(egui::Label::new(clip.preview.as_str()).truncate());
+                });
+            }
+        });
+    }
+
+    fn show_approval(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let mut ask = self.config.approval.global == ApprovalPolicy::Ask;
+        if ui.checkbox(&mut ask, t!("settings.approval_ask")).on_hover_text(t!("settings.approval_hint")).changed() {
+            self.config.approval.global = if ask { ApprovalPolicy::Ask } else { ApprovalPolicy::Auto };
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    fn show_push(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        ui.label(egui::RichText::new(t!("settings.push")).strong());
+        ui.label(t!("settings.push_hint"));
+        if ui.checkbox(&mut self.config.push.manual, t!("settings.push_manual")).changed() {
+            self.hotkey_error = !self.apply_hotkey();
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+        ui.add_enabled_ui(self.push_hotkey.is_some() && self.config.push.manual, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(t!("settings.push_hotkey"));
+                let edit = ui.text_edit_singleline(&mut self.hotkey_input);
+                if edit.lost_focus() && self.hotkey_input.trim() != self.config.push.hotkey {
+                    let previous = std::mem::replace(&mut self.config.push.hotkey, self.hotkey_input.trim().to_string());
+                    if self.apply_hotkey() {
+                        self.hotkey_error = false;
+                        let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+                    } else {
+                        // Torniamo alla scorciatoia che funzionava
+                        self.hotkey_error = true;
+                        self.config.push.hotkey = previous;
+                        self.apply_hotkey();
+                    }
+                }
+            });
+        });
+        if self.hotkey_error {
+            ui.label(egui::RichText::new(t!("settings.push_hotkey_invalid")).color(egui::Color32::RED));
+        }
+    }
+
+    /// Registro dei dispositivi di ogni ring, con i dati per riconoscerli
+    fn show_devices(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        ui.label(t!("devices.hint"));
+        ui.horizontal(|ui| {
+            ui.label(t!("devices.this_device"));
+            ui.monospace(roster::device_code(&RingIdentity::get_derived_device_id())).on_hover_text(t!("devices.code"));
+        });
+        ui.add_space(10.0);
+
+        let ids: Vec<String> = std::iter::once(&self.my_ring_id).chain(&self.extra_rings).cloned().collect();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for ring_id in &ids {
+                ui.label(egui::RichText::new(self.config.rings.name(ring_id)).strong());
+                let online = self.ring_peers.get(ring_id);
+                let devices: Vec<&RosterEntry> = self.roster.iter().filter(|d| &d.ring_id == ring_id).collect();
+                egui::Frame::group(ui.style()).show(ui, |ui| {
+                    if devices.is_empty() {
+                        ui.label(t!("dashboard.no_devices"));
+                    }
+                    for device in devices {
+                        let is_online = online.is_some_and(|peers| peers.iter().any(|p| p.device_id == device.device_id));
+                        ui.horizontal(|ui| {
+                            ui.label(if is_online { "🟢" } else { "⚪" });
+                            ui.label(egui::RichText::new(&device.name).strong());
+                            ui.label(format!("{} · {}",
+                                device.os.as_deref().unwrap_or("?"),
+                                device.app_version.as_deref().unwrap_or("?")));
+                            if ui.small_button("🗑").on_hover_text(t!("devices.forget")).clicked() {
+                                let _ = self.tx.send(UiCommand::ForgetDevice { ring_id: ring_id.clone(), device_id: device.device_id.clone() });
+                            }
+                        });
+                        ui.monospace(device.device_code()).on_hover_text(t!("devices.code"));
+                        ui.label(egui::RichText::new(t!("devices.seen",
+                            first = roster::format_time(device.first_seen),
+                            last = roster::format_time(device.last_seen))).weak());
+                        ui.add_space(4.0);
+                    }
+                });
+                ui.add_space(10.0);
+            }
+        });
+    }
+
+    /// Pagina della diagnostica: i controlli girano nel core, qui si mostra l'ultimo report
+    fn show_doctor(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        ui.label(t!("doctor.hint"));
+        ui.add_space(10.0);
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!self.doctor_running, egui::Button::new(t!("doctor.run"))).clicked() {
+                self.doctor_running = true;
+                let _ = self.tx.send(UiCommand::RunDoctor);
+            }
+            if self.doctor_running {
+                ui.spinner();
+                ui.label(t!("doctor.running"));
+            } else if let Some(report) = &self.doctor_report {
+                if ui.button(t!("doctor.copy")).clicked() {
+                    ui.output_mut(|o| o.copied_text = report.render());
+                }
+            }
+        });
+        ui.add_space(10.0);
+
+        let Some(report) = &self.doctor_report else { return };
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Frame::group(ui.style()).show(ui, |ui| {
+                for check in &report.checks {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(check.status.icon());
+                        ui.label(egui::RichText::new(&check.name).strong());
+                        let detail = egui::RichText::new(&check.detail);
+                        ui.label(match check.status {
+                            Status::Ok => detail,
+                            Status::Warn => detail.color(ui.visuals().warn_fg_color),
+                            Status::Fail => detail.color(ui.visuals().error_fg_color),
+                        });
+                    });
+                }
+            });
+        });
+    }
+
+    /// Ring attivi insieme: il principale (credenziali qui sotto) e quelli aggiunti
+    fn show_rings(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        ui.label(egui::RichText::new(t!("settings.rings")).strong());
+        ui.label(t!("settings.rings_hint"));
+        let mut changed = false;
+        let ids: Vec<String> = std::iter::once(&self.my_ring_id).chain(&self.extra_rings).cloned().collect();
+        for id in &ids {
+            let primary = *id == self.my_ring_id;
+            let placeholder = self.config.rings.name(id);
+            ui.horizontal(|ui| {
+                // La voce in config nasce solo quando l'utente cambia qualcosa
+                let original = self.config.rings.rings.get(id).cloned().unwrap_or_default();
+                let mut settings = original.clone();
+                changed |= ui.checkbox(&mut settings.enabled, "").on_hover_text(t!("settings.ring_enabled")).changed();
+                let name = ui.add(egui::TextEdit::singleline(&mut settings.name).hint_text(placeholder).desired_width(140.0));
+                changed |= name.lost_focus();
+                if settings != original {
+                    *self.config.rings.entry(id) = settings;
+                }
+                if primary {
+                    ui.label(egui::RichText::new(t!("settings.ring_primary")).weak());
+                    return;
+                }
+                let shown = self.revealed_ring.as_ref().is_some_and(|(r, _)| r == id);
+                if ui.selectable_label(shown, "🔑").on_hover_text(t!("settings.ring_show_words")).clicked() {
+                    if shown {
+                        self.revealed_ring = None;
+                    } else {
+                        let _ = self.tx.send(UiCommand::RevealRingMnemonic(id.clone()));
+                    }
+                }
+                if ui.small_button("🗑").on_hover_text(t!("settings.ring_remove")).clicked() {
+                    let _ = self.tx.send(UiCommand::RemoveRing(id.clone()));
+                }
+            });
+            if let Some((_, words)) = self.revealed_ring.as_ref().filter(|(r, _)| r == id) {
+                ui.group(|ui| {
+                    ui.add(egui::Label::new(egui::RichText::new(words.expose()).monospace()).wrap());
+                });
+            }
+        }
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+
+        ui.horizontal(|ui| {
+            ui.add(egui::TextEdit::singleline(&mut self.ring_name_input).hint_text(t!("settings.ring_name_hint")).desired_width(120.0));
+            ui.add(egui::TextEdit::singleline(&mut self.ring_phrase_input).hint_text(t!("settings.ring_phrase_hint")).password(true));
+        });
+        ui.horizontal(|ui| {
+            let phrase = !self.ring_phrase_input.trim().is_empty();
+            if ui.add_enabled(phrase, egui::Button::new(t!("settings.ring_join"))).clicked() {
+                let phrase = SecretString::new(std::mem::take(&mut self.ring_phrase_input));
+                let name = std::mem::take(&mut self.ring_name_input);
+                let _ = self.tx.send(UiCommand::AddRing { name, phrase: Some(phrase) });
+            }
+            if ui.button(t!("settings.ring_new")).clicked() {
+                let name = std::mem::take(&mut self.ring_name_input);
+                let _ = self.tx.send(UiCommand::AddRing { name, phrase: None });
+            }
+        });
+    }
+
+    fn show_direction(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        ui.label(egui::RichText::new(t!("settings.direction")).strong());
+        ui.label(t!("settings.direction_hint"));
+        if direction_combo(ui, "global", &mut self.config.direction.global) {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    fn show_throttle(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let mut changed = false;
+        let throttle = &mut self.config.throttle;
+        ui.label(egui::RichText::new(t!("settings.throttle")).strong());
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.throttle_debounce"));
+            changed |= ui.add(egui::DragValue::new(&mut throttle.debounce_ms).range(0..=10_000)).changed();
+        });
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.throttle_incoming"));
+            changed |= ui.add(egui::DragValue::new(&mut throttle.max_incoming_per_minute).range(0..=1000)).changed();
+        });
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    fn show_hooks(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let mut changed = false;
+        let hooks = &mut self.config.hooks;
+        ui.label(egui::RichText::new(t!("settings.hooks")).strong());
+        ui.label(t!("settings.hooks_hint"));
+        for (label, hook) in [(t!("settings.hook_send"), &mut hooks.on_send), (t!("settings.hook_receive"), &mut hooks.on_receive)] {
+            ui.horizontal(|ui| {
+                ui.label(label);
+                changed |= ui.add(egui::TextEdit::singleline(&mut hook.command).code_editor()).lost_focus();
+                changed |= ui.checkbox(&mut hook.rewrite, t!("settings.hook_rewrite")).changed();
+            });
+        }
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.hook_timeout"));
+            changed |= ui.add(egui::DragValue::new(&mut hooks.timeout_secs).range(1..=120)).changed();
+        });
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    fn show_logging(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let mut changed = false;
+        let logging = &mut self.config.logging;
+        ui.label(egui::RichText::new(t!("settings.logging")).strong());
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.logging_level"));
+            egui::ComboBox::from_id_salt("log_verbosity")
+                .selected_text(format!("{:?}", logging.verbosity))
+                .show_ui(ui, |ui| {
+                    for level in LogVerbosity::ALL {
+                        changed |= ui.selectable_value(&mut logging.verbosity, level, format!("{:?}", level)).changed();
+                    }
+                });
+        });
+        ui.horizontal(|ui| {
+            // Il file si apre all'avvio: cambiare queste due voci vale dal prossimo
+            changed |= ui.checkbox(&mut logging.file_enabled, t!("settings.logging_file"))
+                .on_hover_text(t!("settings.logging_restart")).changed();
+            ui.label(t!("settings.logging_keep"));
+            changed |= ui.add(egui::DragValue::new(&mut logging.max_files).range(1..=90)).changed();
+            if ui.button(t!("settings.logging_open")).clicked() {
+                if let Ok(dir) = logging::log_dir() {
+                    let _ = open::that(dir);
+                }
+            }
+        });
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    /// Statistiche dei trasferimenti, chiuse per default per non allungare la Dashboard
+    fn show_stats(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let stats = &self.metrics;
+        let title = t!("dashboard.stats", sent = stats.total_sent(), received = stats.total_received());
+        egui::CollapsingHeader::new(title).id_salt("transfer_stats").show(ui, |ui| {
+            if stats.peers.is_empty() {
+                ui.label(t!("dashboard.stats_empty"));
+            } else {
+                egui::Grid::new("stats_grid").striped(true).show(ui, |ui| {
+                    for header in [t!("dashboard.stats_device"), t!("dashboard.stats_sent"), t!("dashboard.stats_received"), t!("dashboard.stats_latency")] {
+                        ui.label(egui::RichText::new(header).strong());
+                    }
+                    ui.end_row();
+                    for peer in stats.peers.values() {
+                        ui.label(&peer.name);
+                        ui.label(format!("{} · {}", peer.sent, metrics::format_bytes(peer.bytes_sent)));
+                        ui.label(format!("{} · {}", peer.received, metrics::format_bytes(peer.bytes_received)));
+                        ui.label(peer.latency_ms.mean().map(|ms| format!("{} ms", ms)).unwrap_or_else(|| "-".into()));
+                        ui.end_row();
+                    }
+                });
+            }
+            ui.add_space(5.0);
+            let failures: Vec<String> = Failure::ALL.iter()
+                .filter_map(|reason| stats.failures.get(reason).map(|count| format!("{} {}", reason.label(), count)))
+                .collect();
+            if !failures.is_empty() {
+                ui.label(egui::RichText::new(t!("dashboard.stats_failures", list = failures.join(", "))).color(ui.visuals().warn_fg_color));
+            }
+            if stats.decrypt_rejected > 0 {
+                ui.label(egui::RichText::new(t!("dashboard.stats_rejected", count = stats.decrypt_rejected)).color(ui.visuals().warn_fg_color));
+            }
+        });
+    }
+
+    fn show_metrics_endpoint(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        let mut changed = false;
+        let cfg = &mut self.config.metrics;
+        ui.label(egui::RichText::new(t!("settings.metrics")).strong());
+        ui.horizontal(|ui| {
+            changed |= ui.checkbox(&mut cfg.endpoint_enabled, t!("settings.metrics_endpoint")).changed();
+            ui.label(t!("settings.metrics_port"));
+            // Applicata quando si smette di trascinare, per non riavviare l'endpoint a ogni passo
+            let port = ui.add(egui::DragValue::new(&mut cfg.port).range(1024..=65535));
+            changed |= port.drag_stopped() || port.lost_focus();
+        });
+        if cfg.endpoint_enabled {
+            ui.monospace(format!("http://127.0.0.1:{}/metrics", cfg.port));
+        }
+        if changed {
+            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+        }
+    }
+
+    fn show_backup(&mut self, ui: &mut egui::Ui) {
+        use rust_i18n::t;
+
+        ui.label(egui::RichText::new(t!("settings.backup")).strong());
+        ui.label(t!("settings.backup_hint"));
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.backup_path"));
+            ui.text_edit_singleline(&mut self.backup_path);
+        });
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.backup_passphrase"));
+            ui.add(egui::TextEdit::singleline(&mut self.backup_passphrase).password(true));
+        });
+        ui.horizontal(|ui| {
+            ui.label(t!("settings.confirm_passphrase"));
+            ui.add(egui::TextEdit::singleline(&mut self.backup_confirm).password(true));
+        });
+        ui.horizontal(|ui| {
+            if ui.button(t!("settings.export_btn")).clicked() {
+                // In export la conferma è obbligatoria: una passphrase sbagliata renderebbe il backup inutile
+                self.backup_error = self.backup_passphrase.is_empty() || self.backup_passphrase != self.backup_confirm;
+                if !self.backup_error {
+                    let _ = self.tx.send(UiCommand::ExportBackup {
+                        path: self.backup_path.clone(),
+                        passphrase: SecretString::new(std::mem::take(&mut self.backup_passphrase)),
+                    });
+                    self.backup_confirm.clear();
+                }
+            }
+            if ui.button(t!("settings.import_btn")).clicked() {
+                self.backup_error = self.backup_passphrase.is_empty();
+                if !self.backup_error {
+                    let _ = self.tx.send(UiCommand::ImportBackup {
+                        path: self.backup_path.clone(),
+                        passphrase: SecretString::new(std::mem::take(&mut self.backup_passphrase)),
+                    });
+                    self.backup_confirm.clear();
+                }
+            }
+        });
+        if self.backup_error {
+            ui.label(egui::RichText::new(t!("settings.passphrase_mismatch")).color(egui::Color32::RED));
+        }
+    }
+}
+
+/// Lista "una voce per riga": aggiorna `values` quando il campo perde il focus
+fn edit_lines(ui: &mut egui::Ui, buffer: &mut String, values: &mut Vec<String>) -> bool {
+    if !ui.add(egui::TextEdit::multiline(buffer).code_editor().desired_rows(2)).lost_focus() {
+        return false;
+    }
+    let lines: Vec<String> = buffer.lines()
+        .map(|l| l.trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect();
+    if lines == *values {
+        return false;
+    }
+    *values = lines;
+    true
+}
+
+/// Selettore del verso di sincronizzazione; true se il valore è cambiato
+fn direction_combo(ui: &mut egui::Ui, id: &str, direction: &mut SyncDirection) -> bool {
+    use rust_i18n::t;
+
+    let label = |d: SyncDirection| match d {
+        SyncDirection::Both => t!("settings.direction_both"),
+        SyncDirection::SendOnly => t!("settings.direction_send"),
+        SyncDirection::ReceiveOnly => t!("settings.direction_receive"),
+    };
+    let before = *direction;
+    egui::ComboBox::from_id_salt(("direction", id))
+        .selected_text(label(*direction))
+        .show_ui(ui, |ui| {
+            for option in [SyncDirection::Both, SyncDirection::SendOnly, SyncDirection::ReceiveOnly] {
+                ui.selectable_value(direction, option, label(option));
+            }
+        });
+    *direction != before
+}
+
+/// Selettore del formato immagine; true se il valore è cambiato
+fn codec_combo(ui: &mut egui::Ui, id: &str, codec: &mut ImageCodec) -> bool {
+    let before = *codec;
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(imaging::codec_name(*codec))
+        .show_ui(ui, |ui| {
+            for option in [ImageCodec::Png, ImageCodec::WebpLossless, ImageCodec::WebpLossy, ImageCodec::Jpeg] {
+                ui.selectable_value(codec, option, imaging::codec_name(option));
+            }
+        });
+    *codec != before
+}
+
+fn is_lossy(codec: ImageCodec) -> bool {
+    matches!(codec, ImageCodec::WebpLossy | ImageCodec::Jpeg)
+}
+
 impl eframe::App for RustClipApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         self.update_state();
@@ -98,9 +989,16 @@ impl eframe::App for RustClipApp {
             });
             ui.separator();
 
+            if self.locked {
+                self.show_unlock(ui);
+                return;
+            }
+
             // TABS
             ui.horizontal(|ui| {
                 if ui.selectable_label(self.current_tab == Tab::Dashboard, t!("dashboard.tab")).clicked() { self.current_tab = Tab::Dashboard; }
+                if ui.selectable_label(self.current_tab == Tab::Devices, t!("devices.tab")).clicked() { self.current_tab = Tab::Devices; }
+                if ui.selectable_label(self.current_tab == Tab::Doctor, t!("doctor.tab")).clicked() { self.current_tab = Tab::Doctor; }
                 if ui.selectable_label(self.current_tab == Tab::Settings, t!("settings.tab")).clicked() { self.current_tab = Tab::Settings; }
             });
             ui.separator();
@@ -108,138 +1006,285 @@ impl eframe::App for RustClipApp {
             match self.current_tab {
                 Tab::Dashboard => {
                     let btn_text = if self.is_paused { t!("dashboard.resume_sync") } else { t!("dashboard.pause_sync") };
-                    if ui.add(egui::Button::new(btn_text).min_size(egui::vec2(0.0, 30.0))).clicked() {
-                        let _ = self.tx.send(UiCommand::SetPaused(!self.is_paused));
-                    }
+                    ui.horizontal(|ui| {
+                        if ui.add(egui::Button::new(btn_text).min_size(egui::vec2(0.0, 30.0))).clicked() {
+                            let _ = self.tx.send(UiCommand::SetPaused(!self.is_paused));
+                        }
+                        // In modalità push i clip partono solo da qui (o dalla scorciatoia/tray)
+                        if self.config.push.manual && ui.add(egui::Button::new(t!("dashboard.push")).min_size(egui::vec2(0.0, 30.0))).clicked() {
+                            let _ = self.tx.send(UiCommand::Push);
+                        }
+                    });
                     
                     ui.add_space(15.0);
                     ui.label(egui::RichText::new(t!("dashboard.connected_devices")).strong());
                     egui::Frame::group(ui.style()).show(ui, |ui| {
-                        if self.peers.is_empty() {
+                        if self.ring_peers.is_empty() {
                             ui.label(t!("dashboard.no_devices"));
                         } else {
-                            for peer in &self.peers {
-                                ui.horizontal(|ui| {
-                                    ui.label("🖥️");
-                                    ui.label(egui::RichText::new(&peer.name).strong());
-                                    ui.label(format!("({})", peer.ip));
-                                });
+                            let mut changed = false;
+                            // Con più ring attivi i dispositivi si raggruppano per ring
+                            let grouped = self.ring_peers.len() > 1;
+                            for (ring_id, peers) in &self.ring_peers {
+                                if grouped {
+                                    ui.label(egui::RichText::new(self.config.rings.name(ring_id)).weak());
+                                }
+                                for peer in peers {
+                                    ui.horizontal(|ui| {
+                                        ui.label("🖥️");
+                                        ui.label(egui::RichText::new(&peer.name).strong());
+                                        ui.label(format!("({})", peer.ip));
+                                        if ui.small_button("📤").on_hover_text(t!("dashboard.send_to")).clicked() {
+                                            let _ = self.tx.send(UiCommand::SendTo(peer.device_id.clone()));
+                                        }
+                                        let approval = &mut self.config.approval;
+                                        let asks = approval.for_peer(Some(&peer.device_id)) == ApprovalPolicy::Ask;
+                                        if ui.selectable_label(asks, "✋").on_hover_text(t!("dashboard.ask_before_paste")).clicked() {
+                                            let policy = if asks { ApprovalPolicy::Auto } else { ApprovalPolicy::Ask };
+                                            // Uguale alla politica globale: non serve un'eccezione per il peer
+                                            if policy == approval.global {
+                                                approval.peers.remove(&peer.device_id);
+                                            } else {
+                                                approval.peers.insert(peer.device_id.clone(), policy);
+                                            }
+                                            changed = true;
+                                        }
+                                        let mut direction = self.config.direction.for_peer(&peer.device_id);
+                                        if direction_combo(ui, &format!("{}:{}", ring_id, peer.device_id), &mut direction) {
+                                            // Both è il default: non serve salvarlo
+                                            match direction {
+                                                SyncDirection::Both => self.config.direction.peers.remove(&peer.device_id),
+                                                _ => self.config.direction.peers.insert(peer.device_id.clone(), direction),
+                                            };
+                                            changed = true;
+                                        }
+                                    });
+                                }
+                            }
+                            if changed {
+                                let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
                             }
                         }
                     });
+                    if !self.pending.is_empty() {
+                        ui.add_space(15.0);
+                        self.show_pending(ui);
+                    }
+                    ui.add_space(15.0);
+                    self.show_stats(ui);
                     ui.add_space(15.0);
                     ui.label(t!("dashboard.logs"));
                     egui::ScrollArea::vertical().max_height(150.0).stick_to_bottom(true).show(ui, |ui| {
-                        for log in &self.logs { ui.monospace(log); }
+                        for log in &self.logs {
+                            let text = egui::RichText::new(format!("[{}] {}", log.timestamp, log.message)).monospace();
+                            ui.label(match log.level {
+                                LogLevel::Error => text.color(ui.visuals().error_fg_color),
+                                LogLevel::Warn => text.color(ui.visuals().warn_fg_color),
+                                LogLevel::Info | LogLevel::Success => text,
+                            });
+                        }
                     });
                 },
+                Tab::Devices => self.show_devices(ui),
+                Tab::Doctor => self.show_doctor(ui),
                 Tab::Settings => {
-                    ui.heading(t!("settings.title"));
+                    egui::ScrollArea::vertical().show(ui, |ui| {
+                        ui.heading(t!("settings.title"));
                     
-                    ui.horizontal(|ui| {
-                         ui.label(t!("settings.language"));
-                         let current = self.config.language.clone();
-                         egui::ComboBox::from_id_salt("lang_selector")
-                             .selected_text(if current == "it" { "🇮🇹 Italiano" } else { "🇺🇸 English" })
-                             .show_ui(ui, |ui| {
-                                 let mut changed = false;
-                                 if ui.selectable_value(&mut self.config.language, "en".to_string(), "🇺🇸 English").clicked() { changed = true; }
-                                 if ui.selectable_value(&mut self.config.language, "it".to_string(), "🇮🇹 Italiano").clicked() { changed = true; }
+                        ui.horizontal(|ui| {
+                             ui.label(t!("settings.language"));
+                             let current = self.config.language.clone();
+                             egui::ComboBox::from_id_salt("lang_selector")
+                                 .selected_text(if current == "it" { "🇮🇹 Italiano" } else { "🇺🇸 English" })
+                                 .show_ui(ui, |ui| {
+                                     let mut changed = false;
+                                     if ui.selectable_value(&mut self.config.language, "en".to_string(), "🇺🇸 English").clicked() { changed = true; }
+                                     if ui.selectable_value(&mut self.config.language, "it".to_string(), "🇮🇹 Italiano").clicked() { changed = true; }
                                  
-                                 if changed {
-                                     rust_i18n::set_locale(&self.config.language);
-                                     let _ = self.tx.send(UiCommand::UpdateConfig(self.config.clone()));
-                                 }
-                             });
-                    });
+                                     if changed {
+                                         rust_i18n::set_locale(&self.config.language);
+                                         let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+                                     }
+                                 });
+                        });
 
-                    // --- NOME DISPOSITIVO ---
-                    ui.horizontal(|ui| {
-                        ui.label(t!("settings.device_name"));
-                        if ui.text_edit_singleline(&mut self.config.device_name).lost_focus() {
-                            let _ = self.tx.send(UiCommand::UpdateConfig(self.config.clone()));
-                        }
-                    });
+                        // --- NOME DISPOSITIVO ---
+                        ui.horizontal(|ui| {
+                            ui.label(t!("settings.device_name"));
+                            if ui.text_edit_singleline(&mut self.config.device_name).lost_focus() {
+                                let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+                            }
+                        });
                     
-                    // --- NOTIFICHE ---
-                    if ui.checkbox(&mut self.config.notifications_enabled, t!("settings.enable_notifications")).changed() {
-                        let _ = self.tx.send(UiCommand::UpdateConfig(self.config.clone()));
-                    }
+                        // --- NOTIFICHE ---
+                        if ui.checkbox(&mut self.config.notifications_enabled, t!("settings.enable_notifications")).changed() {
+                            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+                        }
                     
-                    // --- AUTO START ---
-                    if ui.checkbox(&mut self.config.auto_start, t!("settings.auto_start")).changed() {
-                        let _ = self.tx.send(UiCommand::UpdateConfig(self.config.clone()));
-                    }
+                        // --- AUTO START ---
+                        if ui.checkbox(&mut self.config.auto_start, t!("settings.auto_start")).changed() {
+                            let _ = self.tx.send(UiCommand::UpdateConfig(Box::new(self.config.clone())));
+                        }
+
+                        ui.add_space(10.0);
+                        self.show_push(ui);
+
+                        ui.add_space(10.0);
+                        self.show_direction(ui);
+                        self.show_approval(ui);
+
+                        ui.add_space(10.0);
+                        self.show_sensitive(ui);
 
-                    ui.separator();
-                    ui.add_space(10.0);
-
-                    // --- CHIAVE SEGRETA ---
-                    ui.label(egui::RichText::new(t!("settings.credentials")).strong());
-                    ui.label(format!("{} {}", t!("settings.public_id"), self.my_ring_id));
-                    ui.label(t!("settings.secret_key"));
-                    ui.group(|ui| {
-                        ui.horizontal_wrapped(|ui| {
-                            if self.show_mnemonic {
-                                ui.add(egui::Label::new(
-                                    egui::RichText::new(&self.my_mnemonic).monospace()
-                                ).wrap());
-                            } else {
-                                ui.label("*************************************************");
+                        ui.add_space(10.0);
+                        self.show_auto_clear(ui);
+
+                        ui.add_space(10.0);
+                        self.show_app_rules(ui);
+
+                        ui.add_space(10.0);
+                        self.show_pipeline(ui);
+
+                        // La selezione PRIMARY esiste solo su Linux
+                        if cfg!(target_os = "linux") {
+                            ui.add_space(10.0);
+                            self.show_primary(ui);
+                        }
+
+                        ui.add_space(10.0);
+                        self.show_images(ui);
+
+                        ui.add_space(10.0);
+                        self.show_compression(ui);
+
+                        ui.add_space(10.0);
+                        self.show_throttle(ui);
+
+                        ui.add_space(10.0);
+                        self.show_hooks(ui);
+
+                        ui.add_space(10.0);
+                        self.show_logging(ui);
+
+                        ui.add_space(10.0);
+                        self.show_metrics_endpoint(ui);
+
+                        ui.separator();
+                        ui.add_space(10.0);
+                        self.show_rings(ui);
+
+                        ui.add_space(10.0);
+
+                        // --- CHIAVE SEGRETA ---
+                        ui.label(egui::RichText::new(t!("settings.credentials")).strong());
+                        ui.label(format!("{} {}", t!("settings.public_id"), self.my_ring_id));
+                        ui.label(t!("settings.secret_key"));
+                        ui.group(|ui| {
+                            ui.horizontal_wrapped(|ui| {
+                                if let (true, Some(mnemonic)) = (self.show_mnemonic, &self.revealed_mnemonic) {
+                                    ui.add(egui::Label::new(
+                                        egui::RichText::new(mnemonic.expose()).monospace()
+                                    ).wrap());
+                                } else {
+                                    ui.label("*************************************************");
+                                }
+                            });
+                            ui.add_space(5.0);
+                            ui.horizontal(|ui| {
+                                if ui.button(if self.show_mnemonic { t!("settings.hide_key") } else { t!("settings.show_key") }).clicked() {
+                                    self.show_mnemonic = !self.show_mnemonic;
+                                    self.sync_reveal();
+                                }
+                                // Si copia solo una chiave già rivelata esplicitamente
+                                let copy = ui.add_enabled(self.show_mnemonic && self.revealed_mnemonic.is_some(),
+                                    egui::Button::new(t!("settings.copy_key")));
+                                if copy.clicked() {
+                                    if let Some(mnemonic) = &self.revealed_mnemonic {
+                                        ui.output_mut(|o| o.copied_text = mnemonic.expose().to_string());
+                                    }
+                                }
+                                if ui.button(if self.show_qr { t!("settings.hide_qr") } else { t!("settings.show_qr") }).clicked() {
+                                    self.show_qr = !self.show_qr;
+                                    self.sync_reveal();
+                                }
+                            });
+                            if self.show_qr {
+                                self.show_pairing_qr(ui);
                             }
                         });
-                        ui.add_space(5.0);
-                        ui.horizontal(|ui| {
-                            if ui.button(if self.show_mnemonic { t!("settings.hide_key") } else { t!("settings.show_key") }).clicked() {
-                                self.show_mnemonic = !self.show_mnemonic;
-                            }
-                            if ui.button(t!("settings.copy_key")).clicked() {
-                                ui.output_mut(|o| o.copied_text = self.my_mnemonic.clone());
+
+                        ui.add_space(10.0);
+                        ui.label(egui::RichText::new(t!("settings.invite_title")).strong());
+                        if let Some(code) = &self.invite_code {
+                            ui.label(t!("settings.invite_msg"));
+                            ui.label(egui::RichText::new(code).monospace().size(28.0).strong());
+                            if ui.button(t!("settings.confirm_cancel")).clicked() {
+                                let _ = self.tx.send(UiCommand::CancelInvite);
                             }
-                        });
-                    });
+                        } else if ui.button(t!("settings.invite_btn")).clicked() {
+                            let _ = self.tx.send(UiCommand::StartInvite);
+                        }
 
-                    ui.add_space(20.0);
-                    ui.separator();
+                        ui.add_space(10.0);
+                        self.show_protection(ui);
+
+                        ui.add_space(10.0);
+                        self.show_backup(ui);
+
+                        ui.add_space(20.0);
+                        ui.separator();
                     
-                    ui.label(egui::RichText::new(t!("settings.join_ring")).strong());
-                    ui.text_edit_multiline(&mut self.join_phrase);
-                    if ui.button(t!("settings.join_btn")).clicked() {
-                        if !self.join_phrase.is_empty() {
-                            let _ = self.tx.send(UiCommand::JoinRing(self.join_phrase.clone()));
-                            self.join_phrase.clear();
+                        ui.label(egui::RichText::new(t!("settings.join_ring")).strong());
+                        ui.label(t!("settings.join_hint"));
+                        ui.text_edit_multiline(&mut self.join_phrase);
+                        if ui.button(t!("settings.join_btn")).clicked() && !self.join_phrase.is_empty() {
+                            let _ = self.tx.send(UiCommand::JoinRing(SecretString::new(std::mem::take(&mut self.join_phrase))));
                         }
-                    }
+                        ui.horizontal(|ui| {
+                            ui.label(t!("settings.join_qr_image"));
+                            ui.text_edit_singleline(&mut self.join_qr_path);
+                        });
+                        if ui.button(t!("settings.join_qr_btn")).clicked() && !self.join_qr_path.is_empty() {
+                            let _ = self.tx.send(UiCommand::JoinFromQrImage(std::mem::take(&mut self.join_qr_path)));
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label(t!("settings.join_code"));
+                            ui.add(egui::TextEdit::singleline(&mut self.join_code).desired_width(80.0));
+                            if ui.button(t!("settings.join_code_btn")).clicked() && !self.join_code.is_empty() {
+                                let _ = self.tx.send(UiCommand::JoinWithCode(std::mem::take(&mut self.join_code)));
+                            }
+                        });
 
-                    ui.add_space(20.0);
-                    ui.separator();
+                        ui.add_space(20.0);
+                        ui.separator();
 
-                    ui.label(egui::RichText::new(t!("settings.danger_zone")).strong().color(egui::Color32::RED));
+                        ui.label(egui::RichText::new(t!("settings.danger_zone")).strong().color(egui::Color32::RED));
                     
-                    if self.show_confirmation {
-                        ui.label(egui::RichText::new(t!("settings.confirm_title")).heading().color(egui::Color32::RED));
-                        ui.label(t!("settings.confirm_msg"));
+                        if self.show_confirmation {
+                            ui.label(egui::RichText::new(t!("settings.confirm_title")).heading().color(egui::Color32::RED));
+                            ui.label(t!("settings.confirm_msg"));
                         
-                        ui.horizontal(|ui| {
-                            if ui.button(egui::RichText::new(t!("settings.confirm_yes")).color(egui::Color32::RED)).clicked() {
-                                let _ = self.tx.send(UiCommand::GenerateNewIdentity);
-                                self.show_confirmation = false;
-                            }
-                            if ui.button(t!("settings.confirm_cancel")).clicked() {
-                                self.show_confirmation = false;
+                            ui.horizontal(|ui| {
+                                if ui.button(egui::RichText::new(t!("settings.confirm_yes")).color(egui::Color32::RED)).clicked() {
+                                    let _ = self.tx.send(UiCommand::GenerateNewIdentity);
+                                    self.show_confirmation = false;
+                                }
+                                if ui.button(t!("settings.confirm_cancel")).clicked() {
+                                    self.show_confirmation = false;
+                                }
+                            });
+                        } else {
+                            if ui.button(t!("settings.generate_id")).clicked() {
+                                self.show_confirmation = true;
                             }
-                        });
-                    } else {
-                        if ui.button(t!("settings.generate_id")).clicked() {
-                            self.show_confirmation = true;
                         }
-                    }
                     
-                    ui.add_space(20.0);
-                    if ui.button(t!("settings.quit")).clicked() {
-                        let _ = self.tx.send(UiCommand::Quit);
-                        std::process::exit(0);
-                    }
+                        ui.add_space(20.0);
+                        if ui.button(t!("settings.quit")).clicked() {
+                            let _ = self.tx.send(UiCommand::Quit);
+                            std::process::exit(0);
+                        }
+                    });
                 }
             }
         });
@@ -0,0 +1,44 @@
+use global_hotkey::{GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState};
+use global_hotkey::hotkey::HotKey;
+use flume::Sender;
+use crate::events::UiCommand;
+use anyhow::Result;
+
+/// Scorciatoia globale che fa partire il push manuale della clipboard
+pub struct PushHotkey {
+    manager: GlobalHotKeyManager,
+    current: Option<HotKey>,
+}
+
+impl PushHotkey {
+    /// Va creato sul thread principale (richiesto su macOS)
+    pub fn new() -> Result<Self> {
+        let manager = GlobalHotKeyManager::new().map_err(|e| anyhow::anyhow!("Hotkey manager error: {}", e))?;
+        Ok(Self { manager, current: None })
+    }
+
+    /// Sostituisce la scorciatoia registrata (es. "CmdOrCtrl+Alt+C"; vuota = nessuna)
+    pub fn set(&mut self, accelerator: &str) -> Result<()> {
+        if let Some(old) = self.current.take() {
+            let _ = self.manager.unregister(old);
+        }
+        if accelerator.trim().is_empty() {
+            return Ok(());
+        }
+        let hotkey: HotKey = accelerator.parse().map_err(|e| anyhow::anyhow!("{}", e))?;
+        self.manager.register(hotkey).map_err(|e| anyhow::anyhow!("{}", e))?;
+        self.current = Some(hotkey);
+        Ok(())
+    }
+}
+
+/// Inoltra al core ogni pressione della scorciatoia
+pub fn spawn_listener(tx: Sender<UiCommand>) {
+    std::thread::spawn(move || {
+        while let Ok(event) = GlobalHotKeyEvent::receiver().recv() {
+            if event.state == HotKeyState::Pressed {
+                let _ = tx.send(UiCommand::Push);
+            }
+        }
+    });
+}
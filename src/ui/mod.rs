@@ -1,5 +1,6 @@
 pub mod app;
 pub mod tray;
+pub mod hotkey;
 
 use eframe::egui;
 use flume::{Sender, Receiver};
@@ -28,8 +29,8 @@ pub fn run_gui(tx: Sender<UiCommand>, rx: Receiver<CoreEvent>) -> anyhow::Result
     
     let icon_data = eframe::egui::IconData {
         rgba: image.to_rgba8().into_raw(),
-        width: width as u32,
-        height: height as u32,
+        width,
+        height,
     };
 
     let options = eframe::NativeOptions {
@@ -63,6 +64,7 @@ pub fn run_gui(tx: Sender<UiCommand>, rx: Receiver<CoreEvent>) -> anyhow::Result
 
             let show_id = tray.menu_item_show.id().clone();
             let quit_id = tray.menu_item_quit.id().clone();
+            let push_id = tray.menu_item_push.id().clone();
             let tx_t = tx.clone();
             let ctx_t = ctx.clone();
 
@@ -71,7 +73,7 @@ pub fn run_gui(tx: Sender<UiCommand>, rx: Receiver<CoreEvent>) -> anyhow::Result
                 // Usiamo il percorso completo per evitare warning sugli import
                 while let Ok(event) = tray_icon::menu::MenuEvent::receiver().recv() {
                     if event.id == show_id {
-                        println!("Tray: Restore requested (Native)"); 
+                        tracing::debug!("Tray: Restore requested (Native)"); 
                         
                         #[cfg(target_os = "windows")]
                         unsafe {
@@ -92,11 +94,27 @@ pub fn run_gui(tx: Sender<UiCommand>, rx: Receiver<CoreEvent>) -> anyhow::Result
                     } else if event.id == quit_id {
                         let _ = tx_t.send(UiCommand::Quit);
                         std::process::exit(0);
+                    } else if event.id == push_id {
+                        let _ = tx_t.send(UiCommand::Push);
+                    } else if let Some(device_id) = event.id.0.strip_prefix(tray::SEND_TO_PREFIX) {
+                        let _ = tx_t.send(UiCommand::SendTo(device_id.to_string()));
                     }
                 }
             });
 
-            Ok(Box::new(app::RustClipApp::new(cc, tx, rx, tray)))
+            // La scorciatoia del push va registrata sul thread principale
+            let push_hotkey = match hotkey::PushHotkey::new() {
+                Ok(h) => {
+                    hotkey::spawn_listener(tx.clone());
+                    Some(h)
+                },
+                Err(e) => {
+                    tracing::warn!("⚠️ {}", e);
+                    None
+                }
+            };
+
+            Ok(Box::new(app::RustClipApp::new(cc, tx, rx, tray, push_hotkey)))
         }),
     ).map_err(|e| anyhow::anyhow!("GUI error: {}", e))
 }
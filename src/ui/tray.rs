@@ -1,12 +1,18 @@
 use tray_icon::{TrayIconBuilder, TrayIcon};
-use tray_icon::menu::{Menu, MenuItem, PredefinedMenuItem};
+use tray_icon::menu::{Menu, MenuItem, PredefinedMenuItem, Submenu};
+use crate::events::PeerInfo;
 use anyhow::Result;
 use image::GenericImageView; // Importante per leggere le dimensioni
 
+/// Prefisso degli id delle voci "Invia a"; il resto dell'id è il device_id del peer
+pub const SEND_TO_PREFIX: &str = "send-to:";
+
 pub struct AppTray {
     pub icon: TrayIcon,
     pub menu_item_show: MenuItem,
     pub menu_item_quit: MenuItem,
+    pub menu_item_push: MenuItem,
+    pub send_menu: Submenu,
 }
 
 impl AppTray {
@@ -15,9 +21,14 @@ impl AppTray {
         
         let menu_item_show = MenuItem::new(t!("tray.show"), true, None);
         let menu_item_quit = MenuItem::new(t!("tray.quit"), true, None);
+        let menu_item_push = MenuItem::new(t!("tray.push"), true, None);
+        // Popolato con i peer connessi (vedi `set_peers`)
+        let send_menu = Submenu::new(t!("tray.send_to"), false);
 
         // Costruiamo il menu
         tray_menu.append(&menu_item_show)?;
+        tray_menu.append(&menu_item_push)?;
+        tray_menu.append(&send_menu)?;
         tray_menu.append(&PredefinedMenuItem::separator())?;
         tray_menu.append(&menu_item_quit)?;
 
@@ -34,8 +45,20 @@ impl AppTray {
             icon: tray_icon,
             menu_item_show,
             menu_item_quit,
+            menu_item_push,
+            send_menu,
         })
     }
+
+    /// Ricostruisce il sottomenu "Invia a" con la lista aggiornata dei peer
+    pub fn set_peers(&self, peers: &[PeerInfo]) {
+        while self.send_menu.remove_at(0).is_some() {}
+        for peer in peers {
+            let id = format!("{}{}", SEND_TO_PREFIX, peer.device_id);
+            let _ = self.send_menu.append(&MenuItem::with_id(id, &peer.name, true, None));
+        }
+        self.send_menu.set_enabled(!peers.is_empty());
+    }
 }
 
 fn load_icon() -> Result<tray_icon::Icon> {
@@ -0,0 +1,55 @@
+use rust_clip::core::announce::{self, AnnounceMeta};
+use rust_clip::core::identity::RingIdentity;
+use std::collections::HashMap;
+
+fn meta() -> AnnounceMeta {
+    AnnounceMeta {
+        device_id: "0123456789abcdef0123456789abcdef".into(),
+        device_name: "Laptop di Federico".into(),
+        zip: "zstd,lz4".into(),
+        os: "linux".into(),
+        app: "0.1.0".into(),
+        primary: true,
+        img: Some("webp:2048".into()),
+    }
+}
+
+fn open(ring: &RingIdentity, tag: &str, props: &[(String, String)]) -> Option<AnnounceMeta> {
+    let props: HashMap<&str, &str> = props.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+    announce::open_meta(ring, tag, |key| props.get(key).copied())
+}
+
+#[test]
+fn test_meta_readable_only_by_ring() {
+    let ring = RingIdentity::generate().unwrap();
+    let props = announce::seal_meta(&ring, "tag-1", &meta()).unwrap();
+
+    // In chiaro non c'è nulla del dispositivo
+    for (_, value) in &props {
+        assert!(!value.contains("Laptop") && !value.contains("0123456789abcdef"));
+        assert!(value.len() <= 240);
+    }
+    assert_eq!(open(&ring, "tag-1", &props), Some(meta()));
+    // Altro ring, o annuncio copiato in un altro periodo
+    assert_eq!(open(&RingIdentity::generate().unwrap(), "tag-1", &props), None);
+    assert_eq!(open(&ring, "tag-2", &props), None);
+}
+
+#[test]
+fn test_meta_changes_every_announcement() {
+    let ring = RingIdentity::generate().unwrap();
+    let first = announce::seal_meta(&ring, "tag-1", &meta()).unwrap();
+    let second = announce::seal_meta(&ring, "tag-1", &meta()).unwrap();
+    assert_ne!(first, second);
+    assert_ne!(announce::random_instance_name(), announce::random_instance_name());
+}
+
+#[test]
+fn test_long_names_fit_the_announcement() {
+    let ring = RingIdentity::generate().unwrap();
+    let long = AnnounceMeta { device_name: announce::announced_name(&"x".repeat(1000)), ..meta() };
+    assert_eq!(long.device_name.len(), 64);
+    let props = announce::seal_meta(&ring, "tag-1", &long).unwrap();
+    assert!(props.len() > 1);
+    assert_eq!(open(&ring, "tag-1", &props), Some(long));
+}
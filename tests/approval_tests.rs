@@ -0,0 +1,73 @@
+use rust_clip::core::approval::{self, PendingQueue, MAX_PENDING};
+use rust_clip::core::config::{ApprovalConfig, ApprovalPolicy};
+use rust_clip::core::protocol::{ClipContent, ClipMeta};
+use std::net::{IpAddr, Ipv4Addr};
+use std::time::{Duration, Instant};
+
+const PEER_IP: IpAddr = IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20));
+
+fn text(s: &str) -> ClipContent {
+    ClipContent::Text(s.into())
+}
+
+#[test]
+fn test_pending_queue_accept_and_overflow() {
+    let mut queue = PendingQueue::default();
+    let (first, dropped) = queue.push("Laptop".into(), PEER_IP, text("one"), ClipMeta::default());
+    assert!(dropped.is_none());
+    let (second, _) = queue.push("Laptop".into(), PEER_IP, text("two"), ClipMeta::default());
+    assert_eq!(queue.infos().len(), 2);
+
+    // Preso una volta sola
+    let clip = queue.take(first).unwrap();
+    assert_eq!(clip.content, text("one"));
+    assert!(queue.take(first).is_none());
+    assert_eq!(queue.infos()[0].id, second);
+
+    // Coda piena: esce il più vecchio
+    for i in 0..MAX_PENDING {
+        let (_, dropped) = queue.push("Desktop".into(), PEER_IP, text(&i.to_string()), ClipMeta::default());
+        assert_eq!(dropped.is_some(), i == MAX_PENDING - 1);
+    }
+    assert_eq!(queue.infos().len(), MAX_PENDING);
+    assert!(queue.take(second).is_none());
+}
+
+#[test]
+fn test_pending_queue_expiry() {
+    let mut queue = PendingQueue::default();
+    let expiring = ClipMeta { expire_secs: Some(30), sensitive: true, ..ClipMeta::default() };
+    let (short, _) = queue.push("Laptop".into(), PEER_IP, text("otp"), expiring);
+    let (plain, _) = queue.push("Laptop".into(), PEER_IP, text("note"), ClipMeta::default());
+
+    assert!(queue.expire(Instant::now()).is_empty());
+    let expired = queue.expire(Instant::now() + Duration::from_secs(31));
+    assert_eq!(expired.iter().map(|c| c.id).collect::<Vec<_>>(), vec![short]);
+    // Senza scadenza resta in attesa
+    assert_eq!(queue.infos().iter().map(|c| c.id).collect::<Vec<_>>(), vec![plain]);
+    assert!(queue.expire(Instant::now() + Duration::from_secs(3600)).is_empty());
+}
+
+#[test]
+fn test_pending_preview() {
+    assert_eq!(approval::preview(&text("  hello\n  world "), &ClipMeta::default()), "hello world");
+
+    let long = approval::preview(&text(&"x".repeat(500)), &ClipMeta::default());
+    assert!(long.ends_with('…') && long.chars().count() < 100);
+
+    // I clip sensibili non vengono mostrati
+    let sensitive = ClipMeta { sensitive: true, ..ClipMeta::default() };
+    assert!(!approval::preview(&text("hunter2"), &sensitive).contains("hunter2"));
+}
+
+#[test]
+fn test_approval_policy_per_peer() {
+    let mut config = ApprovalConfig::default();
+    assert_eq!(config.for_peer(Some("laptop")), ApprovalPolicy::Auto);
+
+    config.global = ApprovalPolicy::Ask;
+    config.peers.insert("desktop".into(), ApprovalPolicy::Auto);
+    assert_eq!(config.for_peer(Some("laptop")), ApprovalPolicy::Ask);
+    assert_eq!(config.for_peer(Some("desktop")), ApprovalPolicy::Auto);
+    assert_eq!(config.for_peer(None), ApprovalPolicy::Ask);
+}
@@ -27,3 +27,75 @@ fn test_raw_encoding() {
     assert_eq!(height, h);
     assert_eq!(bytes, decoded_bytes);
 }
+
+#[test]
+fn test_clear_delay() {
+    use rust_clip::core::config::{AutoClearConfig, AutoClearMode};
+    use rust_clip::core::protocol::ClipMeta;
+
+    let plain = ClipMeta::default();
+    let sensitive = ClipMeta { expire_secs: None, sensitive: true, ..ClipMeta::default() };
+    let expiring = ClipMeta { expire_secs: Some(10), sensitive: true, ..ClipMeta::default() };
+
+    let off = AutoClearConfig { mode: AutoClearMode::Off, secs: 60 };
+    assert_eq!(clipboard::clear_delay(&off, &plain), None);
+    assert_eq!(clipboard::clear_delay(&off, &sensitive), None);
+    // La scadenza chiesta dal mittente vale sempre
+    assert_eq!(clipboard::clear_delay(&off, &expiring), Some(10));
+
+    let only_sensitive = AutoClearConfig { mode: AutoClearMode::Sensitive, secs: 60 };
+    assert_eq!(clipboard::clear_delay(&only_sensitive, &plain), None);
+    assert_eq!(clipboard::clear_delay(&only_sensitive, &sensitive), Some(60));
+    assert_eq!(clipboard::clear_delay(&only_sensitive, &expiring), Some(10));
+
+    let all = AutoClearConfig { mode: AutoClearMode::All, secs: 5 };
+    assert_eq!(clipboard::clear_delay(&all, &plain), Some(5));
+    assert_eq!(clipboard::clear_delay(&all, &expiring), Some(5));
+}
+
+#[test]
+fn test_sync_direction() {
+    use rust_clip::core::config::{DirectionConfig, SyncDirection};
+
+    let mut direction = DirectionConfig::default();
+    assert!(direction.sends_to("laptop") && direction.receives_from(Some("laptop")) && direction.receives_from(None));
+
+    // Restrizione su un solo peer
+    direction.peers.insert("ci".into(), SyncDirection::SendOnly);
+    assert!(direction.sends_to("ci") && !direction.receives_from(Some("ci")));
+    assert!(direction.receives_from(Some("laptop")));
+    // Mittente non identificato: potrebbe essere "ci", quindi viene rifiutato
+    assert!(!direction.receives_from(None));
+    direction.peers.insert("tablet".into(), SyncDirection::Both);
+    direction.peers.remove("ci");
+    assert!(direction.receives_from(None));
+    direction.peers.insert("ci".into(), SyncDirection::SendOnly);
+
+    // Il verso globale prevale su quello del peer
+    direction.global = SyncDirection::ReceiveOnly;
+    assert!(!direction.sends_to("laptop") && !direction.sends_to("ci"));
+    assert!(direction.receives_from(Some("laptop")) && !direction.receives_from(Some("ci")));
+}
+
+#[test]
+fn test_frames_route_to_their_ring() {
+    use rust_clip::core::clipboard::ActiveRing;
+    use rust_clip::core::config::CompressionConfig;
+    use rust_clip::core::identity::RingIdentity;
+    use rust_clip::core::protocol::{self, ClipContent, ClipMeta};
+    use std::sync::Arc;
+
+    let personal = ActiveRing::new(&RingIdentity::generate().unwrap(), Arc::new(dashmap::DashMap::new()));
+    let team = ActiveRing::new(&RingIdentity::generate().unwrap(), Arc::new(dashmap::DashMap::new()));
+    let rings = [personal.clone(), team.clone()];
+
+    let clip = protocol::encode_clip(&ClipContent::Text("hello team".into()), &ClipMeta::default()).unwrap();
+    let frame = protocol::seal_frame(&clip, None, &CompressionConfig::default(), &team.crypto).unwrap();
+    let header: [u8; 4] = frame[..4].try_into().unwrap();
+
+    // Il listener prova le chiavi dei ring attivi: il frame si apre solo con quella del suo ring
+    let (ring, opened) = clipboard::open_for_ring(&rings, header, &frame[4..]).unwrap();
+    assert_eq!(ring.id, team.id);
+    assert_eq!(opened, clip);
+    assert!(clipboard::open_for_ring(&rings[..1], header, &frame[4..]).is_none());
+}
@@ -0,0 +1,95 @@
+use rust_clip::core::compress;
+use rust_clip::core::config::{CompressionAlgorithm, CompressionConfig};
+use rust_clip::core::crypto::CryptoLayer;
+use rust_clip::core::protocol::{self, ClipContent, ClipMeta};
+use sha2::{Digest, Sha256};
+
+const MAX_PACKET_SIZE: usize = 50 * 1024 * 1024;
+
+fn large_log() -> ClipContent {
+    let line = "2024-05-01T12:00:00Z INFO request handled path=/api/v1/items status=200\n";
+    ClipContent::Text(line.repeat(500))
+}
+
+/// Ricezione come la fa un peer vecchio: header = lunghezza pura, nessuna decompressione
+fn legacy_receive(frame: &[u8], crypto: &CryptoLayer) -> Option<ClipContent> {
+    let len = u32::from_be_bytes(frame[..4].try_into().unwrap()) as usize;
+    if len > MAX_PACKET_SIZE {
+        return None;
+    }
+    let decrypted = crypto.decrypt(&frame[4..4 + len]).ok()?;
+    protocol::decode_clip(&decrypted).ok().map(|(content, _)| content)
+}
+
+fn receive(frame: &[u8], crypto: &CryptoLayer) -> ClipContent {
+    let header: [u8; 4] = frame[..4].try_into().unwrap();
+    let (len, _) = protocol::parse_frame_header(header).unwrap();
+    assert_eq!(len, frame.len() - 4);
+    let clip = protocol::open_frame(header, &frame[4..], crypto).unwrap();
+    protocol::decode_clip(&clip).unwrap().0
+}
+
+#[test]
+fn test_round_trip_with_mixed_peers() {
+    let crypto = CryptoLayer::new(&[7u8; 32]);
+    let content = large_log();
+    let clip = protocol::encode_clip(&content, &ClipMeta::default()).unwrap();
+
+    let zstd_config = CompressionConfig::default();
+    let lz4_config = CompressionConfig { algorithm: CompressionAlgorithm::Lz4, lz4_level: 9, ..CompressionConfig::default() };
+    let old_peer: Vec<CompressionAlgorithm> = Vec::new();
+    let new_peer = compress::parse_supported(&compress::supported_txt());
+
+    // Peer vecchio: nessuna compressione negoziata, il frame resta leggibile
+    assert_eq!(compress::negotiate(&zstd_config, &old_peer), None);
+    let frame = protocol::seal_frame(&clip, None, &zstd_config, &crypto).unwrap();
+    assert_eq!(legacy_receive(&frame, &crypto), Some(content.clone()));
+    assert_eq!(receive(&frame, &crypto), content);
+
+    // Peer nuovi: zstd e lz4, più piccoli del frame non compresso
+    for config in [&zstd_config, &lz4_config] {
+        let algorithm = compress::negotiate(config, &new_peer);
+        assert_eq!(algorithm, Some(config.algorithm));
+        let compressed = protocol::seal_frame(&clip, algorithm, config, &crypto).unwrap();
+        assert!(compressed.len() < frame.len() / 4, "{:?}", config.algorithm);
+        assert_eq!(receive(&compressed, &crypto), content);
+        // Se finisse per errore a un peer vecchio, verrebbe scartato (non decodificato male)
+        assert_eq!(legacy_receive(&compressed, &crypto), None);
+    }
+}
+
+#[test]
+fn test_threshold_and_incompressible_data() {
+    let config = CompressionConfig::default();
+
+    let small = b"short clip".to_vec();
+    assert_eq!(compress::maybe_compress(&small, Some(CompressionAlgorithm::Zstd), &config), (small.clone(), None));
+
+    // Sopra la soglia: il testo ripetitivo si comprime con entrambi gli algoritmi
+    let ClipContent::Text(log) = large_log() else { unreachable!() };
+    for algorithm in [CompressionAlgorithm::Zstd, CompressionAlgorithm::Lz4] {
+        let (out, applied) = compress::maybe_compress(log.as_bytes(), Some(algorithm), &config);
+        assert_eq!(applied, Some(algorithm));
+        assert!(out.len() < log.len() / 4);
+        assert_eq!(compress::decompress(&out, algorithm).unwrap(), log.as_bytes());
+    }
+
+    // 8 KiB pseudo-casuali (SHA-256 di un contatore): la compressione non conviene e viene saltata
+    let noise: Vec<u8> = (0..256u32).flat_map(|i| Sha256::digest(i.to_be_bytes())).collect();
+    for algorithm in [CompressionAlgorithm::Zstd, CompressionAlgorithm::Lz4] {
+        assert_eq!(compress::maybe_compress(&noise, Some(algorithm), &config), (noise.clone(), None));
+    }
+
+    let off = CompressionConfig { algorithm: CompressionAlgorithm::Off, ..config };
+    assert_eq!(compress::negotiate(&off, compress::SUPPORTED), None);
+}
+
+#[test]
+fn test_frame_header_flags() {
+    assert_eq!(protocol::parse_frame_header(protocol::frame_header(1234, None).unwrap()).unwrap(), (1234, None));
+    let header = protocol::frame_header(1234, Some(CompressionAlgorithm::Lz4)).unwrap();
+    assert_eq!(protocol::parse_frame_header(header).unwrap(), (1234, Some(CompressionAlgorithm::Lz4)));
+    assert!(protocol::frame_header(1 << 27, None).is_err());
+    // Bit riservati non ancora assegnati
+    assert!(protocol::parse_frame_header((1u32 << 28).to_be_bytes()).is_err());
+}
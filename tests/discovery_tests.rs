@@ -22,6 +22,12 @@ fn test_peer_map_insertion() {
         ip: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 8080),
         device_id: "device_unique_id".to_string(),
         last_seen: SystemTime::now(),
+        image_pref: None,
+        compression: Vec::new(),
+        primary_selection: false,
+        ring_id: "ring-abc".to_string(),
+        os: None,
+        app_version: None,
     };
     
     peer_map.insert(peer_info.device_id.clone(), peer_info.clone());
@@ -29,3 +35,41 @@ fn test_peer_map_insertion() {
     assert!(peer_map.contains_key("device_unique_id"));
     assert_eq!(peer_map.get("device_unique_id").unwrap().name, "TestDevice");
 }
+
+#[test]
+fn test_parse_peer_from_service() {
+    use mdns_sd::ServiceInfo;
+    use rust_clip::core::announce::{self, AnnounceMeta};
+    use rust_clip::core::identity::{tag_epoch, RingIdentity, TAG_PERIOD_SECS};
+
+    let ring = RingIdentity::generate().unwrap();
+    let now = 1_700_000_000;
+    let tag = ring.announce_tag(tag_epoch(now));
+    let meta = AnnounceMeta {
+        device_id: "dev-1".into(),
+        device_name: "Laptop".into(),
+        zip: "zstd".into(),
+        os: "linux".into(),
+        app: "0.1.0".into(),
+        primary: false,
+        img: None,
+    };
+    let mut properties = vec![("tag".to_string(), tag.clone())];
+    properties.extend(announce::seal_meta(&ring, &tag, &meta).unwrap());
+    let info = ServiceInfo::new("_rustclip._tcp.local.", "rc-0011223344556677", "rc-0011223344556677.local.", "192.168.1.20", 5566, &properties[..]).unwrap();
+
+    let peer = discovery::parse_peer(&info, &ring, now).unwrap();
+    assert_eq!(peer.name, "Laptop");
+    assert_eq!(peer.device_id, "dev-1");
+    assert_eq!(peer.ip, SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)), 5566));
+    assert!(!peer.primary_selection);
+    assert_eq!(peer.ring_id, ring.discovery_id);
+    assert_eq!(peer.os.as_deref(), Some("linux"));
+    assert_eq!(peer.app_version.as_deref(), Some("0.1.0"));
+
+    // Il tag vale anche nel periodo dopo (orologi sfasati), non oltre
+    assert!(discovery::parse_peer(&info, &ring, now + TAG_PERIOD_SECS).is_some());
+    assert!(discovery::parse_peer(&info, &ring, now + 3 * TAG_PERIOD_SECS).is_none());
+    // Servizi di un altro ring vengono ignorati
+    assert!(discovery::parse_peer(&info, &RingIdentity::generate().unwrap(), now).is_none());
+}
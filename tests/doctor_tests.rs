@@ -0,0 +1,88 @@
+use rust_clip::core::crypto::{CryptoLayer, MAX_FUTURE_SECONDS};
+use rust_clip::core::doctor::{self, Check, Report, Status};
+use rust_clip::core::handshake;
+use rust_clip::core::identity::RingIdentity;
+use rust_clip::core::protocol::{self, Hello};
+use rust_clip::core::roster::now_secs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener};
+use std::time::Duration;
+
+/// Listener di un peer con l'orologio spostato di `offset` secondi: come `run_server`,
+/// scarta le aperture che per il suo orologio vengono dal futuro o sono troppo vecchie
+fn serve_skewed(key: [u8; 32], offset: i64, attempts: usize) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let crypto = CryptoLayer::new(&key);
+        for _ in 0..attempts {
+            let (mut socket, _) = listener.accept().unwrap();
+            let mut header = [0u8; 4];
+            socket.read_exact(&mut header).unwrap();
+            let (len, _) = protocol::parse_frame_header(header).unwrap();
+            let mut body = vec![0u8; len];
+            socket.read_exact(&mut body).unwrap();
+
+            let peer_now = now_secs().saturating_add_signed(offset);
+            let (sent_at, plain) = crypto.decrypt_with_time(&body).unwrap();
+            if sent_at > peer_now + MAX_FUTURE_SECONDS || sent_at + 60 < peer_now {
+                continue;
+            }
+            let opening = protocol::decode_hello(&plain).unwrap();
+            let reply = Hello { echo: Some(opening.nonce), ..handshake::opening("dev-b", "Desktop") };
+            socket.write_all(&protocol::seal_hello_at(&reply, &crypto, peer_now).unwrap()).unwrap();
+        }
+    });
+    addr
+}
+
+#[test]
+fn test_probe_reads_peer_clock() {
+    let key = [9u8; 32];
+    let addr = serve_skewed(key, 20, 1);
+    let (hello, peer_time) = handshake::probe(addr, &CryptoLayer::new(&key), "dev-a", 0, Duration::from_secs(2)).unwrap();
+    assert_eq!(hello.device_id, "dev-b");
+    assert!((peer_time as i64 - now_secs() as i64 - 20).abs() <= 1);
+}
+
+#[test]
+fn test_probe_reaches_peer_behind() {
+    // Un peer indietro di 30 s rifiuta l'apertura normale: il secondo tentativo la retrodata
+    let key = [9u8; 32];
+    let addr = serve_skewed(key, -30, 2);
+    let crypto = CryptoLayer::new(&key);
+    assert!(handshake::probe(addr, &crypto, "dev-a", 0, Duration::from_secs(1)).is_err());
+    let (_, peer_time) = handshake::probe(addr, &crypto, "dev-a", -50, Duration::from_secs(2)).unwrap();
+    assert!((peer_time as i64 - now_secs() as i64 + 30).abs() <= 1);
+}
+
+#[test]
+fn test_decrypt_with_time_skips_replay_check() {
+    let crypto = CryptoLayer::new(&[3u8; 32]);
+    let old = crypto.encrypt_at(b"clip", now_secs() - 600).unwrap();
+    assert!(crypto.decrypt(&old).is_err());
+    let (sent_at, data) = crypto.decrypt_with_time(&old).unwrap();
+    assert_eq!(sent_at, now_secs() - 600);
+    assert_eq!(data, b"clip");
+}
+
+#[test]
+fn test_loopback() {
+    let ring = RingIdentity::generate().unwrap();
+    assert!(doctor::loopback(&ring, "dev-a").is_ok());
+}
+
+#[test]
+fn test_report() {
+    let report = Report {
+        checks: vec![
+            Check::new(Status::Ok, "Identity", "decrypted"),
+            Check::new(Status::Warn, "mDNS on eth0", "no echo"),
+        ],
+    };
+    assert_eq!(report.worst(), Status::Warn);
+    assert_eq!(report.failures(), 0);
+    let text = report.render();
+    assert!(text.contains("✅ Identity: decrypted\n"));
+    assert!(text.contains("⚠️ mDNS on eth0: no echo\n"));
+}
@@ -0,0 +1,70 @@
+use rust_clip::core::crypto::CryptoLayer;
+use rust_clip::core::handshake;
+use rust_clip::core::protocol;
+use std::io::{Read, Write};
+use std::net::TcpListener;
+use std::time::Duration;
+
+/// Listener di un solo handshake, come quello di `run_server`
+fn serve_once(key: [u8; 32], device_id: &'static str, name: &'static str) -> std::net::SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    std::thread::spawn(move || {
+        let (mut socket, _) = listener.accept().unwrap();
+        let mut header = [0u8; 4];
+        socket.read_exact(&mut header).unwrap();
+        assert!(protocol::is_hello(header));
+        let (len, _) = protocol::parse_frame_header(header).unwrap();
+        let mut body = vec![0u8; len];
+        socket.read_exact(&mut body).unwrap();
+        if let Ok(reply) = handshake::answer(&body, &CryptoLayer::new(&key), device_id, name) {
+            socket.write_all(&reply).unwrap();
+        }
+    });
+    addr
+}
+
+#[test]
+fn test_handshake_proves_identity() {
+    let key = [7u8; 32];
+    let addr = serve_once(key, "dev-b", "Desktop");
+
+    let hello = handshake::verify_peer(addr, &CryptoLayer::new(&key), "dev-a", "Laptop", Duration::from_secs(2)).unwrap();
+    assert_eq!(hello.device_id, "dev-b");
+    assert_eq!(hello.device_name, "Desktop");
+}
+
+#[test]
+fn test_handshake_rejects_other_ring() {
+    // Chi non ha la chiave del ring non risponde, qualunque nome annunci
+    let addr = serve_once([1u8; 32], "dev-b", "Desktop");
+    assert!(handshake::verify_peer(addr, &CryptoLayer::new(&[2u8; 32]), "dev-a", "Laptop", Duration::from_secs(2)).is_err());
+}
+
+#[test]
+fn test_reply_must_echo_our_nonce() {
+    let crypto = CryptoLayer::new(&[7u8; 32]);
+    let opening = handshake::opening("dev-a", "Laptop");
+    let reply_to = |hello: &protocol::Hello| {
+        let frame = protocol::seal_hello(hello, &crypto).unwrap();
+        let reply = handshake::answer(&frame[4..], &crypto, "dev-b", "Desktop").unwrap();
+        protocol::open_hello(&reply[4..], &crypto).unwrap()
+    };
+
+    assert!(handshake::check_reply(&opening, &reply_to(&opening)).is_ok());
+    // Risposta registrata da un handshake precedente
+    let old = reply_to(&handshake::opening("dev-a", "Laptop"));
+    assert!(handshake::check_reply(&opening, &old).is_err());
+    // La nostra apertura rimbalzata sul nostro stesso listener
+    let mirrored = protocol::Hello { echo: Some(opening.nonce), ..handshake::opening("dev-a", "Laptop") };
+    assert!(handshake::check_reply(&opening, &mirrored).is_err());
+}
+
+#[test]
+fn test_answer_ignores_clips() {
+    let crypto = CryptoLayer::new(&[7u8; 32]);
+    let clip = protocol::encode_clip(&protocol::ClipContent::Text("x".into()), &Default::default()).unwrap();
+    let frame = protocol::seal_frame(&clip, None, &Default::default(), &crypto).unwrap();
+    assert!(!protocol::is_hello(frame[..4].try_into().unwrap()));
+    assert!(handshake::answer(&frame[4..], &crypto, "dev-b", "Desktop").is_err());
+}
@@ -0,0 +1,67 @@
+#![cfg(unix)]
+use rust_clip::core::config::HookConfig;
+use rust_clip::core::hooks::{self, HookContext, HookEvent, HookOutcome};
+use rust_clip::core::protocol::ClipContent;
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+fn hook(command: &str, rewrite: bool) -> HookConfig {
+    HookConfig { command: command.into(), rewrite }
+}
+
+fn ctx() -> HookContext {
+    HookContext { event: HookEvent::Receive, device_name: "Laptop".into(), peers: vec!["10.0.0.2".into()], sensitive: true, expire_secs: None }
+}
+
+fn text(s: &str) -> ClipContent {
+    ClipContent::Text(s.into())
+}
+
+#[test]
+fn test_hook_env_and_keep() {
+    let check = r#"test "$RUSTCLIP_EVENT" = receive && test "$RUSTCLIP_KIND" = text && test "$RUSTCLIP_SIZE" = 5 && test "$RUSTCLIP_SENSITIVE" = 1 && grep -q hello"#;
+    assert_eq!(hooks::run_hook(&hook(check, false), TIMEOUT, &text("hello"), &ctx()).unwrap(), HookOutcome::Keep);
+}
+
+#[test]
+fn test_hook_veto_and_rewrite() {
+    assert_eq!(hooks::run_hook(&hook("exit 3", false), TIMEOUT, &text("x"), &ctx()).unwrap(), HookOutcome::Veto(3));
+    assert_eq!(
+        hooks::run_hook(&hook("tr a-z A-Z", true), TIMEOUT, &text("hello"), &ctx()).unwrap(),
+        HookOutcome::Replace("HELLO".into())
+    );
+    // Senza rewrite lo stdout viene ignorato
+    assert_eq!(hooks::run_hook(&hook("echo other", false), TIMEOUT, &text("hello"), &ctx()).unwrap(), HookOutcome::Keep);
+}
+
+#[test]
+fn test_hook_timeout_and_sandbox() {
+    assert!(hooks::run_hook(&hook("sleep 10", false), Duration::from_secs(1), &text("x"), &ctx()).is_err());
+
+    // Il hook gira in una cartella temporanea vuota, rimossa alla fine
+    let out = hooks::run_hook(&hook("test -z \"$(ls -A)\" && pwd", true), TIMEOUT, &text("x"), &ctx()).unwrap();
+    let HookOutcome::Replace(dir) = out else { panic!("atteso il percorso della cartella") };
+    assert!(dir.contains("rustclip-hook-"));
+    assert!(!std::path::Path::new(&dir).exists());
+}
+
+#[test]
+fn test_hook_timeout_kills_children() {
+    // Il figlio in background sopravviverebbe alla sola shell: deve morire con il gruppo
+    let marker = std::env::temp_dir().join(format!("rustclip-hook-orphan-{}", std::process::id()));
+    let command = format!("(sleep 1 && touch '{}') & wait", marker.display());
+    assert!(hooks::run_hook(&hook(&command, false), Duration::from_millis(300), &text("x"), &ctx()).is_err());
+    std::thread::sleep(Duration::from_secs(2));
+    assert!(!marker.exists());
+}
+
+#[test]
+fn test_hook_background_stdout_respects_timeout() {
+    // La shell esce subito ma `sleep` tiene aperto stdout: niente attesa oltre la scadenza
+    let started = std::time::Instant::now();
+    let out = hooks::run_hook(&hook("sleep 30 & echo x", true), Duration::from_millis(500), &text("x"), &ctx()).unwrap();
+    assert!(started.elapsed() < Duration::from_secs(2));
+    // Output scartato: il clip prosegue invariato
+    assert_eq!(out, HookOutcome::Keep);
+}
@@ -0,0 +1,129 @@
+use rust_clip::core::identity::{RingIdentity, KeyProtection, SecretString};
+use rust_clip::core::vault::{self, KdfParams};
+
+const FAST: KdfParams = KdfParams { m_cost_kib: 64, t_cost: 1, p_cost: 1 };
+const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art";
+
+#[test]
+fn test_backup_roundtrip() {
+    let id = RingIdentity::from_mnemonic(PHRASE).unwrap();
+    let data = id.export_backup(&[], "Laptop", "backup-pw", FAST).unwrap();
+
+    let (restored, backup) = RingIdentity::import_backup(&data, "backup-pw").unwrap();
+    assert_eq!(restored.discovery_id, id.discovery_id);
+    assert_eq!(restored.shared_secret, id.shared_secret);
+    assert_eq!(backup.device_name, "Laptop");
+    assert!(backup.extra_rings().unwrap().is_some_and(|rings| rings.is_empty()));
+}
+
+#[test]
+fn test_backup_includes_extra_rings() {
+    let id = RingIdentity::from_mnemonic(PHRASE).unwrap();
+    let team = RingIdentity::generate().unwrap();
+    let data = id.export_backup(std::slice::from_ref(&team), "Laptop", "backup-pw", FAST).unwrap();
+
+    let (_, backup) = RingIdentity::import_backup(&data, "backup-pw").unwrap();
+    let rings = backup.extra_rings().unwrap().unwrap();
+    assert_eq!(rings.len(), 1);
+    assert_eq!(rings[0].discovery_id, team.discovery_id);
+
+    // Backup di una versione precedente, senza ring aggiuntivi: None, non "nessun ring"
+    let json = format!(r#"{{"version":1,"mnemonic":"{}","device_name":"X"}}"#, PHRASE);
+    let data = vault::seal(b"RCB1", json.as_bytes(), "pw", FAST).unwrap();
+    let (_, backup) = RingIdentity::import_backup(&data, "pw").unwrap();
+    assert!(backup.extra_rings().unwrap().is_none());
+}
+
+#[test]
+fn test_backup_wrong_passphrase_fails() {
+    let id = RingIdentity::from_mnemonic(PHRASE).unwrap();
+    let data = id.export_backup(&[], "Laptop", "backup-pw", FAST).unwrap();
+    assert!(RingIdentity::import_backup(&data, "nope").is_err());
+}
+
+#[test]
+fn test_backup_rejects_invalid_content() {
+    // Un file qualsiasi non è un backup
+    assert!(RingIdentity::import_backup(b"not a backup at all", "pw").is_err());
+
+    // Un backup ben cifrato ma con un mnemonic non valido viene rifiutato
+    let json = br#"{"version":1,"mnemonic":"hello world","device_name":"X"}"#;
+    let data = vault::seal(b"RCB1", json, "pw", FAST).unwrap();
+    assert!(RingIdentity::import_backup(&data, "pw").is_err());
+}
+
+#[test]
+fn test_debug_redacts_secrets() {
+    let id = RingIdentity::from_mnemonic(PHRASE).unwrap();
+    let dump = format!("{:?}", id);
+    assert!(dump.contains(&id.discovery_id));
+    assert!(!dump.contains("abandon"));
+
+    let pass = SecretString::new("hunter2".into());
+    assert!(!format!("{:?}", KeyProtection::Passphrase(pass)).contains("hunter2"));
+}
+
+#[test]
+fn test_ring_settings_defaults() {
+    use rust_clip::core::config::RingsConfig;
+
+    let mut rings = RingsConfig::default();
+    // Un ring senza impostazioni è attivo e ha un nome derivato dall'ID
+    assert!(rings.is_enabled("a1b2c3d4e5"));
+    assert_eq!(rings.name("a1b2c3d4e5"), "Ring a1b2c3");
+
+    rings.entry("a1b2c3d4e5").name = "Team".into();
+    rings.entry("a1b2c3d4e5").enabled = false;
+    assert!(!rings.is_enabled("a1b2c3d4e5"));
+    assert_eq!(rings.name("a1b2c3d4e5"), "Team");
+}
+
+#[test]
+fn test_device_id_is_full_length() {
+    use rust_clip::core::identity::device_id_from_machine;
+
+    let id = device_id_from_machine("machine-1");
+    assert_eq!(id.len(), 32);
+    assert!(id.chars().all(|c| c.is_ascii_hexdigit()));
+    assert_eq!(id, device_id_from_machine("machine-1"));
+    assert_ne!(id, device_id_from_machine("machine-2"));
+    assert_eq!(RingIdentity::get_derived_device_id().len(), 32);
+}
+
+#[test]
+fn test_announce_tag_rotates() {
+    let id = RingIdentity::from_mnemonic(PHRASE).unwrap();
+    let tag = id.announce_tag(100);
+    assert_eq!(tag.len(), 16);
+    assert_eq!(tag, id.announce_tag(100));
+    // Cambia a ogni periodo, non contiene l'ID del ring e dipende dal ring
+    assert_ne!(tag, id.announce_tag(101));
+    assert!(!id.discovery_id.contains(&tag));
+    assert_ne!(tag, RingIdentity::generate().unwrap().announce_tag(100));
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn test_change_protection_reseals_extra_rings() {
+    // Cartella config isolata: nessun altro test di questo file tocca il disco
+    let dir = std::env::temp_dir().join(format!("rustclip-protect-{}", std::process::id()));
+    std::env::set_var("XDG_CONFIG_HOME", &dir);
+
+    let id = RingIdentity::from_mnemonic(PHRASE).unwrap();
+    let team = RingIdentity::generate().unwrap();
+    id.save().unwrap();
+    RingIdentity::save_extra_rings(std::slice::from_ref(&team), &KeyProtection::Machine).unwrap();
+
+    let pass = KeyProtection::Passphrase(SecretString::new("correct horse".into()));
+    id.change_protection(&KeyProtection::Machine, &pass).unwrap();
+    assert!(RingIdentity::is_passphrase_protected().unwrap());
+    // Con la passphrase i ring aggiuntivi non si aprono più con la chiave macchina
+    assert!(RingIdentity::load_extra_rings(&KeyProtection::Machine).is_err());
+    assert_eq!(RingIdentity::load_extra_rings(&pass).unwrap()[0].discovery_id, team.discovery_id);
+
+    id.change_protection(&pass, &KeyProtection::Machine).unwrap();
+    assert!(!RingIdentity::is_passphrase_protected().unwrap());
+    assert_eq!(RingIdentity::load_extra_rings(&KeyProtection::Machine).unwrap()[0].discovery_id, team.discovery_id);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,81 @@
+use rust_clip::core::config::{ImageCodec, ImageConfig, ImagePreference};
+use rust_clip::core::imaging::{self, ImageSettings, RawImage};
+
+/// Gradiente con trasparenza: abbastanza "rumoroso" da non comprimersi a zero
+fn sample(width: u32, height: u32) -> RawImage {
+    let mut rgba = Vec::with_capacity((width * height * 4) as usize);
+    for y in 0..height {
+        for x in 0..width {
+            rgba.extend_from_slice(&[(x * 7 % 256) as u8, (y * 13 % 256) as u8, ((x ^ y) % 256) as u8, if x < width / 2 { 255 } else { 128 }]);
+        }
+    }
+    RawImage { width, height, rgba }
+}
+
+fn settings(codec: ImageCodec) -> ImageSettings {
+    ImageSettings::from_config(&ImageConfig { codec, ..ImageConfig::default() })
+}
+
+#[test]
+fn test_every_codec_round_trips() {
+    let raw = sample(64, 48);
+    for codec in [ImageCodec::Png, ImageCodec::WebpLossless, ImageCodec::WebpLossy, ImageCodec::Jpeg] {
+        let bytes = imaging::encode(&raw, &settings(codec)).unwrap();
+        let decoded = image::load_from_memory(&bytes).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (64, 48), "{:?}", codec);
+    }
+
+    // I codec lossless conservano i pixel esatti
+    for codec in [ImageCodec::Png, ImageCodec::WebpLossless] {
+        let bytes = imaging::encode(&raw, &settings(codec)).unwrap();
+        assert_eq!(image::load_from_memory(&bytes).unwrap().to_rgba8().into_raw(), raw.rgba);
+    }
+}
+
+#[test]
+fn test_downscale_keeps_aspect_ratio() {
+    let raw = sample(400, 100);
+    let mut s = settings(ImageCodec::Png);
+    s.max_dimension = 200;
+    let decoded = image::load_from_memory(&imaging::encode(&raw, &s).unwrap()).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (200, 50));
+
+    // Le immagini piccole non vengono ingrandite
+    s.max_dimension = 1000;
+    let decoded = image::load_from_memory(&imaging::encode(&raw, &s).unwrap()).unwrap();
+    assert_eq!((decoded.width(), decoded.height()), (400, 100));
+}
+
+#[test]
+fn test_max_bytes_shrinks_or_fails() {
+    let raw = sample(512, 512);
+    let mut s = settings(ImageCodec::Png);
+    let full = imaging::encode(&raw, &s).unwrap().len();
+
+    s.max_bytes = full / 3;
+    let bytes = imaging::encode(&raw, &s).unwrap();
+    assert!(bytes.len() <= s.max_bytes);
+    let decoded = image::load_from_memory(&bytes).unwrap();
+    assert!(decoded.width() < 512);
+
+    s.max_bytes = 10;
+    assert!(imaging::encode(&raw, &s).is_err());
+}
+
+#[test]
+fn test_peer_preference() {
+    let pref = ImagePreference { codec: ImageCodec::WebpLossy, quality: 70, max_dimension: 1920 };
+    let txt = imaging::preference_txt(&pref);
+    assert_eq!(txt, "webp-lossy:70:1920");
+    assert_eq!(imaging::parse_preference(&txt), Some(pref.clone()));
+    assert_eq!(imaging::parse_preference("avif:50:0"), None);
+    assert_eq!(imaging::parse_preference("jpeg").map(|p| p.codec), Some(ImageCodec::Jpeg));
+
+    let local = ImageSettings::from_config(&ImageConfig { max_dimension: 1024, ..ImageConfig::default() });
+    assert_eq!(local.for_peer(None), local);
+    let peer = local.for_peer(Some(&pref));
+    assert_eq!((peer.codec, peer.quality), (ImageCodec::WebpLossy, 70));
+    // Vince il lato massimo più stretto
+    assert_eq!(peer.max_dimension, 1024);
+    assert_eq!(peer.max_bytes, local.max_bytes);
+}
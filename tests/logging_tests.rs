@@ -0,0 +1,31 @@
+use rust_clip::core::config::{AppConfig, LogVerbosity, LoggingConfig};
+use rust_clip::core::logging;
+use rust_clip::events::{LogEntry, LogLevel};
+use tracing::Level;
+
+#[test]
+fn test_ui_shows_info_and_above() {
+    assert_eq!(logging::ui_level(&Level::ERROR), Some(LogLevel::Error));
+    assert_eq!(logging::ui_level(&Level::WARN), Some(LogLevel::Warn));
+    assert_eq!(logging::ui_level(&Level::INFO), Some(LogLevel::Info));
+    // Il dettaglio resta su terminale e file
+    assert_eq!(logging::ui_level(&Level::DEBUG), None);
+    assert_eq!(logging::ui_level(&Level::TRACE), None);
+
+    assert_eq!(LogEntry::with_level(LogLevel::Warn, "x").level, LogLevel::Warn);
+    assert_eq!(LogEntry::new("x").level, LogLevel::Info);
+}
+
+#[test]
+fn test_logging_config() {
+    // Le config salvate prima del logging su file lo attivano con i valori di default
+    let old: AppConfig = serde_json::from_str(r#"{"device_name":"pc","notifications_enabled":true,"auto_start":false}"#).unwrap();
+    assert_eq!(old.logging, LoggingConfig::default());
+    assert_eq!(old.logging.verbosity, LogVerbosity::Info);
+    assert!(old.logging.file_enabled);
+
+    let custom: LoggingConfig = serde_json::from_str(r#"{"verbosity":"debug","max_files":3}"#).unwrap();
+    assert_eq!(custom.verbosity, LogVerbosity::Debug);
+    assert_eq!(custom.max_files, 3);
+    assert!(LogVerbosity::Debug > LogVerbosity::Info);
+}
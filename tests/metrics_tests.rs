@@ -0,0 +1,107 @@
+use rust_clip::core::metrics::{self, Failure, Histogram, Metrics, LATENCY_BUCKETS_MS};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+#[test]
+fn test_histogram_is_cumulative() {
+    let mut hist = Histogram::new(&LATENCY_BUCKETS_MS);
+    hist.observe(7);
+    hist.observe(80);
+    hist.observe(9000);
+    let buckets: Vec<(u64, u64)> = hist.buckets().collect();
+    assert_eq!(buckets[0], (5, 0));
+    assert_eq!(buckets[1], (10, 1));
+    assert_eq!(buckets[4], (100, 2));
+    // Oltre l'ultimo bucket conta solo nel totale (+Inf)
+    assert_eq!(buckets.last(), Some(&(5000, 2)));
+    assert_eq!(hist.count, 3);
+    assert_eq!(hist.mean(), Some(3029));
+}
+
+#[test]
+fn test_counters_per_peer() {
+    let mut m = Metrics::default();
+    m.record_sent("dev-b", "Desktop", 1000, Duration::from_millis(12));
+    m.record_sent("dev-b", "Desktop", 500, Duration::from_millis(30));
+    m.record_received("dev-c", "Laptop", 200);
+    m.record_failure(Failure::Connect);
+    m.record_decrypt_rejected();
+
+    assert_eq!(m.peers["dev-b"].sent, 2);
+    assert_eq!(m.peers["dev-b"].bytes_sent, 1500);
+    assert_eq!(m.peers["dev-c"].received, 1);
+    assert_eq!(m.total_sent(), 2);
+    assert_eq!(m.total_received(), 1);
+    assert_eq!(m.failures[&Failure::Connect], 1);
+    assert_eq!(m.decrypt_rejected, 1);
+    assert_eq!(m.revision, 5);
+}
+
+#[test]
+fn test_prometheus_format() {
+    let mut m = Metrics::default();
+    m.record_sent("dev-b", "Desk \"A\"", 1000, Duration::from_millis(12));
+    let text = m.render_prometheus();
+    assert!(text.contains("# TYPE rustclip_clips_sent_total counter\n"));
+    assert!(text.contains("rustclip_clips_sent_total{peer=\"dev-b\",name=\"Desk \\\"A\\\"\"} 1\n"));
+    assert!(text.contains("rustclip_send_latency_seconds_bucket{peer=\"dev-b\",name=\"Desk \\\"A\\\"\",le=\"0.025\"} 1\n"));
+    assert!(text.contains("rustclip_clip_size_bytes_count{direction=\"sent\"} 1\n"));
+    assert!(text.contains("rustclip_failures_total{reason=\"rate_limited\"} 0\n"));
+    assert!(text.contains("rustclip_decrypt_rejected_total 0\n"));
+}
+
+#[test]
+fn test_format_bytes() {
+    assert_eq!(metrics::format_bytes(512), "512 B");
+    assert_eq!(metrics::format_bytes(1536), "1.5 KiB");
+    assert_eq!(metrics::format_bytes(5 * 1024 * 1024), "5.0 MiB");
+}
+
+#[tokio::test]
+async fn test_endpoint_serves_metrics() {
+    let shared = Arc::new(Mutex::new(Metrics::default()));
+    shared.lock().unwrap().record_received("dev-b", "Desktop", 300);
+    // Porta libera scelta dal sistema, poi rilasciata per l'endpoint
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    tokio::spawn(metrics::serve(shared, port));
+
+    let get = |path: &'static str, host: &'static str| async move {
+        let mut stream = loop {
+            match tokio::net::TcpStream::connect(("127.0.0.1", port)).await {
+                Ok(stream) => break stream,
+                Err(_) => tokio::time::sleep(Duration::from_millis(20)).await,
+            }
+        };
+        stream.write_all(format!("GET {} HTTP/1.1\r\nHost: {}\r\n\r\n", path, host).as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    };
+
+    let response = get("/metrics", "localhost").await;
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("rustclip_clips_received_total{peer=\"dev-b\",name=\"Desktop\"} 1"));
+    assert!(get("/", "localhost").await.starts_with("HTTP/1.1 404"));
+    // Pagina web con un suo dominio risolto a 127.0.0.1 (DNS rebinding)
+    assert!(get("/metrics", "evil.example:9466").await.starts_with("HTTP/1.1 403"));
+}
+
+#[test]
+fn test_local_host_header() {
+    assert!(metrics::local_host("GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n"));
+    assert!(metrics::local_host("GET /metrics HTTP/1.1\r\nUser-Agent: x\r\nhost: 127.0.0.1:9466\r\n\r\n"));
+    assert!(!metrics::local_host("GET /metrics HTTP/1.1\r\nHost: localhost.evil.example\r\n\r\n"));
+    assert!(!metrics::local_host("GET /metrics HTTP/1.1\r\nHost: 127.0.0.1.nip.io\r\n\r\n"));
+    // Senza Host (HTTP/1.0 o client artigianali) la richiesta è rifiutata
+    assert!(!metrics::local_host("GET /metrics HTTP/1.0\r\n\r\n"));
+}
+
+#[test]
+fn test_dropped_is_a_failure_reason() {
+    let mut m = Metrics::default();
+    m.record_received("dev-b", "Desktop", 200);
+    m.record_failure(Failure::Dropped);
+    assert_eq!(m.total_received(), 1);
+    assert!(m.render_prometheus().contains("rustclip_failures_total{reason=\"dropped\"} 1\n"));
+}
@@ -0,0 +1,32 @@
+use rust_clip::core::identity::RingIdentity;
+use rust_clip::core::pairing;
+
+const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art";
+
+#[test]
+fn test_payload_roundtrip() {
+    let id = RingIdentity::from_mnemonic(PHRASE).unwrap();
+    let payload = pairing::pairing_payload(&id).unwrap();
+    assert!(payload.starts_with("RUSTCLIP:PAIR:1:"));
+
+    assert_eq!(pairing::parse_pairing_payload(&payload).unwrap().as_str(), PHRASE);
+    let joined = pairing::identity_from_input(&payload).unwrap();
+    assert_eq!(joined.shared_secret, id.shared_secret);
+}
+
+#[test]
+fn test_join_input_accepts_words() {
+    let id = pairing::identity_from_input(&format!("  {}\n", PHRASE)).unwrap();
+    assert_eq!(id.mnemonic, PHRASE);
+    assert!(pairing::identity_from_input("RUSTCLIP:PAIR:1:ZZZZ").is_err());
+}
+
+#[test]
+fn test_qr_render_decode() {
+    let id = RingIdentity::from_mnemonic(PHRASE).unwrap();
+    let payload = pairing::pairing_payload(&id).unwrap();
+
+    let (side, pixels) = pairing::render_qr_pixels(&payload, 4).unwrap();
+    assert_eq!(pixels.len(), side * side);
+    assert_eq!(pairing::decode_qr_luma(side, side, &pixels).unwrap(), payload.as_str());
+}
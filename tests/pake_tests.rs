@@ -0,0 +1,52 @@
+use rust_clip::core::identity::RingIdentity;
+use rust_clip::core::pake;
+use tokio::net::TcpListener;
+
+const PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon art";
+
+#[test]
+fn test_generate_code_format() {
+    let code = pake::generate_code();
+    assert_eq!(code.len(), 6);
+    assert!(code.chars().all(|c| c.is_ascii_digit()));
+}
+
+#[tokio::test]
+async fn test_pairing_with_correct_code() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let inviter = RingIdentity::from_mnemonic(PHRASE).unwrap();
+
+    let server = tokio::spawn(async move {
+        pake::serve_invite(listener, &inviter, "Desktop", "123456").await
+    });
+
+    let (joined, inviter_name) = pake::join_at(addr, "123456").await.unwrap();
+    assert!(server.await.unwrap().is_ok());
+    assert_eq!(joined.mnemonic, PHRASE);
+    assert_eq!(inviter_name, "Desktop");
+}
+
+#[tokio::test]
+async fn test_pairing_with_wrong_code_fails() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let inviter = RingIdentity::from_mnemonic(PHRASE).unwrap();
+
+    let server = tokio::spawn(async move {
+        pake::serve_invite(listener, &inviter, "Desktop", "123456").await
+    });
+
+    assert!(pake::join_at(addr, "654321").await.is_err());
+    assert!(server.await.unwrap().is_err());
+}
+
+#[test]
+fn test_invite_tag_matches_only_its_code() {
+    let tag = pake::invite_tag("123456", "a1b2c3d4e5f60718");
+    assert_eq!(tag.len(), 2);
+    assert_eq!(tag, pake::invite_tag(" 123456\n", "a1b2c3d4e5f60718"));
+    // 8 bit: qualche codice collide, ma la gran parte degli inviti altrui viene scartata
+    let others = (0..100).map(|n| format!("{:06}", n)).filter(|c| pake::invite_tag(c, "a1b2c3d4e5f60718") == tag).count();
+    assert!(others < 10);
+}
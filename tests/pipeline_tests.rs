@@ -0,0 +1,66 @@
+use rust_clip::core::config::PipelineConfig;
+use rust_clip::core::pipeline::*;
+use rust_clip::core::protocol::ClipContent;
+
+fn text(s: &str) -> ClipContent {
+    ClipContent::Text(s.to_string())
+}
+
+#[test]
+fn test_trim_whitespace() {
+    let stage = TrimWhitespace;
+    assert_eq!(stage.apply(text("  hello world \n\n"), Direction::Outgoing), Some(text("hello world")));
+    assert_eq!(stage.apply(text(" \t\n"), Direction::Outgoing), None);
+    let image = ClipContent::Image(vec![1, 2, 3]);
+    assert_eq!(stage.apply(image.clone(), Direction::Incoming), Some(image));
+}
+
+#[test]
+fn test_strip_tracking_params() {
+    let stage = StripTrackingParams::new(vec!["ref".into()]);
+    assert_eq!(
+        stage.apply(text("see https://example.com/a?id=7&utm_source=x&fbclid=abc#top now"), Direction::Outgoing),
+        Some(text("see https://example.com/a?id=7#top now"))
+    );
+    assert_eq!(stage.clean_url("https://shop.io/p?ref=mail&UTM_Medium=y"), "https://shop.io/p");
+    assert_eq!(stage.clean_url("https://example.com/plain"), "https://example.com/plain");
+}
+
+#[test]
+fn test_normalize_line_endings() {
+    let windows = NormalizeLineEndings::with_local_crlf(true);
+    let unix = NormalizeLineEndings::with_local_crlf(false);
+    // Sul filo sempre LF
+    assert_eq!(windows.apply(text("a\r\nb"), Direction::Outgoing), Some(text("a\nb")));
+    // In ricezione il formato del sistema locale
+    assert_eq!(windows.apply(text("a\nb\r\nc"), Direction::Incoming), Some(text("a\r\nb\r\nc")));
+    assert_eq!(unix.apply(text("a\r\nb"), Direction::Incoming), Some(text("a\nb")));
+}
+
+#[test]
+fn test_size_cap() {
+    let stage = SizeCap { max_text_bytes: 5, max_image_bytes: 0 };
+    assert_eq!(stage.apply(text("12345"), Direction::Outgoing), Some(text("12345")));
+    assert_eq!(stage.apply(text("123456"), Direction::Outgoing), None);
+    // 0 = nessun limite
+    assert!(stage.apply(ClipContent::Image(vec![0; 1024]), Direction::Incoming).is_some());
+
+    // Stesso limite usato per le varianti delle immagini ricodificate per un peer
+    let cap = SizeCap::from_config(&PipelineConfig { max_image_kib: 1, ..PipelineConfig::default() });
+    assert!(cap.apply(ClipContent::Image(vec![0; 1024]), Direction::Outgoing).is_some());
+    assert!(cap.apply(ClipContent::Image(vec![0; 1025]), Direction::Outgoing).is_none());
+}
+
+#[test]
+fn test_pipeline_from_config() {
+    let config = PipelineConfig {
+        trim_whitespace: true,
+        strip_tracking: true,
+        max_text_kib: 1,
+        ..PipelineConfig::default()
+    };
+    let pipeline = Pipeline::from_config(&config);
+    assert_eq!(pipeline.run(text("  https://a.io/?utm_x=1 "), Direction::Outgoing), Ok(text("https://a.io/")));
+    assert_eq!(pipeline.run(text("   "), Direction::Outgoing), Err("trim_whitespace"));
+    assert_eq!(pipeline.run(text(&"x".repeat(2048)), Direction::Outgoing), Err("size_cap"));
+}
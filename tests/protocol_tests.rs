@@ -0,0 +1,56 @@
+use rust_clip::core::protocol::{self, ClipContent, ClipMeta, Selection};
+use serde::{Serialize, Deserialize};
+
+#[test]
+fn test_clip_roundtrip_with_meta() {
+    let meta = ClipMeta { expire_secs: Some(30), sensitive: true, selection: Selection::Primary, sender: Some("dev-a".into()) };
+    let data = protocol::encode_clip(&ClipContent::Text("hello".into()), &meta).unwrap();
+    let (content, decoded) = protocol::decode_clip(&data).unwrap();
+    assert_eq!(content, ClipContent::Text("hello".into()));
+    assert_eq!(decoded, meta);
+}
+
+#[test]
+fn test_legacy_peers_compatibility() {
+    // Pacchetto di un peer vecchio: solo il contenuto, senza metadati
+    let legacy = bincode::serialize(&ClipContent::Image(vec![1, 2, 3])).unwrap();
+    let (content, meta) = protocol::decode_clip(&legacy).unwrap();
+    assert_eq!(content, ClipContent::Image(vec![1, 2, 3]));
+    assert_eq!(meta, ClipMeta::default());
+
+    // Un peer vecchio legge il nostro pacchetto ignorando i metadati in coda
+    let data = protocol::encode_clip(&ClipContent::Text("hi".into()), &ClipMeta { expire_secs: Some(5), sensitive: true, ..ClipMeta::default() }).unwrap();
+    let content: ClipContent = bincode::deserialize(&data).unwrap();
+    assert_eq!(content, ClipContent::Text("hi".into()));
+}
+
+#[test]
+fn test_meta_blocks_from_older_versions() {
+    // Metadati come li scrivevano le versioni con scadenza ma senza selezione
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct OldMeta { expire_secs: Option<u32>, sensitive: bool }
+
+    let mut old = bincode::serialize(&ClipContent::Text("pw".into())).unwrap();
+    old.extend_from_slice(&bincode::serialize(&OldMeta { expire_secs: Some(20), sensitive: true }).unwrap());
+    let (_, meta) = protocol::decode_clip(&old).unwrap();
+    assert_eq!(meta, ClipMeta { expire_secs: Some(20), sensitive: true, ..ClipMeta::default() });
+
+    // E quelle versioni leggono i nostri metadati ignorando i blocchi nuovi
+    let meta = ClipMeta { expire_secs: Some(20), sensitive: true, selection: Selection::Primary, sender: Some("dev-a".into()) };
+    let data = protocol::encode_clip(&ClipContent::Text("pw".into()), &meta).unwrap();
+    let mut reader = data.as_slice();
+    let _: ClipContent = bincode::deserialize_from(&mut reader).unwrap();
+    let old_meta: OldMeta = bincode::deserialize(reader).unwrap();
+    assert_eq!(old_meta, OldMeta { expire_secs: Some(20), sensitive: true });
+}
+
+#[test]
+fn test_sender_survives_missing_blocks() {
+    // Versioni con selezione ma senza mittente: il clip arriva con sender None
+    let mut data = bincode::serialize(&ClipContent::Text("hi".into())).unwrap();
+    data.extend_from_slice(&bincode::serialize(&(Some(5u32), false)).unwrap());
+    data.extend_from_slice(&bincode::serialize(&Selection::Primary).unwrap());
+    let (_, meta) = protocol::decode_clip(&data).unwrap();
+    assert_eq!(meta.selection, Selection::Primary);
+    assert_eq!(meta.sender, None);
+}
@@ -0,0 +1,73 @@
+use rust_clip::core::roster::{self, Roster};
+use rust_clip::events::PeerInfo;
+use std::net::{SocketAddr, IpAddr, Ipv4Addr};
+use std::time::SystemTime;
+
+fn peer(ring_id: &str, device_id: &str, name: &str) -> PeerInfo {
+    PeerInfo {
+        name: name.to_string(),
+        ip: SocketAddr::new(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20)), 5566),
+        device_id: device_id.to_string(),
+        last_seen: SystemTime::now(),
+        image_pref: None,
+        compression: Vec::new(),
+        primary_selection: false,
+        ring_id: ring_id.to_string(),
+        os: Some("linux".into()),
+        app_version: Some("0.1.0".into()),
+    }
+}
+
+#[test]
+fn test_roster_tracks_first_and_last_seen() {
+    let mut roster = Roster::default();
+    let laptop = peer("personal", "a1b2c3d4", "Laptop");
+
+    assert!(roster.observe(&laptop, 1_000));
+    // Riannuncio poco dopo: niente da salvare
+    assert!(!roster.observe(&laptop, 1_010));
+    assert!(roster.observe(&laptop, 1_100));
+
+    let entry = roster.ring("personal").next().unwrap();
+    assert_eq!((entry.first_seen, entry.last_seen), (1_000, 1_100));
+    assert_eq!(entry.os.as_deref(), Some("linux"));
+
+    // Un cambio di nome va salvato subito
+    assert!(roster.observe(&peer("personal", "a1b2c3d4", "Work Laptop"), 1_101));
+    assert_eq!(roster.ring("personal").next().unwrap().name, "Work Laptop");
+}
+
+#[test]
+fn test_roster_keeps_rings_apart() {
+    let mut roster = Roster::default();
+    roster.observe(&peer("personal", "a1b2c3d4", "Laptop"), 1_000);
+    roster.observe(&peer("team", "a1b2c3d4", "Laptop"), 2_000);
+    assert_eq!(roster.devices.len(), 2);
+    assert_eq!(roster.ring("team").next().unwrap().first_seen, 2_000);
+
+    assert!(roster.forget("team", "a1b2c3d4"));
+    assert!(!roster.forget("team", "a1b2c3d4"));
+    assert_eq!(roster.ring("team").count(), 0);
+    assert_eq!(roster.ring("personal").count(), 1);
+}
+
+#[test]
+fn test_device_code_groups() {
+    assert_eq!(roster::device_code("a1b2c3d4e5f6"), "a1b2 c3d4 e5f6");
+}
+
+#[test]
+fn test_legacy_peer_rules_are_reported() {
+    use rust_clip::core::config::{AppConfig, ApprovalPolicy, SyncDirection};
+
+    let full = "0123456789abcdef0123456789abcdef";
+    let mut config = AppConfig::default();
+    config.direction.peers.insert(full.into(), SyncDirection::SendOnly);
+    assert!(config.legacy_peer_rules().is_empty());
+
+    // ID a 4 cifre di una versione precedente, anche se presente in entrambe le tabelle
+    config.direction.peers.insert("a1b2".into(), SyncDirection::SendOnly);
+    config.approval.peers.insert("a1b2".into(), ApprovalPolicy::Ask);
+    config.approval.peers.insert("ffee".into(), ApprovalPolicy::Ask);
+    assert_eq!(config.legacy_peer_rules(), vec!["a1b2", "ffee"]);
+}
@@ -0,0 +1,31 @@
+use rust_clip::core::config::AppRules;
+use rust_clip::core::source::{self, SourceApp};
+
+fn keepass() -> SourceApp {
+    SourceApp::new(vec!["keepassxc".into(), "KeePassXC".into()])
+}
+
+fn terminal() -> SourceApp {
+    SourceApp::new(vec!["gnome-terminal-server".into(), "Gnome-terminal".into()])
+}
+
+#[test]
+fn test_deny_list() {
+    let rules = AppRules { deny: vec!["KeePass".into()], allow: vec![] };
+    assert!(!source::is_allowed(&rules, Some(&keepass())));
+    assert!(source::is_allowed(&rules, Some(&terminal())));
+    // Senza allow-list un'app non rilevabile passa
+    assert!(source::is_allowed(&rules, None));
+}
+
+#[test]
+fn test_allow_list() {
+    let rules = AppRules { deny: vec![], allow: vec!["terminal".into()] };
+    assert!(source::is_allowed(&rules, Some(&terminal())));
+    assert!(!source::is_allowed(&rules, Some(&keepass())));
+    assert!(!source::is_allowed(&rules, None));
+
+    // Deny vince sempre
+    let rules = AppRules { deny: vec!["gnome".into()], allow: vec!["terminal".into()] };
+    assert!(!source::is_allowed(&rules, Some(&terminal())));
+}
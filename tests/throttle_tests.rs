@@ -0,0 +1,57 @@
+use rust_clip::core::throttle::{Debouncer, RateLimiter};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_debouncer_waits_for_stable_value() {
+    let start = Instant::now();
+    let ms = |n| start + Duration::from_millis(n);
+    let mut debounce = Debouncer::new(Duration::from_millis(500));
+
+    // Una selezione trascinata cambia di continuo: nulla parte
+    debounce.observe("a", ms(0));
+    debounce.observe("ab", ms(200));
+    debounce.observe("abc", ms(400));
+    assert_eq!(debounce.ready(ms(600)), None);
+
+    // Ferma da abbastanza tempo: parte una volta sola
+    debounce.observe("abc", ms(800));
+    assert_eq!(debounce.ready(ms(900)), Some("abc"));
+    assert_eq!(debounce.ready(ms(2000)), None);
+
+    // Un nuovo valore fa ripartire l'attesa
+    debounce.observe("xyz", ms(2000));
+    assert_eq!(debounce.ready(ms(2100)), None);
+    assert_eq!(debounce.ready(ms(2500)), Some("xyz"));
+}
+
+#[test]
+fn test_rate_limiter_per_peer() {
+    let start = Instant::now();
+    let secs = |n| start + Duration::from_secs(n);
+    let mut limiter = RateLimiter::new(3, Duration::from_secs(60));
+
+    for t in 0..3 {
+        assert!(limiter.allow("flood", secs(t)));
+    }
+    assert!(!limiter.allow("flood", secs(10)));
+    // Gli altri peer hanno un conteggio separato
+    assert!(limiter.allow("calm", secs(10)));
+
+    // Passata la finestra del primo clip, se ne libera un posto
+    assert!(limiter.allow("flood", secs(60)));
+    assert!(!limiter.allow("flood", secs(60)));
+
+    // 0 = nessun limite
+    limiter.set_limit(0);
+    assert!((0..100).all(|_| limiter.allow("flood", secs(61))));
+}
+
+#[test]
+fn test_address_limit_is_looser_than_peer_limit() {
+    use rust_clip::core::throttle::address_limit;
+
+    // Più peer dietro lo stesso NAT restano ognuno entro il proprio limite
+    assert!(address_limit(60) >= 60 * 2);
+    assert_eq!(address_limit(0), 0);
+    assert_eq!(address_limit(u32::MAX), u32::MAX);
+}
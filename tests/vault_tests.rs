@@ -0,0 +1,30 @@
+use rust_clip::core::vault::{self, KdfParams};
+
+// Parametri leggeri: in debug Argon2 con 64 MiB è molto lento
+const FAST: KdfParams = KdfParams { m_cost_kib: 64, t_cost: 1, p_cost: 1 };
+const MAGIC: &[u8; 4] = b"TEST";
+
+#[test]
+fn test_seal_open_roundtrip() {
+    let sealed = vault::seal(MAGIC, b"secret mnemonic", "correct horse", FAST).unwrap();
+    assert!(vault::has_magic(MAGIC, &sealed));
+
+    let opened = vault::open(MAGIC, &sealed, "correct horse").unwrap();
+    assert_eq!(opened, b"secret mnemonic");
+}
+
+#[test]
+fn test_wrong_passphrase_fails() {
+    let sealed = vault::seal(MAGIC, b"secret mnemonic", "correct horse", FAST).unwrap();
+    assert!(vault::open(MAGIC, &sealed, "battery staple").is_err());
+}
+
+#[test]
+fn test_wrong_magic_or_tampering_fails() {
+    let mut sealed = vault::seal(MAGIC, b"secret mnemonic", "pw", FAST).unwrap();
+    assert!(vault::open(b"XXXX", &sealed, "pw").is_err());
+
+    let last = sealed.len() - 1;
+    sealed[last] ^= 0x01;
+    assert!(vault::open(MAGIC, &sealed, "pw").is_err());
+}